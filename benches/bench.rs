@@ -4,6 +4,9 @@
  * obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+//! To compare 16 by 16 leaves with 64 by 64 leaves, run `cargo bench` followed by
+//! `cargo bench --features leaf64`. Criterion reports the change from the first run.
+
 #[macro_use]
 extern crate criterion;
 
@@ -71,24 +74,91 @@ o$6b2o4b3o4b2o$6b2o3bo3bo3b2o$5b3o4b3o4b3o$3b2o17b2o$2bo5b2o2bobo2b2o
     .unwrap()
 }
 
+/// A 256 by 256 pseudo-random soup, which spends much more time in the base case than a single
+/// spaceship.
+fn create_soup() -> smeagol::Life {
+    let mut state: u64 = 0x853c_49e6_748f_ea9b;
+    let mut life = smeagol::Life::new();
+    for y in 0..256 {
+        for x in 0..256 {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            if state >> 62 == 0 {
+                life.set_cell_alive(smeagol::Position::new(x, y));
+            }
+        }
+    }
+    life
+}
+
+/// Returns every node with the given nodes as its four children.
+fn combine_nodes(
+    store: &mut smeagol::node::Store,
+    children: &[smeagol::node::NodeId],
+) -> Vec<smeagol::node::NodeId> {
+    let mut nodes = Vec::with_capacity(children.len().pow(4));
+    for &nw in children {
+        for &ne in children {
+            for &sw in children {
+                for &se in children {
+                    nodes.push(store.create_interior(smeagol::node::NodeTemplate {
+                        nw,
+                        ne,
+                        sw,
+                        se,
+                    }));
+                }
+            }
+        }
+    }
+    nodes
+}
+
+/// Creates 65536 distinct nodes two levels above the leaves, from an empty leaf and a leaf with a
+/// single alive cell.
+fn create_nodes(store: &mut smeagol::node::Store) -> Vec<smeagol::node::NodeId> {
+    let empty = store.create_empty(smeagol::node::LEAF_LEVEL);
+    let alive = empty.set_cell_alive(store, smeagol::Position::new(0, 0));
+    let children = combine_nodes(store, &[empty, alive]);
+    combine_nodes(store, &children)
+}
+
+fn bench_create_nodes(c: &mut criterion::Criterion) {
+    c.bench_function("create nodes", |b| {
+        b.iter(|| create_nodes(&mut smeagol::node::Store::new()))
+    });
+}
+
+fn bench_find_nodes(c: &mut criterion::Criterion) {
+    // every node already exists, so creating them again only looks them up
+    let mut store = smeagol::node::Store::new();
+    create_nodes(&mut store);
+    c.bench_function("find nodes", move |b| b.iter(|| create_nodes(&mut store)));
+}
+
 fn bench_create_glider(c: &mut criterion::Criterion) {
-    c.bench_function("create glider", |b| b.iter(|| create_glider()));
+    c.bench_function("create glider", |b| b.iter(create_glider));
 }
 
 fn bench_create_lobster(c: &mut criterion::Criterion) {
-    c.bench_function("create lobster", |b| b.iter(|| create_lobster()));
+    c.bench_function("create lobster", |b| b.iter(create_lobster));
 }
 
 fn bench_create_sir_robin(c: &mut criterion::Criterion) {
-    c.bench_function("create sir robin", |b| b.iter(|| create_sir_robin()));
+    c.bench_function("create sir robin", |b| b.iter(create_sir_robin));
 }
 
 fn bench_create_spaghetti_monster(c: &mut criterion::Criterion) {
     c.bench_function("create spaghetti monster", |b| {
-        b.iter(|| create_spaghetti_monster())
+        b.iter(create_spaghetti_monster)
     });
 }
 
+fn bench_create_soup(c: &mut criterion::Criterion) {
+    c.bench_function("create soup", |b| b.iter(create_soup));
+}
+
 fn bench_step_glider_1(c: &mut criterion::Criterion) {
     let life = create_glider();
     c.bench_function("step glider 1", move |b| {
@@ -129,6 +199,16 @@ fn bench_step_spaghetti_monster_1(c: &mut criterion::Criterion) {
     });
 }
 
+fn bench_step_soup_1(c: &mut criterion::Criterion) {
+    let life = create_soup();
+    c.bench_function("step soup 1", move |b| {
+        b.iter(|| {
+            let mut life = life.clone();
+            life.step();
+        })
+    });
+}
+
 fn bench_step_glider_1024(c: &mut criterion::Criterion) {
     let mut life = create_glider();
     life.set_step_log_2(10);
@@ -173,19 +253,35 @@ fn bench_step_spaghetti_monster_1024(c: &mut criterion::Criterion) {
     });
 }
 
+fn bench_step_soup_1024(c: &mut criterion::Criterion) {
+    let mut life = create_soup();
+    life.set_step_log_2(10);
+    c.bench_function("step soup 1024", move |b| {
+        b.iter(|| {
+            let mut life = life.clone();
+            life.step();
+        })
+    });
+}
+
 criterion_group!(
     benches,
     bench_create_glider,
     bench_create_lobster,
     bench_create_sir_robin,
     bench_create_spaghetti_monster,
+    bench_create_soup,
+    bench_create_nodes,
+    bench_find_nodes,
     bench_step_glider_1,
     bench_step_lobster_1,
     bench_step_sir_robin_1,
     bench_step_spaghetti_monster_1,
+    bench_step_soup_1,
     bench_step_glider_1024,
     bench_step_lobster_1024,
     bench_step_sir_robin_1024,
     bench_step_spaghetti_monster_1024,
+    bench_step_soup_1024,
 );
 criterion_main!(benches);
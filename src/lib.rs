@@ -30,18 +30,27 @@
 //! # Ok(())
 //! # }
 //! ```
+// `failure_derive` generates impls inside anonymous constants
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate failure;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "simd")]
 #[macro_use]
 extern crate packed_simd;
 
 mod life;
 pub mod node;
 pub mod parse;
+pub mod rules;
 
-pub use crate::life::Life;
+pub use crate::life::{
+    Axis, Census, CensusObject, Direction, Emission, EmissionSchedule, Envelope, Growth, Life,
+    ObjectKind, OscillatorAnalysis, Periodicity, Sample, Spaceship, Stabilization, Symmetry,
+    SymmetryElement, TimeSeries, Topology, TopologyError, Transformation, Twist,
+};
 use crate::{node::Quadrant, parse::rle::RleError};
 
 /// An error that can occur.
@@ -57,6 +66,7 @@ pub enum Error {
 
 /// A cell in a Life grid.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Cell {
     /// An alive cell.
     Alive,
@@ -99,6 +109,7 @@ impl Cell {
 
 /// The position of a cell in a Life grid.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     /// The x coordinate.
     pub x: i64,
@@ -167,6 +178,8 @@ impl Position {
 
 /// A rectangular region of a Life grid.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "BoundingBoxCorners"))]
 pub struct BoundingBox {
     upper_left: Position,
     lower_right: Position,
@@ -281,6 +294,34 @@ impl BoundingBox {
     }
 }
 
+/// The serialized form of a bounding box, which is checked before it becomes a `BoundingBox`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct BoundingBoxCorners {
+    upper_left: Position,
+    lower_right: Position,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<BoundingBoxCorners> for BoundingBox {
+    type Error = &'static str;
+
+    fn try_from(corners: BoundingBoxCorners) -> Result<Self, Self::Error> {
+        let BoundingBoxCorners {
+            upper_left,
+            lower_right,
+        } = corners;
+        if upper_left.x <= lower_right.x && upper_left.y <= lower_right.y {
+            Ok(Self {
+                upper_left,
+                lower_right,
+            })
+        } else {
+            Err("upper left corner is below or to the right of the lower right corner")
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,4 +334,27 @@ mod tests {
         assert!(alive.is_alive());
         assert!(!dead.is_alive());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let bounding_box = BoundingBox::new(Position::new(-1, 2), Position::new(3, 4));
+        let json = serde_json::to_string(&bounding_box).unwrap();
+        assert_eq!(
+            json,
+            r#"{"upper_left":{"x":-1,"y":2},"lower_right":{"x":3,"y":4}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<BoundingBox>(&json).unwrap(),
+            bounding_box
+        );
+        assert!(serde_json::from_str::<BoundingBox>(
+            r#"{"upper_left":{"x":3,"y":2},"lower_right":{"x":-1,"y":4}}"#
+        )
+        .is_err());
+
+        let cells = vec![Cell::Alive, Cell::Dead];
+        let bytes = bincode::serialize(&cells).unwrap();
+        assert_eq!(bincode::deserialize::<Vec<Cell>>(&bytes).unwrap(), cells);
+    }
 }
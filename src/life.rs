@@ -4,27 +4,72 @@
  * obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+mod apgcode;
+mod census;
+mod compare;
+mod emission;
+mod envelope;
+mod history;
+mod origin;
+mod oscillator;
+mod period;
+mod record;
 mod render;
-
+#[cfg(feature = "serde")]
+mod serialize;
+mod stable;
+mod state;
+mod symmetry;
+mod topology;
+
+pub use self::census::{Census, CensusObject, ObjectKind};
+pub use self::emission::{Emission, EmissionSchedule};
+pub use self::envelope::Envelope;
+use self::history::History;
+pub use self::oscillator::OscillatorAnalysis;
+pub use self::period::{Direction, Periodicity, Spaceship};
+pub use self::record::{Growth, Sample, TimeSeries};
+pub use self::stable::Stabilization;
+pub use self::symmetry::{Axis, Symmetry, SymmetryElement, Transformation};
+pub use self::topology::{Topology, TopologyError, Twist};
 use crate::{
-    node::{Level, NodeId, Store},
+    node::{Level, NodeId, Store, StoreStats, LEAF_LEVEL},
     parse::rle::Rle,
     BoundingBox, Position,
 };
 
-const INITIAL_LEVEL: Level = Level(7);
+const INITIAL_LEVEL: Level = Level(LEAF_LEVEL.0 + 3);
+
+/// The largest step size log 2, since the step size is a `u64`.
+const MAX_STEP_LOG_2: u8 = 63;
 
 /// Conway's Game of Life.
 #[derive(Clone, Debug)]
 pub struct Life {
     /// The root node of the Life grid.
     root: NodeId,
+    /// The position of the center of the root node.
+    origin: Position,
     /// The store.
     store: Store,
     /// How many generations the Life grid has been advanced.
     generation: u128,
     /// A bounding box containing all alive cells.
     bounding_box: Option<BoundingBox>,
+    /// The topology of the Life grid.
+    topology: Topology,
+    /// The step size log 2.
+    ///
+    /// This can be larger than the step size log 2 of the store, since bounded grids are advanced
+    /// in several smaller steps.
+    step_log_2: u8,
+    /// Whether to evolve the Life grid on multiple threads.
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+    /// Past and undone states of the Life grid.
+    history: History,
+    /// The samples recorded as the Life grid is stepped, if recording.
+    recorder: Option<TimeSeries>,
 }
 
 impl Life {
@@ -41,9 +86,16 @@ impl Life {
         let root = store.create_empty(INITIAL_LEVEL);
         Self {
             root,
+            origin: Position::new(0, 0),
             store,
             generation: 0,
             bounding_box: None,
+            topology: Topology::Plane,
+            step_log_2: 0,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            history: History::new(),
+            recorder: None,
         }
     }
 
@@ -83,6 +135,8 @@ impl Life {
     }
 
     /// Creates a Life grid from the given RLE struct.
+    ///
+    /// The Life grid has the topology given in the rule of the RLE file, if any.
     pub fn from_rle(rle: &Rle) -> Self {
         let alive_cells = rle
             .alive_cells()
@@ -110,16 +164,29 @@ impl Life {
             root = root.set_cells_alive(&mut store, alive_cells);
         }
 
-        Self {
+        let mut life = Self {
             bounding_box: root.bounding_box(&store),
             root,
+            origin: Position::new(0, 0),
             store,
             generation: 0,
-        }
+            topology: Topology::Plane,
+            step_log_2: 0,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            history: History::new(),
+            recorder: None,
+        };
+        // parsed topologies always have cells
+        life.apply_topology(rle.topology());
+        life
     }
 
     /// Sets the cell at the given position in the Life grid to be an alive cell.
     ///
+    /// Positions outside a torus or Klein bottle are wrapped around onto the grid, and positions
+    /// outside a bounded plane are ignored.
+    ///
     /// # Examples
     ///
     /// ```
@@ -134,15 +201,15 @@ impl Life {
     /// assert_eq!(life.population(), 4);
     /// ```
     pub fn set_cell_alive(&mut self, position: Position) {
-        while position.x < self.root.min_coord(&self.store)
-            || position.y < self.root.min_coord(&self.store)
-            || position.x > self.root.max_coord(&self.store)
-            || position.y > self.root.max_coord(&self.store)
-        {
-            self.root = self.root.expand(&mut self.store);
-        }
+        let position = match self.topology.wrap(position) {
+            Some(position) => position,
+            None => return,
+        };
+        self.expand_to_fit(BoundingBox::new(position, position));
+        let position = self.to_root(position);
         self.root = self.root.set_cell_alive(&mut self.store, position);
-        self.bounding_box = self.root.bounding_box(&self.store);
+        self.update_bounding_box();
+        self.truncate_history();
     }
 
     /// Returns a list of the positions of the alive cells in the Life grid.
@@ -159,7 +226,14 @@ impl Life {
     /// # }
     /// ```
     pub fn get_alive_cells(&self) -> Vec<Position> {
-        self.root.get_alive_cells(&self.store)
+        let origin = self.origin;
+        let mut alive_cells = self.root.get_alive_cells(&self.store);
+        if origin != Position::new(0, 0) {
+            for position in &mut alive_cells {
+                *position = position.offset(origin.x, origin.y);
+            }
+        }
+        alive_cells
     }
 
     /// Returns true if the given bounding box contains any alive cells.
@@ -178,6 +252,7 @@ impl Life {
     pub fn contains_alive_cells(&self, bounding_box: BoundingBox) -> bool {
         if let Some(self_bbox) = self.bounding_box {
             if let Some(intersect) = self_bbox.intersect(bounding_box) {
+                let intersect = intersect.offset(-self.origin.x, -self.origin.y);
                 self.root.contains_alive_cells(&self.store, intersect)
             } else {
                 false
@@ -241,19 +316,171 @@ impl Life {
     ///
     /// The default step size is 1.
     pub fn step_size(&self) -> u64 {
-        1 << self.store.step_log_2()
+        1 << self.step_log_2
     }
 
     /// Returns the step size log 2.
     pub fn step_log_2(&self) -> u8 {
-        self.store.step_log_2()
+        self.step_log_2
     }
 
     /// Sets the step size to be `2^step_log_2`.
     ///
+    /// The step size is a `u64`, so `step_log_2` is clamped to at most 63.
+    ///
     /// This clears the cache of previously computed steps.
     pub fn set_step_log_2(&mut self, step_log_2: u8) {
-        self.store.set_step_log_2(step_log_2);
+        let step_log_2 = step_log_2.min(MAX_STEP_LOG_2);
+        self.step_log_2 = step_log_2;
+        self.store
+            .set_step_log_2(step_log_2.min(max_step_log_2(self.topology)));
+    }
+
+    /// Returns true if the Life grid is evolved on multiple threads.
+    ///
+    /// Requires the `parallel` feature.
+    ///
+    /// Life grids are evolved on a single thread by default.
+    #[cfg(feature = "parallel")]
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Sets whether to evolve the Life grid on multiple threads.
+    ///
+    /// Parallel evolution uses the threads of the current rayon thread pool and gives exactly the
+    /// same results as evolution on a single thread. Requires the `parallel` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_file("./assets/gosperglidergun.rle")?;
+    /// life.set_parallel(true);
+    /// life.set_step_log_2(10);
+    /// life.step();
+    /// assert_eq!(life.generation(), 1024);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Returns statistics about the nodes and evolution results stored for the Life grid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_file("./assets/gosperglidergun.rle")?;
+    /// life.set_step_log_2(6);
+    /// life.step();
+    ///
+    /// let stats = life.stats();
+    /// assert!(stats.step_misses > 0);
+    /// assert!(stats.memory_usage > 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> StoreStats {
+        self.store.stats()
+    }
+
+    /// Returns the memory limit of the Life grid in bytes, if any.
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.store.memory_limit()
+    }
+
+    /// Sets the maximum number of bytes the store of the Life grid should allocate, or removes the limit.
+    ///
+    /// After every step, nodes that are no longer needed and the least recently used evolution
+    /// results are dropped until the Life grid is back under the limit. A limit that is too small
+    /// slows the simulation down, since dropped results have to be recalculated, but doesn't
+    /// change the results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_file("./assets/gosperglidergun.rle")?;
+    /// life.set_step_log_2(10);
+    ///
+    /// let mut limited = life.clone();
+    /// limited.set_memory_limit(Some(1 << 16));
+    ///
+    /// life.step();
+    /// limited.step();
+    /// assert_eq!(limited.get_alive_cells(), life.get_alive_cells());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.store.set_memory_limit(memory_limit);
+        self.evict();
+    }
+
+    /// Returns the topology of the Life grid.
+    ///
+    /// The default topology is an unbounded plane.
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Sets the topology of the Life grid.
+    ///
+    /// Alive cells outside a torus or Klein bottle are wrapped around onto the grid, and alive
+    /// cells outside a bounded plane are removed.
+    ///
+    /// Returns an error, leaving the Life grid unchanged, if the grid of the topology has a width
+    /// or height of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // glider
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// life.set_topology("T8,8".parse()?)?;
+    ///
+    /// // the glider returns to where it started after travelling once around the torus
+    /// let cells = life.get_alive_cells();
+    /// life.set_step_log_2(5);
+    /// life.step();
+    /// assert_eq!(life.get_alive_cells(), cells);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_topology(&mut self, topology: Topology) -> Result<(), TopologyError> {
+        if !topology.has_cells() {
+            return Err(TopologyError::Empty { topology });
+        }
+        self.apply_topology(topology);
+        Ok(())
+    }
+
+    /// Sets a topology whose grid is known to have cells.
+    fn apply_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.clear_history();
+
+        if let Some(grid) = topology.bounding_box() {
+            let alive_cells = self
+                .get_alive_cells()
+                .into_iter()
+                .filter_map(|pos| topology.wrap(pos))
+                .collect::<Vec<_>>();
+            self.root = self.store.create_empty(INITIAL_LEVEL);
+            self.origin = Position::new(0, 0);
+            self.expand_to_fit(grid);
+            let alive_cells = alive_cells.into_iter().map(|pos| self.to_root(pos));
+            let alive_cells = alive_cells.collect::<Vec<_>>();
+            self.root = self.root.set_cells_alive(&mut self.store, alive_cells);
+            self.update_bounding_box();
+        }
+
+        self.set_step_log_2(self.step_log_2);
     }
 
     /// Pads the Life grid such that it can be advanced into the future without the edges of the
@@ -290,7 +517,7 @@ impl Life {
                     .ne(&self.store)
                     .population(&self.store)
         {
-            self.root = self.root.expand(&mut self.store);
+            self.expand();
         }
     }
 
@@ -313,10 +540,154 @@ impl Life {
     /// # }
     /// ```
     pub fn step(&mut self) {
+        self.record_history();
+        self.advance();
+        self.record_sample();
+    }
+
+    /// Advances the Life grid by the step size without saving anything in the history.
+    fn advance(&mut self) {
+        if let Some(grid) = self.topology.bounding_box() {
+            for _ in 0..1u64 << (self.step_log_2 - self.store.step_log_2()) {
+                self.step_bounded(grid);
+                self.evict();
+            }
+        } else {
+            self.pad();
+            self.step_root();
+            self.update_bounding_box();
+            self.shrink();
+            self.evict();
+        }
+        self.generation += u128::from(self.step_size());
+        self.update_bounding_box();
+    }
+
+    /// Advances a bounded Life grid by the step size of the store.
+    ///
+    /// The grid is surrounded by a border as wide as the step size, containing copies of the cells
+    /// on the opposite edges of a torus or Klein bottle and nothing for a bounded plane. After
+    /// stepping, everything outside the grid is removed again.
+    fn step_bounded(&mut self, grid: BoundingBox) {
+        let distance = 1 << self.store.step_log_2();
+        let border_cells = self.border_cells(grid, distance);
+
+        self.expand_to_fit(grid.pad(distance));
+        let border_cells = border_cells.into_iter().map(|pos| self.to_root(pos));
+        let border_cells = border_cells.collect::<Vec<_>>();
+        self.root = self.root.set_cells_alive(&mut self.store, border_cells);
         self.pad();
+        self.step_root();
+        let root_grid = grid.offset(-self.origin.x, -self.origin.y);
+        self.root = self.root.crop(&mut self.store, root_grid);
+    }
+
+    /// Returns the copies of the alive cells of a torus or Klein bottle that lie within `distance`
+    /// of the edges of its grid.
+    ///
+    /// Only the strips along the edges of the grid that are copied are visited, by cropping the
+    /// root node to each strip.
+    fn border_cells(&mut self, grid: BoundingBox, distance: i64) -> Vec<Position> {
+        let (width, height, twist) = match self.topology {
+            Topology::Torus { width, height } => (i64::from(width), i64::from(height), None),
+            Topology::KleinBottle {
+                width,
+                height,
+                twist,
+            } => (i64::from(width), i64::from(height), Some(twist)),
+            Topology::Plane | Topology::BoundedPlane { .. } => return vec![],
+        };
+        let border = grid.pad(distance);
+
+        let mut border_cells = vec![];
+        for tile_y in -1..=1 {
+            for tile_x in -1..=1 {
+                if tile_x == 0 && tile_y == 0 {
+                    continue;
+                }
+
+                // the part of the border covered by this copy of the grid
+                let tile = grid.offset(tile_x * width, tile_y * height);
+                let target = border.intersect(tile).unwrap();
+
+                // the cells of the grid copied there, mirrored if the edges are joined with a twist
+                let flip_x = twist == Some(Twist::Horizontal) && tile_y != 0;
+                let flip_y = twist == Some(Twist::Vertical) && tile_x != 0;
+                let copy = |pos: Position| {
+                    let x = if flip_x { width - 1 - pos.x } else { pos.x };
+                    let y = if flip_y { height - 1 - pos.y } else { pos.y };
+                    Position::new(x + tile_x * width, y + tile_y * height)
+                };
+                let uncopy = |pos: Position| {
+                    let pos = pos.offset(-tile_x * width, -tile_y * height);
+                    let x = if flip_x { width - 1 - pos.x } else { pos.x };
+                    let y = if flip_y { height - 1 - pos.y } else { pos.y };
+                    Position::new(x, y)
+                };
+                let source =
+                    BoundingBox::new(uncopy(target.upper_left), uncopy(target.upper_left)).combine(
+                        BoundingBox::new(uncopy(target.lower_right), uncopy(target.lower_right)),
+                    );
+
+                let origin = self.origin;
+                let root_source = source.offset(-origin.x, -origin.y);
+                if !self.root.contains_alive_cells(&self.store, root_source) {
+                    continue;
+                }
+                let strip = self.root.crop(&mut self.store, root_source);
+                border_cells.extend(
+                    strip
+                        .get_alive_cells(&self.store)
+                        .into_iter()
+                        .map(|pos| copy(pos.offset(origin.x, origin.y))),
+                );
+            }
+        }
+        border_cells
+    }
+
+    /// Brings the store back under its memory limit, if any, and frees node indices once half of
+    /// them are taken.
+    ///
+    /// Every state saved in the history is kept.
+    fn evict(&mut self) {
+        let mut roots = vec![self.root];
+        roots.extend(self.history.roots());
+        let roots = self.store.evict(&roots);
+        self.root = roots[0];
+        self.history.set_roots(&roots[1..]);
+    }
+
+    /// Advances the root node by the step size of the store.
+    #[cfg(feature = "parallel")]
+    fn step_root(&mut self) {
+        self.root = if self.parallel {
+            self.root.par_step(&mut self.store)
+        } else {
+            self.root.step(&mut self.store)
+        };
+    }
+
+    /// Advances the root node by the step size of the store.
+    #[cfg(not(feature = "parallel"))]
+    fn step_root(&mut self) {
         self.root = self.root.step(&mut self.store);
-        self.generation += u128::from(self.step_size());
-        self.bounding_box = self.root.bounding_box(&self.store);
+    }
+}
+
+/// Returns the largest step size log 2 that a Life grid with the given topology can be advanced
+/// by in a single step.
+///
+/// Wrapped grids are limited by their smallest dimension, since the border of copied cells must not
+/// be wider than the grid, and bounded planes must be cropped after every generation. The grid
+/// must have cells, as checked by `set_topology`.
+fn max_step_log_2(topology: Topology) -> u8 {
+    match topology {
+        Topology::Plane => u8::MAX,
+        Topology::BoundedPlane { .. } => 0,
+        Topology::Torus { width, height } | Topology::KleinBottle { width, height, .. } => {
+            (31 - width.min(height).leading_zeros()) as u8
+        }
     }
 }
 
@@ -346,12 +717,16 @@ mod tests {
         life.set_step_log_2(10);
         assert_eq!(life.step_log_2(), 10);
         assert_eq!(life.step_size(), 1024);
+
+        life.set_step_log_2(u8::MAX);
+        assert_eq!(life.step_log_2(), 63);
+        assert_eq!(life.step_size(), 1 << 63);
     }
 
     #[test]
     fn empty() {
-        let min = i64::min_value();
-        let max = i64::max_value();
+        let min = i64::MIN;
+        let max = i64::MAX;
         let life = Life::new();
         assert_eq!(life.bounding_box(), None);
         assert!(!life.contains_alive_cells(BoundingBox::new(
@@ -372,12 +747,177 @@ mod tests {
         assert_eq!(life.population(), 5);
     }
 
+    #[test]
+    fn memory_limit() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_step_log_2(4);
+        let mut limited = life.clone();
+        limited.set_memory_limit(Some(1 << 14));
+        assert_eq!(limited.memory_limit(), Some(1 << 14));
+
+        for _ in 0..20 {
+            life.step();
+            limited.step();
+            assert_eq!(limited.get_alive_cells(), life.get_alive_cells());
+            assert!(limited.store.len() <= life.store.len());
+        }
+    }
+
+    #[test]
+    fn torus() {
+        for &step_log_2 in &[0, 3, 6] {
+            let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+            life.set_topology(Topology::Torus {
+                width: 16,
+                height: 16,
+            })
+            .unwrap();
+            life.set_step_log_2(step_log_2);
+
+            // a glider travels once around the torus in 64 generations
+            let cells = life.get_alive_cells();
+            while life.generation() < 64 {
+                life.step();
+                assert_eq!(life.population(), 5);
+            }
+            assert_eq!(life.get_alive_cells(), cells);
+        }
+    }
+
+    #[test]
+    fn klein_bottle() {
+        let topology = Topology::KleinBottle {
+            width: 8,
+            height: 6,
+            twist: Twist::Horizontal,
+        };
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        life.set_topology(topology).unwrap();
+
+        // evolve the same cells naively, looking up every neighbor through the topology
+        let mut cells = life.get_alive_cells();
+        for &step_log_2 in &[0, 1, 2] {
+            life.set_step_log_2(step_log_2);
+            for _ in 0..10 {
+                life.step();
+                for _ in 0..life.step_size() {
+                    let alive = |x: i64, y: i64| {
+                        let pos = topology.wrap(Position::new(x, y)).unwrap();
+                        cells.contains(&pos)
+                    };
+                    let mut next = vec![];
+                    for y in 0..6 {
+                        for x in 0..8 {
+                            let neighbors = (-1..=1)
+                                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                                .filter(|&(dx, dy)| (dx, dy) != (0, 0) && alive(x + dx, y + dy))
+                                .count();
+                            if neighbors == 3 || (neighbors == 2 && alive(x, y)) {
+                                next.push(Position::new(x, y));
+                            }
+                        }
+                    }
+                    cells = next;
+                }
+                let mut alive_cells = life.get_alive_cells();
+                alive_cells.sort_by_key(|pos| (pos.y, pos.x));
+                assert_eq!(alive_cells, cells);
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_plane() {
+        let mut life = Life::new();
+        life.set_topology(Topology::BoundedPlane {
+            width: 10,
+            height: 10,
+        })
+        .unwrap();
+
+        // a blinker against the top edge loses the cell that would lie outside the grid
+        life.set_cell_alive(Position::new(4, 0));
+        life.set_cell_alive(Position::new(5, 0));
+        life.set_cell_alive(Position::new(6, 0));
+        life.set_cell_alive(Position::new(10, 0));
+        assert_eq!(life.population(), 3);
+
+        life.step();
+        assert_eq!(
+            life.get_alive_cells(),
+            vec![Position::new(5, 0), Position::new(5, 1)]
+        );
+        life.step();
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn set_topology() {
+        let mut life = Life::new();
+        life.set_cell_alive(Position::new(-1, 0));
+        life.set_cell_alive(Position::new(20, 0));
+        life.set_step_log_2(10);
+
+        life.set_topology(Topology::Torus {
+            width: 8,
+            height: 8,
+        })
+        .unwrap();
+        assert_eq!(
+            life.get_alive_cells(),
+            vec![Position::new(4, 0), Position::new(7, 0)]
+        );
+        assert_eq!(life.step_log_2(), 10);
+        assert_eq!(life.store.step_log_2(), 3);
+
+        life.set_topology(Topology::Plane).unwrap();
+        assert_eq!(life.store.step_log_2(), 10);
+    }
+
+    #[test]
+    fn empty_topology() {
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        for &topology in &[
+            Topology::BoundedPlane {
+                width: 0,
+                height: 10,
+            },
+            Topology::Torus {
+                width: 10,
+                height: 0,
+            },
+            Topology::KleinBottle {
+                width: 0,
+                height: 0,
+                twist: Twist::Vertical,
+            },
+        ] {
+            assert!(life.set_topology(topology).is_err());
+            assert_eq!(life.topology(), Topology::Plane);
+            assert_eq!(life.population(), 5);
+        }
+    }
+
+    #[test]
+    fn from_rle_topology() {
+        let life = Life::from_rle_file_contents(b"x = 3, y = 3, rule = B3/S23:K4*,4\nbob$2bo$3o!")
+            .unwrap();
+        assert_eq!(
+            life.topology(),
+            Topology::KleinBottle {
+                width: 4,
+                height: 4,
+                twist: Twist::Horizontal
+            }
+        );
+    }
+
     #[test]
     fn position_extremes() {
         let mut life = Life::new();
 
-        let min = i64::min_value();
-        let max = i64::max_value();
+        let min = i64::MIN;
+        let max = i64::MAX;
 
         life.set_cell_alive(Position::new(min, min));
         life.set_cell_alive(Position::new(min, max));
@@ -0,0 +1,139 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Naming patterns by their canonical apgcode.
+
+use crate::{
+    parse::apgcode::{encode_wechsler, Apgcode, ApgcodeError},
+    Life, Position, Transformation,
+};
+
+/// Returns the cells moved so that their bounding box starts at the origin.
+fn normalize(cells: &[Position]) -> Vec<(u32, u32)> {
+    let min_x = cells.iter().map(|position| position.x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|position| position.y).min().unwrap_or(0);
+    cells
+        .iter()
+        .map(|position| ((position.x - min_x) as u32, (position.y - min_y) as u32))
+        .collect()
+}
+
+impl Life {
+    /// Returns the canonical apgcode of the pattern, advancing a copy of the Life grid one
+    /// generation at a time for at most the given number of generations to find its period.
+    ///
+    /// The canonical apgcode is the shortest, and then alphabetically first, extended Wechsler
+    /// encoding of all rotations and reflections of all phases of the pattern. Returns `None` if
+    /// the pattern is empty or doesn't repeat in time, including patterns that only repeat after
+    /// they have settled down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let glider = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// assert_eq!(glider.apgcode(100).unwrap(), "xq4_153");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apgcode(&self, max_generations: u128) -> Option<String> {
+        if self.population() == 0 {
+            return None;
+        }
+        let periodicity = self
+            .detect_period(max_generations)
+            .filter(|periodicity| periodicity.start == self.generation)?;
+        let prefix = match periodicity.period {
+            _ if !periodicity.is_stationary() => format!("xq{}", periodicity.period),
+            1 => format!("xs{}", self.population()),
+            period => format!("xp{}", period),
+        };
+
+        let mut life = self.clone();
+        life.set_step_log_2(0);
+        let mut best: Option<String> = None;
+        for generation in 0..periodicity.period {
+            if generation > 0 {
+                life.advance();
+            }
+            let alive_cells = life.get_alive_cells();
+            for &transformation in &Transformation::ALL {
+                let cells = alive_cells
+                    .iter()
+                    .map(|&position| transformation.apply(position));
+                let code = encode_wechsler(&normalize(&cells.collect::<Vec<_>>()));
+                let is_better = best
+                    .as_ref()
+                    .is_none_or(|best| (code.len(), &code) < (best.len(), best));
+                if is_better {
+                    best = Some(code);
+                }
+            }
+        }
+        best.map(|best| format!("{}_{}", prefix, best))
+    }
+
+    /// Creates a Life grid from an apgcode, with the bounding box of the pattern starting at the
+    /// origin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // beehive
+    /// let life = smeagol::Life::from_apgcode("xs6_696")?;
+    /// assert_eq!(life.population(), 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_apgcode(code: &str) -> Result<Self, ApgcodeError> {
+        let apgcode = Apgcode::from_code(code)?;
+        let mut life = Life::new();
+        for (x, y) in apgcode.alive_cells() {
+            life.set_cell_alive(Position::new(i64::from(x), i64::from(y)));
+        }
+        Ok(life)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        for &(pattern, code) in &[
+            (&b"2o$2o!"[..], "xs4_33"),
+            (b"b2o$o2bo$b2o!", "xs6_696"),
+            (b"o$o$o!", "xp2_7"),
+            (b"3o$o$bo!", "xq4_153"),
+            (b"2o$obo$b2o!", "xs6_356"),
+            (b"2o$obo$bo!", "xs5_253"),
+        ] {
+            let life = Life::from_rle_pattern(pattern).unwrap();
+            assert_eq!(life.apgcode(100).unwrap(), code);
+        }
+
+        let life = Life::from_rle_file("./assets/pentadecathlon.rle").unwrap();
+        assert_eq!(life.apgcode(100).unwrap(), "xp15_4r4z4r4");
+    }
+
+    #[test]
+    fn round_trip() {
+        for &code in &["xs4_33", "xp2_7", "xq4_153", "xp15_4r4z4r4", "xs7_2596"] {
+            let life = Life::from_apgcode(code).unwrap();
+            assert_eq!(life.apgcode(100).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn not_periodic() {
+        // the r-pentomino takes 1103 generations to settle down
+        let life = Life::from_rle_pattern(b"b2o$2o$bo!").unwrap();
+        assert_eq!(life.apgcode(100), None);
+        assert_eq!(Life::new().apgcode(100), None);
+    }
+}
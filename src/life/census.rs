@@ -0,0 +1,268 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Splitting a pattern into separate objects and counting them.
+//!
+//! Every object is evolved on its own through all of its phases, and the shape of each phase is
+//! stored as a node in a separate store. Since nodes are hash-consed, an object that has been seen
+//! before, in any phase and at any position, is found again by its node ID without evolving it.
+
+use crate::{
+    node::{NodeId, Store},
+    Life, Position, Spaceship,
+};
+use std::collections::{HashMap, HashSet};
+
+/// What kind of object a group of cells is.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ObjectKind {
+    /// An object that never changes.
+    StillLife,
+    /// An object that repeats itself without moving.
+    Oscillator {
+        /// The number of generations after which the object repeats.
+        period: u128,
+    },
+    /// An object that repeats itself at another position.
+    Spaceship(Spaceship),
+    /// An object that doesn't repeat itself in time, or changes when it is evolved on its own.
+    Other,
+}
+
+/// An object found in a census, and how many times it was found.
+#[derive(Clone, Debug)]
+pub struct CensusObject {
+    /// What kind of object it is.
+    pub kind: ObjectKind,
+    /// The object, in the phase it was first found in, with its bounding box starting at the
+    /// origin.
+    pub pattern: Life,
+    /// The number of times the object was found.
+    pub count: usize,
+}
+
+/// The objects a pattern consists of.
+#[derive(Clone, Debug, Default)]
+pub struct Census {
+    /// The objects, most common first.
+    pub objects: Vec<CensusObject>,
+}
+
+impl Census {
+    /// Returns the total number of objects.
+    pub fn total(&self) -> usize {
+        self.objects.iter().map(|object| object.count).sum()
+    }
+
+    /// Returns the number of objects of the given kind.
+    pub fn count(&self, kind: ObjectKind) -> usize {
+        self.objects
+            .iter()
+            .filter(|object| object.kind == kind)
+            .map(|object| object.count)
+            .sum()
+    }
+}
+
+/// Splits the cells into groups of cells that are at most the given distance apart along both
+/// axes from another cell in the group.
+pub(super) fn connected_components(cells: &[Position], distance: i64) -> Vec<Vec<Position>> {
+    let mut unvisited = cells.iter().copied().collect::<HashSet<_>>();
+    let mut components = vec![];
+    for &cell in cells {
+        if !unvisited.remove(&cell) {
+            continue;
+        }
+        let mut component = vec![cell];
+        let mut index = 0;
+        while index < component.len() {
+            let position = component[index];
+            for y in -distance..=distance {
+                for x in -distance..=distance {
+                    let neighbor =
+                        Position::new(position.x.wrapping_add(x), position.y.wrapping_add(y));
+                    if unvisited.remove(&neighbor) {
+                        component.push(neighbor);
+                    }
+                }
+            }
+            index += 1;
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Creates a Life grid with the cells moved so that their bounding box starts at the origin.
+fn isolate(cells: &[Position]) -> Life {
+    let min_x = cells.iter().map(|position| position.x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|position| position.y).min().unwrap_or(0);
+    let mut life = Life::new();
+    for position in cells {
+        life.set_cell_alive(Position::new(position.x - min_x, position.y - min_y));
+    }
+    life
+}
+
+/// Evolves the object on its own for at most the given number of generations, and returns what
+/// kind of object it is and the shapes of its phases.
+fn classify(object: &Life, shapes: &mut Store, max_generations: u128) -> (ObjectKind, Vec<NodeId>) {
+    let mut life = object.clone();
+    life.set_step_log_2(0);
+
+    let (shape, first_upper_left) = life.shape(shapes);
+    let mut phases = vec![shape];
+    for generation in 1..=max_generations {
+        life.advance();
+        let (shape, upper_left) = life.shape(shapes);
+        if shape == phases[0] {
+            let (dx, dy) = (
+                upper_left.x - first_upper_left.x,
+                upper_left.y - first_upper_left.y,
+            );
+            let kind = match (generation, dx, dy) {
+                (1, 0, 0) => ObjectKind::StillLife,
+                (period, 0, 0) => ObjectKind::Oscillator { period },
+                (period, dx, dy) => ObjectKind::Spaceship(Spaceship { dx, dy, period }),
+            };
+            return (kind, phases);
+        }
+        if phases.contains(&shape) {
+            // the object settled down into something else
+            break;
+        }
+        phases.push(shape);
+    }
+    (ObjectKind::Other, vec![phases[0]])
+}
+
+impl Life {
+    /// Splits the alive cells into objects and counts how many times each object occurs.
+    ///
+    /// Alive cells that are at most `distance` cells apart along both axes belong to the same
+    /// object. Each distinct object is evolved on its own for at most `max_generations`
+    /// generations to find out whether it is a still life, an oscillator or a spaceship. The same
+    /// object in another phase or at another position is counted as the same object, but rotated
+    /// or reflected objects are counted separately.
+    ///
+    /// Objects that are too close together to be separated, like a pseudo still life, are counted
+    /// as one object, which may not be recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // two blocks and a blinker in both of its phases
+    /// let life = smeagol::Life::from_rle_pattern(b"2o3b2o3b3o$2o3b2o$10bo$10bo$10bo!")?;
+    /// let census = life.census(1, 100);
+    /// assert_eq!(census.total(), 4);
+    /// assert_eq!(census.count(smeagol::ObjectKind::StillLife), 2);
+    /// assert_eq!(census.count(smeagol::ObjectKind::Oscillator { period: 2 }), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn census(&self, distance: i64, max_generations: u128) -> Census {
+        let mut shapes = Store::new();
+        let mut seen = HashMap::<NodeId, usize>::new();
+        let mut objects = Vec::<CensusObject>::new();
+        for component in connected_components(&self.get_alive_cells(), distance) {
+            let object = isolate(&component);
+            let (shape, _) = object.shape(&mut shapes);
+            if let Some(&index) = seen.get(&shape) {
+                objects[index].count += 1;
+                continue;
+            }
+
+            let (kind, phases) = classify(&object, &mut shapes, max_generations);
+            for phase in phases {
+                seen.insert(phase, objects.len());
+            }
+            objects.push(CensusObject {
+                kind,
+                pattern: object,
+                count: 1,
+            });
+        }
+
+        // sort by count, and then by size to get the same order every time
+        objects.sort_by_key(|object| {
+            let cells = object.pattern.get_alive_cells();
+            (std::cmp::Reverse(object.count), cells.len(), cells)
+        });
+        Census { objects }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components() {
+        let cells = [
+            Position::new(0, 0),
+            Position::new(1, 1),
+            Position::new(3, 1),
+            Position::new(10, 10),
+        ];
+        let mut sizes = connected_components(&cells, 1)
+            .iter()
+            .map(Vec::len)
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 1, 2]);
+
+        let mut sizes = connected_components(&cells, 2)
+            .iter()
+            .map(Vec::len)
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 3]);
+    }
+
+    #[test]
+    fn spaceships() {
+        // a glider in two phases and a sir robin
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            life.set_cell_alive(Position::new(x - 100, y - 100));
+        }
+        for &(x, y) in &[(0, 0), (2, 0), (1, 1), (2, 1), (1, 2)] {
+            life.set_cell_alive(Position::new(x + 100, y + 100));
+        }
+        assert!(life.census(1, 100).objects.len() > 2);
+
+        // the parts of a sir robin are up to two cells apart
+        let census = life.census(2, 100);
+        assert_eq!(census.objects.len(), 2);
+
+        let glider = &census.objects[0];
+        assert_eq!(glider.count, 2);
+        assert_eq!(glider.pattern.population(), 5);
+        assert_eq!(
+            glider.kind,
+            ObjectKind::Spaceship(Spaceship {
+                dx: 1,
+                dy: 1,
+                period: 4
+            })
+        );
+        match census.objects[1].kind {
+            ObjectKind::Spaceship(spaceship) => assert_eq!(spaceship.speed(), "(2,1)c/6"),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn other() {
+        // the r-pentomino doesn't settle down in time
+        let life = Life::from_rle_pattern(b"b2o$2o$bo!").unwrap();
+        let census = life.census(1, 10);
+        assert_eq!(census.count(ObjectKind::Other), 1);
+        assert_eq!(Life::new().census(1, 10).total(), 0);
+    }
+}
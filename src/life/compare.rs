@@ -0,0 +1,477 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Comparing and hashing the patterns of Life grids.
+//!
+//! The pattern of a Life grid is described by the smallest square containing all its alive cells
+//! whose corners are at multiples of half its side length. The quadrants of that square line up
+//! with the same squares of cells whatever the root node of the Life grid, so they are compared or
+//! hashed through the content hashes of the nodes covering them, without creating any nodes.
+//!
+//! Squares that don't line up with the nodes, as when comparing patterns up to a translation, are
+//! hashed from their 8 by 8 squares of cells, skipping those without alive cells.
+
+use crate::{
+    life::origin::{Location, Square},
+    node::{
+        block_content_hash, combine_content_hashes, empty_content_hash, get_leaf_row,
+        leaf_square_content_hash, positioned_content_hash, Level, Node, NodeId, Store, BLOCK_LEVEL,
+        LEAF_LEVEL, LEAF_MIN_COORD,
+    },
+    BoundingBox, Life, Position,
+};
+use std::collections::HashSet;
+
+/// The level of the smallest square used to describe a pattern.
+///
+/// The quadrants of the square are at least 8 by 8 cells, the smallest squares with content hashes.
+const MIN_PATTERN_LEVEL: u8 = 4;
+
+/// Returns the smallest square containing the bounding box whose corners are at multiples of half
+/// its side length. Of two such squares, the one with the smaller coordinates is used.
+fn pattern_square(bounding_box: BoundingBox) -> Square {
+    let upper_left = bounding_box.upper_left;
+    let lower_right = bounding_box.lower_right;
+    (MIN_PATTERN_LEVEL..=64)
+        .find_map(|level| {
+            let half = 1i128 << (level - 1);
+            let corner = |min: i64| i128::from(min).div_euclid(half) * half;
+            let square = Square {
+                x: corner(upper_left.x),
+                y: corner(upper_left.y),
+                level,
+            };
+            if i128::from(lower_right.x) < square.x + 2 * half
+                && i128::from(lower_right.y) < square.y + 2 * half
+            {
+                Some(square)
+            } else {
+                None
+            }
+        })
+        .unwrap()
+}
+
+/// Returns the content hash of a square of the Life grid.
+fn square_hash(life: &Life, square: Square) -> u64 {
+    match life.locate(square) {
+        Location::Outside => empty_content_hash(Level(square.level)),
+        Location::Node(id) => id.content_hash(&life.store),
+        Location::Leaf { id, x, y } => match life.store.node(id) {
+            Node::Leaf { grid } => leaf_square_content_hash(grid, x, y, square.level),
+            Node::StateLeaf { .. } | Node::Interior { .. } => unreachable!(),
+        },
+        Location::Split => {
+            let [nw, ne, sw, se] = square.quadrants();
+            combine_content_hashes(
+                Level(square.level),
+                [
+                    square_hash(life, nw),
+                    square_hash(life, ne),
+                    square_hash(life, sw),
+                    square_hash(life, se),
+                ],
+            )
+        }
+    }
+}
+
+/// Returns the bounding box of a square, clamped to the coordinates of a Life grid.
+fn square_box(square: Square) -> BoundingBox {
+    let clamp = |coord: i128| coord.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+    let last = square.side_len() - 1;
+    BoundingBox::new(
+        Position::new(clamp(square.x), clamp(square.y)),
+        Position::new(clamp(square.x + last), clamp(square.y + last)),
+    )
+}
+
+/// Returns the 8 cells of a row of the Life grid starting at the given position, with the
+/// leftmost cell in the highest bit.
+fn block_row(life: &Life, x: i128, y: i128) -> u64 {
+    let root_square = life.root_square();
+    let side_len = 1 << LEAF_LEVEL.0;
+    let leaf_x = root_square.x + (x - root_square.x).div_euclid(side_len) * side_len;
+    let leaf_y = root_square.y + (y - root_square.y).div_euclid(side_len) * side_len;
+    let leaf_row = |leaf_x: i128| {
+        let square = Square {
+            x: leaf_x,
+            y: leaf_y,
+            level: LEAF_LEVEL.0,
+        };
+        match life.locate(square) {
+            Location::Node(id) => match life.store.node(id) {
+                Node::Leaf { grid } => {
+                    u128::from(get_leaf_row(grid, LEAF_MIN_COORD + (y - leaf_y) as i64))
+                }
+                Node::StateLeaf { .. } | Node::Interior { .. } => unreachable!(),
+            },
+            _ => 0,
+        }
+    };
+
+    // the row may continue into the leaf to the east
+    let column = x - leaf_x;
+    let mut row = leaf_row(leaf_x) << side_len;
+    if column + 8 > side_len {
+        row |= leaf_row(leaf_x + side_len);
+    }
+    ((row >> (2 * side_len - column - 8)) & 0xff) as u64
+}
+
+/// Returns the content hash of a square of the Life grid, whether or not it lines up with the
+/// nodes.
+///
+/// Squares that line up are hashed as in `square_hash`. The others are hashed from their 8 by 8
+/// squares of cells, so the cost grows with the number of those containing alive cells.
+fn any_square_hash(life: &Life, square: Square) -> u64 {
+    if !life.contains_alive_cells(square_box(square)) {
+        return empty_content_hash(Level(square.level));
+    }
+    if square.level == BLOCK_LEVEL {
+        let block = (0..8).fold(0, |block, row| {
+            block << 8 | block_row(life, square.x, square.y + row)
+        });
+        return block_content_hash(block);
+    }
+    match life.locate(square) {
+        Location::Split => {
+            let [nw, ne, sw, se] = square.quadrants();
+            combine_content_hashes(
+                Level(square.level),
+                [
+                    any_square_hash(life, nw),
+                    any_square_hash(life, ne),
+                    any_square_hash(life, sw),
+                    any_square_hash(life, se),
+                ],
+            )
+        }
+        _ => square_hash(life, square),
+    }
+}
+
+/// Returns true if the Life grids have the same cells in a square at least as large as a leaf.
+fn same_square(
+    life: &Life,
+    other: &Life,
+    square: Square,
+    same: &mut HashSet<(NodeId, NodeId)>,
+) -> bool {
+    match (life.locate(square), other.locate(square)) {
+        (Location::Node(id), Location::Node(other_id)) => {
+            same_cells(&life.store, id, &other.store, other_id, same)
+        }
+        (Location::Outside, Location::Outside) => true,
+        (Location::Node(id), Location::Outside) => id.population(&life.store) == 0,
+        (Location::Outside, Location::Node(other_id)) => other_id.population(&other.store) == 0,
+        (Location::Leaf { .. }, _) | (_, Location::Leaf { .. }) => unreachable!(),
+        _ => square
+            .quadrants()
+            .iter()
+            .all(|&quadrant| same_square(life, other, quadrant, same)),
+    }
+}
+
+/// Returns true if the nodes have the same cells.
+///
+/// Pairs of nodes already known to be the same are skipped, so every pair is only compared once.
+fn same_cells(
+    store: &Store,
+    id: NodeId,
+    other_store: &Store,
+    other_id: NodeId,
+    same: &mut HashSet<(NodeId, NodeId)>,
+) -> bool {
+    if id.content_hash(store) != other_id.content_hash(other_store)
+        || id.population(store) != other_id.population(other_store)
+    {
+        return false;
+    }
+    if same.contains(&(id, other_id)) {
+        return true;
+    }
+    let equal = match (store.node(id), other_store.node(other_id)) {
+        (Node::Leaf { grid }, Node::Leaf { grid: other_grid }) => grid == other_grid,
+        (Node::StateLeaf { grid }, Node::StateLeaf { grid: other_grid }) => grid == other_grid,
+        (
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                ..
+            },
+            Node::Interior {
+                nw: other_nw,
+                ne: other_ne,
+                sw: other_sw,
+                se: other_se,
+                level: other_level,
+                ..
+            },
+        ) => {
+            level == other_level
+                && same_cells(store, nw, other_store, other_nw, same)
+                && same_cells(store, ne, other_store, other_ne, same)
+                && same_cells(store, sw, other_store, other_sw, same)
+                && same_cells(store, se, other_store, other_se, same)
+        }
+        _ => false,
+    };
+    if equal {
+        same.insert((id, other_id));
+    }
+    equal
+}
+
+impl Life {
+    /// Returns true if the Life grids have the same alive cells at the same positions.
+    ///
+    /// The generation, step size and topology are not compared. Different patterns are almost
+    /// always told apart by their bounding boxes, populations and content hashes, which only look
+    /// at the nodes along the edges of the pattern. When those match, the patterns are confirmed by
+    /// comparing every distinct pair of nodes covering them once, so the cost grows with the number
+    /// of distinct nodes rather than with the population.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// let mut other = life.clone();
+    /// assert!(life.same_pattern(&other));
+    ///
+    /// other.set_step_log_2(2);
+    /// other.step();
+    /// assert!(!life.same_pattern(&other));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn same_pattern(&self, other: &Life) -> bool {
+        let bounding_box = match (self.bounding_box, other.bounding_box) {
+            (Some(bounding_box), Some(other_bounding_box))
+                if bounding_box == other_bounding_box =>
+            {
+                bounding_box
+            }
+            (bounding_box, other_bounding_box) => {
+                return bounding_box.is_none() && other_bounding_box.is_none();
+            }
+        };
+        let square = pattern_square(bounding_box);
+        if self.population() != other.population()
+            || square_hash(self, square) != square_hash(other, square)
+        {
+            return false;
+        }
+        if square.level <= LEAF_LEVEL.0 {
+            // the quadrants are smaller than leaves, but so is the whole pattern
+            let mut cells = self.get_alive_cells();
+            let mut other_cells = other.get_alive_cells();
+            cells.sort_unstable();
+            other_cells.sort_unstable();
+            return cells == other_cells;
+        }
+        let mut same = HashSet::new();
+        same_square(self, other, square, &mut same)
+    }
+
+    /// Returns true if the Life grids have the same alive cells up to a translation.
+    ///
+    /// Patterns at the same position are compared as in `same_pattern`. Otherwise the populations
+    /// and bounding boxes are compared first, followed by the content hashes of the patterns. The
+    /// translated pattern no longer lines up with its nodes, so it is hashed from its 8 by 8 squares
+    /// of cells containing alive cells. When the hashes match, both patterns are moved to the same
+    /// corner of a new store and compared there, which takes time proportional to the population.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// let mut other = life.clone();
+    ///
+    /// // a glider moves one cell diagonally every four generations
+    /// other.set_step_log_2(2);
+    /// other.step();
+    /// assert!(life.same_shape(&other));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn same_shape(&self, other: &Life) -> bool {
+        let (bounding_box, other_bounding_box) = match (self.bounding_box, other.bounding_box) {
+            (Some(bounding_box), Some(other_bounding_box)) => (bounding_box, other_bounding_box),
+            (bounding_box, other_bounding_box) => {
+                return bounding_box.is_none() && other_bounding_box.is_none();
+            }
+        };
+        let upper_left = bounding_box.upper_left();
+        let other_upper_left = other_bounding_box.upper_left();
+        let x_offset = other_upper_left.x - upper_left.x;
+        let y_offset = other_upper_left.y - upper_left.y;
+        if bounding_box.offset(x_offset, y_offset) != other_bounding_box
+            || self.population() != other.population()
+        {
+            return false;
+        }
+        if x_offset == 0 && y_offset == 0 {
+            return self.same_pattern(other);
+        }
+
+        let square = pattern_square(bounding_box);
+        let shifted = Square {
+            x: square.x + i128::from(x_offset),
+            y: square.y + i128::from(y_offset),
+            ..square
+        };
+        if square_hash(self, square) != any_square_hash(other, shifted) {
+            return false;
+        }
+
+        // confirm the match with both shapes as nodes of one store, where they are the same node
+        let mut store = Store::new();
+        self.shape(&mut store).0 == other.shape(&mut store).0
+    }
+
+    /// Returns a hash of the alive cells and their positions.
+    ///
+    /// Life grids with the same pattern have the same hash, whatever their generation, topology or
+    /// history, and the hash is the same in every run of the program, so it can be used as a key
+    /// to find patterns that have been seen before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // blinker
+    /// let mut life = smeagol::Life::from_rle_pattern(b"3o!")?;
+    /// let hash = life.pattern_hash();
+    ///
+    /// life.step();
+    /// assert_ne!(life.pattern_hash(), hash);
+    /// life.step();
+    /// assert_eq!(life.pattern_hash(), hash);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pattern_hash(&self) -> u64 {
+        match self.bounding_box {
+            Some(bounding_box) => {
+                let square = pattern_square(bounding_box);
+                let corner = Position::new(square.x as i64, square.y as i64);
+                positioned_content_hash(square_hash(self, square), corner)
+            }
+            None => empty_content_hash(Level(MIN_PATTERN_LEVEL)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Life, Position, Topology};
+
+    #[test]
+    fn same_pattern_with_different_roots() {
+        let life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+
+        // a bounded grid has a much larger root node
+        let mut bounded = life.clone();
+        bounded
+            .set_topology(Topology::BoundedPlane {
+                width: 1 << 10,
+                height: 1 << 10,
+            })
+            .unwrap();
+        assert!(bounded.root.level(&bounded.store) > life.root.level(&life.store));
+        assert!(bounded.same_pattern(&life));
+        assert!(life.same_pattern(&bounded));
+        assert_eq!(bounded.pattern_hash(), life.pattern_hash());
+
+        let mut other = life.clone();
+        other.set_cell_alive(Position::new(1 << 20, 0));
+        assert!(!life.same_pattern(&other));
+        assert_ne!(other.pattern_hash(), life.pattern_hash());
+    }
+
+    #[test]
+    fn stable_hash() {
+        // the same with both leaf sizes and in every run
+        let life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        assert_eq!(life.pattern_hash(), 0xc399_e6fe_4b6c_5cac);
+    }
+
+    #[test]
+    fn empty() {
+        let empty = Life::new();
+        let mut other = Life::new();
+        other.set_cell_alive(Position::new(1 << 20, 0));
+        other
+            .set_topology(Topology::BoundedPlane {
+                width: 1 << 10,
+                height: 1 << 10,
+            })
+            .unwrap();
+        assert_eq!(other.population(), 0);
+        assert!(empty.same_pattern(&other));
+        assert!(empty.same_shape(&other));
+        assert_eq!(empty.pattern_hash(), other.pattern_hash());
+    }
+
+    #[test]
+    fn same_shape() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        let mut other = Life::new();
+        for position in life.get_alive_cells() {
+            other.set_cell_alive(position.offset(-1000, 37));
+        }
+        assert!(life.same_shape(&other));
+        assert!(!life.same_pattern(&other));
+
+        life.step();
+        assert!(!life.same_shape(&other));
+    }
+
+    #[test]
+    fn same_shape_offsets() {
+        let life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        let cells = life.get_alive_cells();
+        for &(dx, dy) in &[
+            (1, 0),
+            (0, -7),
+            (8, 8),
+            (-13, 100),
+            (1 << 40, -(1 << 40) + 3),
+        ] {
+            let mut other = Life::new();
+            for &position in &cells {
+                other.set_cell_alive(position.offset(dx, dy));
+            }
+            assert!(life.same_shape(&other));
+            assert!(other.same_shape(&life));
+
+            // the same population and bounding box, with one cell moved inside the pattern
+            let moved = (0..cells.len())
+                .map(|index| {
+                    let mut moved = cells.clone();
+                    moved[index] = moved[index].offset(1, 0);
+                    let mut other = Life::new();
+                    for position in moved {
+                        other.set_cell_alive(position.offset(dx, dy));
+                    }
+                    other
+                })
+                .find(|moved| {
+                    moved.population() == life.population()
+                        && moved.bounding_box() == other.bounding_box()
+                })
+                .unwrap();
+            assert!(!life.same_shape(&moved));
+            assert!(!moved.same_shape(&life));
+        }
+    }
+}
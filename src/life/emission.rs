@@ -0,0 +1,267 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Detecting the spaceships emitted by guns and puffers.
+//!
+//! A copy of the pattern is advanced one generation at a time, and objects outside a core region
+//! around the pattern are identified. Spaceships are recorded and removed once they have left the
+//! core completely, while anything else, like the debris of a puffer, makes the core grow.
+
+use super::census::connected_components;
+use crate::{
+    node::{NodeId, Store},
+    BoundingBox, Life, Position, Spaceship,
+};
+use std::collections::HashMap;
+
+/// How far the core extends beyond the cells that aren't spaceships.
+const CORE_MARGIN: i64 = 4;
+
+/// How far apart cells of the same object can be.
+const OBJECT_DISTANCE: i64 = 2;
+
+/// The largest period of spaceships that are recognized.
+const MAX_SPACESHIP_PERIOD: u128 = 64;
+
+/// A spaceship that left the core of a pattern.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Emission {
+    /// The generation in which the spaceship was first completely outside the core.
+    pub generation: u128,
+    /// The generation modulo the period of the emissions, or the generation if there is no period.
+    pub phase: u128,
+    /// The canonical apgcode of the spaceship.
+    pub apgcode: String,
+    /// The velocity of the spaceship.
+    pub spaceship: Spaceship,
+    /// The line the spaceship travels along, given by `dy * x - dx * y` for the upper left corner
+    /// of its bounding box when it left the core, which doesn't change as the spaceship moves from
+    /// one phase to the same phase again.
+    pub lane: i64,
+}
+
+/// The spaceships emitted by a pattern, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmissionSchedule {
+    /// The number of generations after which the same spaceships are emitted along the same lanes
+    /// again, if the emissions repeat.
+    pub period: Option<u128>,
+    /// The emitted spaceships.
+    pub emissions: Vec<Emission>,
+}
+
+/// Returns the bounding box of the cells, if there are any.
+fn bounding_box(cells: &[Position]) -> Option<BoundingBox> {
+    cells
+        .iter()
+        .map(|&position| BoundingBox::new(position, position))
+        .fold(None, |bounding_box, cell| {
+            Some(bounding_box.map_or(cell, |bounding_box: BoundingBox| bounding_box.combine(cell)))
+        })
+}
+
+/// Returns true if the inner bounding box is completely inside the outer one.
+fn contains(outer: BoundingBox, inner: BoundingBox) -> bool {
+    outer.intersect(inner) == Some(inner)
+}
+
+/// Returns the smallest number of generations after which every emission is followed by the same
+/// spaceship along the same lane, as far as the emissions were observed.
+fn emission_period(emissions: &[Emission]) -> Option<u128> {
+    let key = |emission: &Emission| (emission.apgcode.clone(), emission.spaceship, emission.lane);
+    let first = emissions.first()?;
+    let last_generation = emissions.last()?.generation;
+    let periods = emissions
+        .iter()
+        .filter(|emission| key(emission) == key(first))
+        .map(|emission| emission.generation - first.generation)
+        .filter(|&period| period > 0)
+        .collect::<Vec<_>>();
+    periods.into_iter().find(|&period| {
+        emissions
+            .iter()
+            .filter(|emission| emission.generation + period <= last_generation)
+            .all(|emission| {
+                emissions.iter().any(|later| {
+                    later.generation == emission.generation + period && key(later) == key(emission)
+                })
+            })
+    })
+}
+
+impl Life {
+    /// Advances a copy of the Life grid one generation at a time for the given number of
+    /// generations, and reports the spaceships that leave the pattern.
+    ///
+    /// The core of the pattern starts out as its bounding box with a small margin, and grows to
+    /// include everything outside of it that isn't a spaceship. A spaceship is reported when it is
+    /// completely outside the core, and then removed from the copy. Spaceships that are too close
+    /// to each other or to debris to be separated are not recognized.
+    ///
+    /// A pattern that is a spaceship as a whole doesn't emit anything, however far it travels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let life = smeagol::Life::from_rle_file("./assets/gosperglidergun.rle")?;
+    /// let schedule = life.detect_emissions(200);
+    /// assert_eq!(schedule.period, Some(30));
+    /// assert!(schedule
+    ///     .emissions
+    ///     .iter()
+    ///     .all(|emission| emission.apgcode == "xq4_153"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_emissions(&self, generations: u128) -> EmissionSchedule {
+        let mut core = match self.bounding_box {
+            Some(bounding_box) => bounding_box.pad(CORE_MARGIN),
+            None => return EmissionSchedule::default(),
+        };
+        if self.detect_spaceship(MAX_SPACESHIP_PERIOD).is_some() {
+            return EmissionSchedule::default();
+        }
+        let mut life = self.clone();
+        life.set_step_log_2(0);
+
+        let mut shapes = Store::new();
+        let mut spaceships = HashMap::<NodeId, Option<(String, Spaceship)>>::new();
+        let mut emissions = vec![];
+        let end = self.generation + generations;
+        while life.generation < end {
+            life.advance();
+            let outside = life
+                .bounding_box
+                .is_some_and(|bounding_box| !contains(core, bounding_box));
+            if !outside {
+                continue;
+            }
+
+            let mut kept = vec![];
+            let mut emitted = false;
+            for component in connected_components(&life.get_alive_cells(), OBJECT_DISTANCE) {
+                let component_box = bounding_box(&component).unwrap();
+                if contains(core, component_box) {
+                    kept.extend(component);
+                    continue;
+                }
+
+                let mut object = Life::new();
+                for &position in &component {
+                    object.set_cell_alive(position);
+                }
+                let (shape, _) = object.shape(&mut shapes);
+                let spaceship = spaceships
+                    .entry(shape)
+                    .or_insert_with(|| {
+                        let spaceship = object.detect_spaceship(MAX_SPACESHIP_PERIOD)?;
+                        let apgcode = object.apgcode(spaceship.period)?;
+                        Some((apgcode, spaceship))
+                    })
+                    .clone();
+                match spaceship {
+                    Some((apgcode, spaceship)) if core.intersect(component_box).is_none() => {
+                        let upper_left = component_box.upper_left;
+                        emissions.push(Emission {
+                            generation: life.generation,
+                            phase: life.generation,
+                            apgcode,
+                            spaceship,
+                            lane: spaceship.dy * upper_left.x - spaceship.dx * upper_left.y,
+                        });
+                        emitted = true;
+                    }
+                    Some(_) => kept.extend(component),
+                    None => {
+                        core = core.combine(component_box.pad(CORE_MARGIN));
+                        kept.extend(component);
+                    }
+                }
+            }
+            if emitted {
+                life.retain_cells(kept);
+            }
+        }
+
+        let period = emission_period(&emissions);
+        if let Some(period) = period {
+            for emission in &mut emissions {
+                emission.phase = emission.generation % period;
+            }
+        }
+        EmissionSchedule { period, emissions }
+    }
+
+    /// Replaces the alive cells with the given ones, keeping the store and generation.
+    fn retain_cells(&mut self, cells: Vec<Position>) {
+        let level = self.root.level(&self.store);
+        let positions = cells.into_iter().map(|position| self.to_root(position));
+        let positions = positions.collect::<Vec<_>>();
+        self.root = self.store.create_empty(level);
+        self.root = self.root.set_cells_alive(&mut self.store, positions);
+        self.update_bounding_box();
+        self.truncate_history();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gun() {
+        let life = Life::from_rle_file("./assets/gosperglidergun.rle").unwrap();
+        let schedule = life.detect_emissions(300);
+        assert_eq!(schedule.period, Some(30));
+        assert!(schedule.emissions.len() >= 8);
+
+        let first = &schedule.emissions[0];
+        assert_eq!(first.spaceship.period, 4);
+        assert_eq!(first.spaceship.dx.abs(), 1);
+        assert_eq!(first.spaceship.dy.abs(), 1);
+        for (emission, next) in schedule.emissions.iter().zip(&schedule.emissions[1..]) {
+            assert_eq!(next.generation - emission.generation, 30);
+            assert_eq!(next.lane, emission.lane);
+            assert_eq!(next.phase, emission.phase);
+        }
+    }
+
+    #[test]
+    fn single_spaceship() {
+        // long enough for both to leave their starting bounding boxes far behind
+        let life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        assert_eq!(life.detect_emissions(600), EmissionSchedule::default());
+        let life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        assert_eq!(life.detect_emissions(200), EmissionSchedule::default());
+
+        // a glider flying away from a block
+        let life = Life::from_rle_pattern(b"3o$o$bo5$10b2o$10b2o!").unwrap();
+        let schedule = life.detect_emissions(100);
+        assert_eq!(schedule.emissions.len(), 1);
+        assert_eq!(schedule.emissions[0].apgcode, "xq4_153");
+        assert_eq!(
+            schedule.emissions[0].spaceship,
+            Spaceship {
+                dx: -1,
+                dy: -1,
+                period: 4
+            }
+        );
+    }
+
+    #[test]
+    fn still_life() {
+        let life = Life::from_rle_pattern(b"2o$2o!").unwrap();
+        assert_eq!(life.detect_emissions(10), EmissionSchedule::default());
+        assert_eq!(
+            Life::new().detect_emissions(10),
+            EmissionSchedule::default()
+        );
+    }
+}
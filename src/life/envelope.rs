@@ -0,0 +1,211 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Collecting every cell that is alive during a range of generations, and how often.
+
+use crate::{BoundingBox, Life, Position};
+use png::HasParameters;
+use std::{collections::HashSet, io::Write, ops::Range};
+
+/// The cells that were alive during a range of generations.
+#[derive(Clone, Debug)]
+pub struct Envelope {
+    /// Every cell that was alive in at least one of the generations.
+    pub envelope: Life,
+    /// The region in which the activity is counted.
+    pub bounding_box: BoundingBox,
+    /// For every cell in the bounding box, row by row, the number of generations it was alive in.
+    pub activity: Vec<u64>,
+    /// The number of generations.
+    pub generations: u64,
+}
+
+impl Envelope {
+    /// Returns the number of generations the cell at the given position was alive in, or `None`
+    /// if the position is outside the bounding box.
+    pub fn activity_at(&self, position: Position) -> Option<u64> {
+        let upper_left = self.bounding_box.upper_left;
+        let lower_right = self.bounding_box.lower_right;
+        if position.x < upper_left.x
+            || position.x > lower_right.x
+            || position.y < upper_left.y
+            || position.y > lower_right.y
+        {
+            return None;
+        }
+        let width = (lower_right.x - upper_left.x + 1) as usize;
+        let index =
+            (position.y - upper_left.y) as usize * width + (position.x - upper_left.x) as usize;
+        Some(self.activity[index])
+    }
+
+    /// Writes the activity as CSV, with one line for each row of the bounding box.
+    pub fn write_csv<W>(&self, mut writer: W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        for row in self.activity.chunks(self.width()) {
+            let row = row.iter().map(u64::to_string).collect::<Vec<_>>();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        writer.flush()
+    }
+
+    /// Renders the activity as a grayscale png image, with one pixel per cell.
+    ///
+    /// Cells that were never alive are white, and cells that were alive in every generation are
+    /// black.
+    pub fn save_png<P>(&self, path: P) -> std::io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::create(path)?;
+        let writer = std::io::BufWriter::new(file);
+
+        let generations = self.generations.max(1);
+        let data = self
+            .activity
+            .iter()
+            .map(|&count| 255 - (count * 255 / generations) as u8)
+            .collect::<Vec<_>>();
+
+        let height = self.activity.len() / self.width();
+        let mut encoder = png::Encoder::new(writer, self.width() as u32, height as u32);
+        encoder
+            .set(png::ColorType::Grayscale)
+            .set(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+
+        Ok(())
+    }
+
+    /// Returns the width of the bounding box.
+    fn width(&self) -> usize {
+        (self.bounding_box.lower_right.x - self.bounding_box.upper_left.x + 1) as usize
+    }
+}
+
+impl Life {
+    /// Collects the envelope of the pattern over a range of generations, and counts how many of
+    /// those generations each cell in the bounding box was alive in.
+    ///
+    /// A copy of the Life grid is advanced to the start of the range in as few steps as possible,
+    /// and then one generation at a time. Generations before the current one are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // blinker
+    /// let life = smeagol::Life::from_rle_pattern(b"3o!")?;
+    /// let bounding_box = life.bounding_box().unwrap().pad(1);
+    /// let envelope = life.envelope(0..10, bounding_box);
+    /// assert_eq!(envelope.envelope.population(), 5);
+    /// assert_eq!(envelope.activity_at(smeagol::Position::new(1, 0)), Some(10));
+    /// assert_eq!(envelope.activity_at(smeagol::Position::new(0, 0)), Some(5));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn envelope(&self, generations: Range<u128>, bounding_box: BoundingBox) -> Envelope {
+        let mut life = self.clone();
+        while life.generation < generations.start {
+            let remaining = generations.start - life.generation;
+            let step_log_2 = (127 - remaining.leading_zeros()).min(63);
+            life.set_step_log_2(step_log_2 as u8);
+            life.advance();
+        }
+        life.set_step_log_2(0);
+
+        let upper_left = bounding_box.upper_left;
+        let lower_right = bounding_box.lower_right;
+        let width = (lower_right.x - upper_left.x + 1) as usize;
+        let height = (lower_right.y - upper_left.y + 1) as usize;
+        let mut activity = vec![0; width * height];
+        let mut cells = HashSet::new();
+        let mut count = 0;
+        while life.generation < generations.end {
+            for position in life.get_alive_cells() {
+                cells.insert(position);
+                if position.x >= upper_left.x
+                    && position.x <= lower_right.x
+                    && position.y >= upper_left.y
+                    && position.y <= lower_right.y
+                {
+                    let x = (position.x - upper_left.x) as usize;
+                    let y = (position.y - upper_left.y) as usize;
+                    activity[y * width + x] += 1;
+                }
+            }
+            count += 1;
+            life.advance();
+        }
+
+        let mut envelope = Life::new();
+        for position in cells {
+            envelope.set_cell_alive(position);
+        }
+        Envelope {
+            envelope,
+            bounding_box,
+            activity,
+            generations: count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glider() {
+        let life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        let bounding_box = BoundingBox::new(Position::new(0, 0), Position::new(9, 9));
+        let envelope = life.envelope(0..4, bounding_box);
+        assert_eq!(envelope.generations, 4);
+        assert_eq!(envelope.activity.iter().sum::<u64>(), 20);
+        assert_eq!(envelope.activity_at(Position::new(10, 0)), None);
+
+        // later generations are reached by jumping ahead
+        let later = life.envelope(1000..1004, bounding_box);
+        assert_eq!(later.envelope.population(), envelope.envelope.population());
+        assert_eq!(later.activity.iter().sum::<u64>(), 0);
+        assert!(later.envelope.contains_alive_cells(BoundingBox::new(
+            Position::new(250, 250),
+            Position::new(252, 252)
+        )));
+    }
+
+    #[test]
+    fn export() {
+        let life = Life::from_rle_file("./assets/pulsar.rle").unwrap();
+        let bounding_box = life.bounding_box().unwrap().pad(1);
+        let envelope = life.envelope(0..3, bounding_box);
+
+        let mut csv = vec![];
+        envelope.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let width = (bounding_box.lower_right.x - bounding_box.upper_left.x + 1) as usize;
+        let height = (bounding_box.lower_right.y - bounding_box.upper_left.y + 1) as usize;
+        assert_eq!(csv.lines().count(), height);
+        assert!(csv.lines().all(|line| line.split(',').count() == width));
+
+        envelope
+            .save_png(std::env::temp_dir().join("envelope.png"))
+            .unwrap();
+    }
+
+    #[test]
+    fn empty_range() {
+        let life = Life::from_rle_pattern(b"3o!").unwrap();
+        let bounding_box = life.bounding_box().unwrap();
+        let envelope = life.envelope(5..5, bounding_box);
+        assert_eq!(envelope.generations, 0);
+        assert_eq!(envelope.envelope.population(), 0);
+    }
+}
@@ -0,0 +1,412 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Undo, redo and jumping to earlier generations.
+//!
+//! Since nodes are immutable, a past state of a Life grid is just its generation, root node and
+//! origin.
+//! Every state a Life grid is stepped from or moved away from is saved, and stepping an earlier
+//! state forward gives the same later states again, so undone states stay valid until the cells are
+//! changed.
+
+use crate::{node::NodeId, Life, Position};
+use std::collections::VecDeque;
+
+/// The default number of states saved in the history of a Life grid.
+const DEFAULT_HISTORY_LIMIT: usize = 64;
+
+/// A saved state of a Life grid.
+#[derive(Clone, Copy, Debug)]
+struct Snapshot {
+    generation: u128,
+    root: NodeId,
+    origin: Position,
+}
+
+/// The saved states of a Life grid.
+#[derive(Clone, Debug)]
+pub(super) struct History {
+    /// The state the Life grid was first stepped from, which is never dropped.
+    start: Option<Snapshot>,
+    /// Saved states sorted by generation.
+    snapshots: VecDeque<Snapshot>,
+    /// The maximum number of saved states, not counting the start.
+    limit: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            snapshots: VecDeque::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+
+    /// Saves a state, replacing any saved state with the same generation.
+    ///
+    /// The oldest saved states are dropped if there are too many.
+    fn record(&mut self, snapshot: Snapshot) {
+        self.insert(snapshot);
+        while self.snapshots.len() > self.limit {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Saves a state, replacing any saved state with the same generation, without dropping any
+    /// saved states.
+    fn insert(&mut self, snapshot: Snapshot) {
+        if self.start.is_none() {
+            self.start = Some(snapshot);
+        }
+        let index = self
+            .snapshots
+            .iter()
+            .position(|saved| saved.generation >= snapshot.generation)
+            .unwrap_or(self.snapshots.len());
+        match self.snapshots.get_mut(index) {
+            Some(saved) if saved.generation == snapshot.generation => *saved = snapshot,
+            _ => self.snapshots.insert(index, snapshot),
+        }
+    }
+
+    /// Drops every saved state from the given generation onwards.
+    fn truncate(&mut self, generation: u128) {
+        if self
+            .start
+            .is_some_and(|start| start.generation >= generation)
+        {
+            self.start = None;
+        }
+        self.snapshots
+            .retain(|snapshot| snapshot.generation < generation);
+    }
+
+    /// Returns every saved state, including the start.
+    fn all(&self) -> impl Iterator<Item = Snapshot> + '_ {
+        self.start.iter().chain(&self.snapshots).copied()
+    }
+
+    /// Returns the root nodes of every saved state.
+    pub fn roots(&self) -> Vec<NodeId> {
+        self.all().map(|snapshot| snapshot.root).collect()
+    }
+
+    /// Replaces the root nodes of every saved state, in the order returned by `roots`.
+    pub fn set_roots(&mut self, roots: &[NodeId]) {
+        let mut roots = roots.iter().copied();
+        for snapshot in self.start.iter_mut().chain(&mut self.snapshots) {
+            snapshot.root = roots.next().unwrap();
+        }
+    }
+}
+
+impl Life {
+    /// Returns the maximum number of past and undone states saved in the history.
+    ///
+    /// The default limit is 64. The state the Life grid was first stepped from is always saved in
+    /// addition to these.
+    pub fn history_limit(&self) -> usize {
+        self.history.limit
+    }
+
+    /// Sets the maximum number of past and undone states saved in the history.
+    ///
+    /// When there are too many states, the ones with the earliest generations are dropped. The
+    /// state left by undoing, redoing or going to another generation doesn't count towards the
+    /// limit until the Life grid is stepped again, so every saved state can be undone.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.limit = limit;
+        while self.history.snapshots.len() > limit {
+            self.history.snapshots.pop_front();
+        }
+    }
+
+    /// Goes back to the latest saved state before the current generation.
+    ///
+    /// Returns false if there is no earlier state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// let cells = life.get_alive_cells();
+    ///
+    /// life.step();
+    /// assert!(life.undo());
+    /// assert_eq!(life.generation(), 0);
+    /// assert_eq!(life.get_alive_cells(), cells);
+    /// assert!(!life.undo());
+    ///
+    /// assert!(life.redo());
+    /// assert_eq!(life.generation(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn undo(&mut self) -> bool {
+        let generation = self.generation;
+        let previous = self
+            .history
+            .all()
+            .filter(|snapshot| snapshot.generation < generation)
+            .max_by_key(|snapshot| snapshot.generation);
+        self.go_to_snapshot(previous)
+    }
+
+    /// Goes forward to the earliest saved state after the current generation.
+    ///
+    /// Returns false if there is no later state.
+    pub fn redo(&mut self) -> bool {
+        let generation = self.generation;
+        let next = self
+            .history
+            .all()
+            .filter(|snapshot| snapshot.generation > generation)
+            .min_by_key(|snapshot| snapshot.generation);
+        self.go_to_snapshot(next)
+    }
+
+    /// Goes back to the state the Life grid was first stepped from.
+    ///
+    /// Does nothing if the Life grid has never been stepped.
+    pub fn reset_to_start(&mut self) {
+        let start = self.history.start;
+        self.go_to_snapshot(start);
+    }
+
+    /// Goes to the given generation, stepping forward from the latest saved state at or before it.
+    ///
+    /// The step size is unchanged afterwards.
+    ///
+    /// Returns false, leaving the Life grid unchanged, if the generation is before every saved state
+    /// and the current generation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// life.set_step_log_2(4);
+    /// life.step();
+    /// life.step();
+    ///
+    /// assert!(life.goto_generation(21));
+    /// assert_eq!(life.generation(), 21);
+    /// assert_eq!(life.step_log_2(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn goto_generation(&mut self, generation: u128) -> bool {
+        let current = self.snapshot();
+        let nearest = self
+            .history
+            .all()
+            .chain(Some(current))
+            .filter(|snapshot| snapshot.generation <= generation)
+            .max_by_key(|snapshot| snapshot.generation);
+        if !self.go_to_snapshot(nearest) {
+            return false;
+        }
+
+        let step_log_2 = self.step_log_2;
+        while self.generation < generation {
+            let remaining = generation - self.generation;
+            // the largest power of two steps that doesn't overshoot
+            let largest_step_log_2 = (127 - remaining.leading_zeros()) as u8;
+            self.set_step_log_2(step_log_2.min(largest_step_log_2));
+            self.advance();
+        }
+        self.set_step_log_2(step_log_2);
+        true
+    }
+
+    /// Saves the current state in the history.
+    pub(super) fn record_history(&mut self) {
+        let snapshot = self.snapshot();
+        self.history.record(snapshot);
+    }
+
+    /// Forgets the saved states that are no longer reachable after the cells have been changed.
+    pub(super) fn truncate_history(&mut self) {
+        self.history.truncate(self.generation);
+    }
+
+    /// Forgets every saved state.
+    pub(super) fn clear_history(&mut self) {
+        self.history.truncate(0);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            generation: self.generation,
+            root: self.root,
+            origin: self.origin,
+        }
+    }
+
+    /// Goes to the given state, after saving the current state. Returns false if there is no
+    /// state to go to.
+    ///
+    /// The current state is saved without dropping the oldest saved state, which may be the one
+    /// being gone to.
+    fn go_to_snapshot(&mut self, snapshot: Option<Snapshot>) -> bool {
+        match snapshot {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.history.insert(current);
+                self.generation = snapshot.generation;
+                self.root = snapshot.root;
+                self.origin = snapshot.origin;
+                self.update_bounding_box();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Life, Position};
+
+    #[test]
+    fn undo_redo() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_step_log_2(3);
+        let mut states = vec![life.get_alive_cells()];
+        for _ in 0..5 {
+            life.step();
+            states.push(life.get_alive_cells());
+        }
+
+        for generation in (0..5).rev() {
+            assert!(life.undo());
+            assert_eq!(life.generation(), generation * 8);
+            assert_eq!(life.get_alive_cells(), states[generation as usize]);
+        }
+        assert!(!life.undo());
+
+        for generation in 1..=5 {
+            assert!(life.redo());
+            assert_eq!(life.generation(), generation * 8);
+            assert_eq!(life.get_alive_cells(), states[generation as usize]);
+        }
+        assert!(!life.redo());
+
+        life.reset_to_start();
+        assert_eq!(life.generation(), 0);
+        assert_eq!(life.get_alive_cells(), states[0]);
+    }
+
+    #[test]
+    fn goto_generation() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        let start = life.clone();
+        life.set_step_log_2(5);
+        life.step();
+        life.step();
+
+        for &generation in &[37, 12, 64, 100] {
+            assert!(life.goto_generation(generation));
+            let mut expected = start.clone();
+            while expected.generation() < generation {
+                expected.step();
+            }
+            assert_eq!(life.generation(), generation);
+            assert_eq!(life.get_alive_cells(), expected.get_alive_cells());
+        }
+        assert_eq!(life.step_log_2(), 5);
+    }
+
+    #[test]
+    fn step_records_once() {
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        for _ in 0..3 {
+            life.step();
+        }
+        let generations = life
+            .history
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.generation)
+            .collect::<Vec<_>>();
+        assert_eq!(generations, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn goto_generation_before_history() {
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        life.set_step_log_2(4);
+        life.step();
+
+        // the history isn't saved with the state
+        let mut bytes = vec![];
+        life.write_state(&mut bytes).unwrap();
+        let mut resumed = Life::read_state(&bytes[..]).unwrap();
+        let cells = resumed.get_alive_cells();
+        assert!(!resumed.goto_generation(3));
+        assert_eq!(resumed.generation(), 16);
+        assert_eq!(resumed.get_alive_cells(), cells);
+    }
+
+    #[test]
+    fn edit_drops_later_states() {
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        life.step();
+        life.step();
+        assert!(life.undo());
+
+        life.set_cell_alive(Position::new(10, 10));
+        assert!(!life.redo());
+        assert!(life.undo());
+        assert_eq!(life.generation(), 0);
+        assert_eq!(life.population(), 5);
+    }
+
+    #[test]
+    fn history_limit() {
+        let mut life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        life.set_history_limit(3);
+        for _ in 0..10 {
+            life.step();
+        }
+
+        // the three latest states and the start
+        let mut generations = vec![];
+        while life.undo() {
+            generations.push(life.generation());
+        }
+        assert_eq!(generations, vec![9, 8, 7, 0]);
+
+        // and back again, including the state undone first
+        while life.redo() {
+            generations.push(life.generation());
+        }
+        assert_eq!(generations, vec![9, 8, 7, 0, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn memory_limit_keeps_history() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_memory_limit(Some(0));
+        life.set_step_log_2(4);
+        let start = life.get_alive_cells();
+        let mut expected = life.clone();
+        for _ in 0..5 {
+            life.step();
+        }
+        expected.step();
+        expected.step();
+
+        life.reset_to_start();
+        assert_eq!(life.get_alive_cells(), start);
+        life.redo();
+        life.redo();
+        assert_eq!(life.generation(), 32);
+        assert_eq!(life.get_alive_cells(), expected.get_alive_cells());
+    }
+}
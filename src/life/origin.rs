@@ -0,0 +1,347 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Moving the root node to follow the alive cells.
+//!
+//! The root node of a Life grid is centered on its origin rather than on the position (0, 0), so
+//! it can shrink around the alive cells wherever they are, and spaceships can travel indefinitely
+//! without the root node growing. The corners of a level `n` root node are kept at multiples of
+//! `1 << (n - 2)`, so every level `n - 2` node lines up with the same squares of cells whatever
+//! the origin is.
+
+use crate::{
+    life::{Life, INITIAL_LEVEL},
+    node::{Level, NodeId, NodeTemplate, LEAF_LEVEL},
+    BoundingBox, Position,
+};
+use std::convert::TryFrom;
+
+/// A square of cells, given by its upper left corner and its level.
+///
+/// Coordinates are 128 bits wide, so squares around a level 64 root node can be described.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct Square {
+    pub x: i128,
+    pub y: i128,
+    pub level: u8,
+}
+
+impl Square {
+    /// Returns the side length of the square.
+    pub fn side_len(self) -> i128 {
+        1 << self.level
+    }
+
+    /// Returns the northwest, northeast, southwest and southeast quadrants of the square.
+    pub fn quadrants(self) -> [Square; 4] {
+        let half = self.side_len() / 2;
+        let level = self.level - 1;
+        [
+            Square {
+                x: self.x,
+                y: self.y,
+                level,
+            },
+            Square {
+                x: self.x + half,
+                y: self.y,
+                level,
+            },
+            Square {
+                x: self.x,
+                y: self.y + half,
+                level,
+            },
+            Square {
+                x: self.x + half,
+                y: self.y + half,
+                level,
+            },
+        ]
+    }
+
+    /// Returns true if the other square lies entirely within this one.
+    fn contains(self, other: Square) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.side_len() <= self.x + self.side_len()
+            && other.y + other.side_len() <= self.y + self.side_len()
+    }
+
+    /// Returns true if the squares have any cells in common.
+    fn intersects(self, other: Square) -> bool {
+        other.x < self.x + self.side_len()
+            && self.x < other.x + other.side_len()
+            && other.y < self.y + self.side_len()
+            && self.y < other.y + other.side_len()
+    }
+
+    /// Returns true if the square contains the bounding box.
+    fn contains_bounding_box(self, bounding_box: BoundingBox) -> bool {
+        i128::from(bounding_box.upper_left.x) >= self.x
+            && i128::from(bounding_box.upper_left.y) >= self.y
+            && i128::from(bounding_box.lower_right.x) < self.x + self.side_len()
+            && i128::from(bounding_box.lower_right.y) < self.y + self.side_len()
+    }
+}
+
+/// Where the cells of a square are found in the root node of a Life grid.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Location {
+    /// The square lies outside the root node, so all its cells are dead.
+    Outside,
+    /// The square is exactly the given node.
+    Node(NodeId),
+    /// The square is smaller than a leaf, and its upper left cell is `x` columns and `y` rows from
+    /// the upper left of the given leaf.
+    Leaf { id: NodeId, x: i64, y: i64 },
+    /// The square is not a single node, so it must be split into its quadrants.
+    Split,
+}
+
+impl Life {
+    /// Returns the square covered by the root node.
+    pub(super) fn root_square(&self) -> Square {
+        let level = self.root.level(&self.store).0;
+        let half = 1 << (level - 1);
+        Square {
+            x: i128::from(self.origin.x) - half,
+            y: i128::from(self.origin.y) - half,
+            level,
+        }
+    }
+
+    /// Finds the cells of the given square in the root node.
+    pub(super) fn locate(&self, square: Square) -> Location {
+        let root_square = self.root_square();
+        if !root_square.intersects(square) {
+            return Location::Outside;
+        }
+        if !root_square.contains(square)
+            || (square.x - root_square.x) % square.side_len() != 0
+            || (square.y - root_square.y) % square.side_len() != 0
+        {
+            return Location::Split;
+        }
+
+        let mut id = self.root;
+        let mut corner = root_square;
+        while corner.level > square.level.max(LEAF_LEVEL.0) {
+            let half = corner.side_len() / 2;
+            let east = square.x >= corner.x + half;
+            let south = square.y >= corner.y + half;
+            let [nw, ne, sw, se] = corner.quadrants();
+            let (child, quadrant) = match (east, south) {
+                (false, false) => (id.nw(&self.store), nw),
+                (true, false) => (id.ne(&self.store), ne),
+                (false, true) => (id.sw(&self.store), sw),
+                (true, true) => (id.se(&self.store), se),
+            };
+            id = child;
+            corner = quadrant;
+        }
+
+        if corner.level == square.level {
+            Location::Node(id)
+        } else {
+            Location::Leaf {
+                id,
+                x: (square.x - corner.x) as i64,
+                y: (square.y - corner.y) as i64,
+            }
+        }
+    }
+
+    /// Creates a node with the cells of the given square, which must be at least as large as a
+    /// leaf and have its corners at multiples of the side length of a leaf.
+    fn create_square(&mut self, square: Square) -> NodeId {
+        match self.locate(square) {
+            Location::Outside => self.store.create_empty(Level(square.level)),
+            Location::Node(id) => id,
+            Location::Leaf { .. } => panic!("square {:?} is smaller than a leaf", square),
+            Location::Split => {
+                let [nw, ne, sw, se] = square.quadrants();
+                let nw = self.create_square(nw);
+                let ne = self.create_square(ne);
+                let sw = self.create_square(sw);
+                let se = self.create_square(se);
+                self.store.create_interior(NodeTemplate { nw, ne, sw, se })
+            }
+        }
+    }
+
+    /// Moves the root node to the given square, centered on the given origin.
+    fn move_root(&mut self, square: Square, origin: Position) {
+        self.root = self.create_square(square);
+        self.origin = origin;
+    }
+
+    /// Expands the root node to twice its side length.
+    ///
+    /// The old root node is kept in the center of the new one unless that would put the corners of
+    /// the new root node out of line, in which case the new root node is moved towards the
+    /// position (0, 0).
+    pub(super) fn expand(&mut self) {
+        let level = self.root.level(&self.store).0;
+        let quarter = 1 << (level - 1);
+        let center = |origin: i64| {
+            let origin = i128::from(origin);
+            match origin.rem_euclid(quarter) {
+                0 => origin,
+                offset if origin > 0 => origin - offset,
+                offset => origin + quarter - offset,
+            }
+        };
+        let x = center(self.origin.x);
+        let y = center(self.origin.y);
+
+        if x == i128::from(self.origin.x) && y == i128::from(self.origin.y) {
+            self.root = self.root.expand(&mut self.store);
+        } else {
+            let half = quarter * 2;
+            let square = Square {
+                x: x - half,
+                y: y - half,
+                level: level + 1,
+            };
+            self.move_root(square, Position::new(x as i64, y as i64));
+        }
+    }
+
+    /// Expands the root node until it contains the given bounding box.
+    pub(super) fn expand_to_fit(&mut self, bounding_box: BoundingBox) {
+        while !self.root_square().contains_bounding_box(bounding_box) {
+            self.expand();
+        }
+    }
+
+    /// Shrinks the root node of a Life grid on a plane to the smallest node that can be advanced by
+    /// the step size of the store, moving the origin to the center of the alive cells.
+    ///
+    /// As in `pad`, the alive cells must lie within the central square of the new root node whose
+    /// side length is a quarter of that of the root node.
+    pub(super) fn shrink(&mut self) {
+        let level = self.root.level(&self.store).0;
+        let bounding_box = match self.bounding_box {
+            Some(bounding_box) => bounding_box,
+            None => {
+                if level > INITIAL_LEVEL.0 {
+                    self.root = self.store.create_empty(INITIAL_LEVEL);
+                }
+                return;
+            }
+        };
+
+        let min_level = INITIAL_LEVEL.0.max(self.store.step_log_2() + 2);
+        for new_level in min_level..level {
+            let quarter = 1i128 << (new_level - 2);
+            // the nearest multiple of a quarter of the side length, if the cells are close enough
+            let center = |min: i64, max: i64| {
+                let center = (i128::from(min) + quarter / 2).div_euclid(quarter) * quarter;
+                if i128::from(max) < center + quarter / 2 {
+                    i64::try_from(center).ok()
+                } else {
+                    None
+                }
+            };
+            let x = center(bounding_box.upper_left.x, bounding_box.lower_right.x);
+            let y = center(bounding_box.upper_left.y, bounding_box.lower_right.y);
+            if let (Some(x), Some(y)) = (x, y) {
+                let square = Square {
+                    x: i128::from(x) - quarter * 2,
+                    y: i128::from(y) - quarter * 2,
+                    level: new_level,
+                };
+                self.move_root(square, Position::new(x, y));
+                return;
+            }
+        }
+    }
+
+    /// Converts a position in the Life grid to a position relative to the center of the root node.
+    pub(super) fn to_root(&self, position: Position) -> Position {
+        position.offset(-self.origin.x, -self.origin.y)
+    }
+
+    /// Recalculates the bounding box of the Life grid from the root node.
+    pub(super) fn update_bounding_box(&mut self) {
+        let origin = self.origin;
+        self.bounding_box = self
+            .root
+            .bounding_box(&self.store)
+            .map(|bounding_box| bounding_box.offset(origin.x, origin.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Life, Position};
+
+    #[test]
+    fn spaceship_keeps_root_small() {
+        let mut life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        let cells = life.get_alive_cells();
+        let level = life.root.level(&life.store);
+        life.set_step_log_2(4);
+
+        let mut max_level = level;
+        for _ in 0..1 << 10 {
+            life.step();
+            max_level = max_level.max(life.root.level(&life.store));
+        }
+        // 4 cells down and to the right every 16 generations
+        let distance = 1 << 12;
+        assert!(max_level.0 <= level.0 + 1);
+        assert_eq!(
+            life.get_alive_cells(),
+            cells
+                .iter()
+                .map(|position| position.offset(distance, distance))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shrink_after_debris_dies() {
+        let mut life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        let level = life.root.level(&life.store);
+        life.set_cell_alive(Position::new(1 << 40, -(1 << 40)));
+        assert!(life.root.level(&life.store).0 > 40);
+
+        // the lone cell dies
+        life.step();
+        life.step();
+        assert_eq!(life.population(), 5);
+        assert!(life.root.level(&life.store) <= level);
+    }
+
+    #[test]
+    fn edit_after_moving() {
+        let mut life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        life.set_step_log_2(8);
+        life.step();
+        life.step();
+
+        let mut expected = life.get_alive_cells();
+        for &position in &[
+            Position::new(0, 0),
+            Position::new(-1000, 3),
+            Position::new(i64::MAX, i64::MIN),
+        ] {
+            life.set_cell_alive(position);
+            expected.push(position);
+        }
+        expected.sort_unstable_by_key(|position| (position.y, position.x));
+
+        let mut cells = life.get_alive_cells();
+        cells.sort_unstable_by_key(|position| (position.y, position.x));
+        assert_eq!(cells, expected);
+        assert!(life.contains_alive_cells(crate::BoundingBox::new(
+            Position::new(-1000, 3),
+            Position::new(-1000, 3)
+        )));
+    }
+}
@@ -0,0 +1,206 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Splitting oscillators into their rotor and stator, and measuring how active they are.
+
+use crate::{Life, Position};
+use std::collections::{HashMap, HashSet};
+
+/// The rotor, stator and activity of an oscillator over one full period.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OscillatorAnalysis {
+    /// The number of generations after which the oscillator repeats.
+    pub period: u128,
+    /// The cells that are alive in every generation, sorted.
+    pub stator: Vec<Position>,
+    /// The cells that are alive in some generations but not others, sorted.
+    pub rotor: Vec<Position>,
+    /// The cells of the rotor that only repeat after the full period, sorted.
+    pub strict_rotor: Vec<Position>,
+    /// The average number of cells that change from one generation to the next.
+    pub average_heat: f64,
+    /// The largest number of cells that change from one generation to the next.
+    pub max_heat: usize,
+}
+
+impl OscillatorAnalysis {
+    /// Returns the size of the rotor relative to all cells that are ever alive.
+    pub fn volatility(&self) -> f64 {
+        self.rotor.len() as f64 / (self.rotor.len() + self.stator.len()) as f64
+    }
+
+    /// Returns the size of the strict rotor relative to all cells that are ever alive.
+    pub fn strict_volatility(&self) -> f64 {
+        self.strict_rotor.len() as f64 / (self.rotor.len() + self.stator.len()) as f64
+    }
+
+    /// Returns a Life grid containing the stator.
+    pub fn stator_pattern(&self) -> Life {
+        pattern(&self.stator)
+    }
+
+    /// Returns a Life grid containing the rotor.
+    pub fn rotor_pattern(&self) -> Life {
+        pattern(&self.rotor)
+    }
+
+    /// Returns a Life grid containing the strict rotor.
+    pub fn strict_rotor_pattern(&self) -> Life {
+        pattern(&self.strict_rotor)
+    }
+}
+
+/// Creates a Life grid with the given cells alive.
+fn pattern(cells: &[Position]) -> Life {
+    let mut life = Life::new();
+    for &position in cells {
+        life.set_cell_alive(position);
+    }
+    life
+}
+
+/// Returns the smallest number of generations after which the states of a cell repeat.
+fn cell_period(states: &[bool]) -> usize {
+    let period = states.len();
+    (1..period)
+        .filter(|&divisor| period.is_multiple_of(divisor))
+        .find(|&divisor| {
+            (0..period).all(|index| states[index] == states[(index + divisor) % period])
+        })
+        .unwrap_or(period)
+}
+
+impl Life {
+    /// Analyzes the pattern as an oscillator with the given period, advancing a copy of the Life
+    /// grid one generation at a time through one full period.
+    ///
+    /// Returns `None` if the period is zero or the pattern isn't the same after the period, as is
+    /// the case for spaceships.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // blinker
+    /// let life = smeagol::Life::from_rle_pattern(b"3o!")?;
+    /// let analysis = life.analyze_oscillator(2).unwrap();
+    /// assert_eq!(analysis.stator.len(), 1);
+    /// assert_eq!(analysis.rotor.len(), 4);
+    /// assert_eq!(analysis.max_heat, 4);
+    /// assert_eq!(analysis.volatility(), 0.8);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze_oscillator(&self, period: u128) -> Option<OscillatorAnalysis> {
+        if period == 0 {
+            return None;
+        }
+        let mut life = self.clone();
+        life.set_step_log_2(0);
+
+        let phases = period as usize;
+        let mut states = HashMap::<Position, Vec<bool>>::new();
+        let mut heats = Vec::with_capacity(phases);
+        let mut previous = life.get_alive_cells().into_iter().collect::<HashSet<_>>();
+        for phase in 0..phases {
+            for &position in &previous {
+                states
+                    .entry(position)
+                    .or_insert_with(|| vec![false; phases])[phase] = true;
+            }
+            life.advance();
+            let next = life.get_alive_cells().into_iter().collect::<HashSet<_>>();
+            heats.push(previous.symmetric_difference(&next).count());
+            previous = next;
+        }
+        if !life.same_pattern(self) {
+            return None;
+        }
+
+        let mut stator = vec![];
+        let mut rotor = vec![];
+        let mut strict_rotor = vec![];
+        for (position, states) in states {
+            if states.iter().all(|&alive| alive) {
+                stator.push(position);
+                continue;
+            }
+            if cell_period(&states) == phases {
+                strict_rotor.push(position);
+            }
+            rotor.push(position);
+        }
+        stator.sort_unstable();
+        rotor.sort_unstable();
+        strict_rotor.sort_unstable();
+
+        Some(OscillatorAnalysis {
+            period,
+            stator,
+            rotor,
+            strict_rotor,
+            average_heat: heats.iter().sum::<usize>() as f64 / phases as f64,
+            max_heat: heats.iter().copied().max().unwrap_or(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn still_life() {
+        let life = Life::from_rle_pattern(b"2o$2o!").unwrap();
+        let analysis = life.analyze_oscillator(1).unwrap();
+        assert_eq!(analysis.stator.len(), 4);
+        assert!(analysis.rotor.is_empty());
+        assert_eq!(analysis.average_heat, 0.0);
+        assert_eq!(analysis.stator_pattern().population(), 4);
+        assert_eq!(analysis.rotor_pattern().population(), 0);
+    }
+
+    #[test]
+    fn pentadecathlon() {
+        let life = Life::from_rle_file("./assets/pentadecathlon.rle").unwrap();
+        let analysis = life.analyze_oscillator(15).unwrap();
+        assert!(analysis.stator.is_empty());
+        assert_eq!(analysis.volatility(), 1.0);
+        assert_eq!(analysis.strict_volatility(), 1.0);
+        assert_eq!(analysis.average_heat, 22.4);
+        assert_eq!(analysis.max_heat, 54);
+    }
+
+    #[test]
+    fn strict_rotor() {
+        // a blinker next to a figure eight, so the blinker cells only have period 2
+        let mut life = Life::from_rle_file("./assets/figureeight.rle").unwrap();
+        let analysis = life.analyze_oscillator(8).unwrap();
+        assert_eq!(analysis.rotor, analysis.strict_rotor);
+
+        for x in 20..23 {
+            life.set_cell_alive(Position::new(x, 0));
+        }
+        let with_blinker = life.analyze_oscillator(8).unwrap();
+        assert_eq!(with_blinker.rotor.len(), analysis.rotor.len() + 4);
+        assert_eq!(with_blinker.strict_rotor, analysis.strict_rotor);
+    }
+
+    #[test]
+    fn not_an_oscillator() {
+        let life = Life::from_rle_pattern(b"bob$2bo$3o!").unwrap();
+        assert_eq!(life.analyze_oscillator(4), None);
+        assert_eq!(life.analyze_oscillator(0), None);
+    }
+
+    #[test]
+    fn cell_periods() {
+        assert_eq!(cell_period(&[true, false, true, false]), 2);
+        assert_eq!(cell_period(&[true, true, false, false]), 4);
+        assert_eq!(cell_period(&[false, false, true]), 3);
+    }
+}
@@ -0,0 +1,360 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Detecting oscillators and spaceships.
+//!
+//! Every generation, the alive cells are moved so that their bounding box starts in the corner of
+//! a node in a separate store. Since nodes are hash-consed, a pattern that returns to an earlier
+//! shape gets the same node ID, wherever it has moved to.
+
+use crate::{
+    life::history::History,
+    node::{Level, Node, NodeId, NodeTemplate, Store, LEAF_LEVEL},
+    Life, Position,
+};
+use std::{collections::HashMap, fmt};
+
+/// How a pattern repeats itself.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Periodicity {
+    /// The number of generations after which the pattern repeats.
+    pub period: u128,
+    /// The number of cells the pattern moves to the right every period.
+    pub dx: i64,
+    /// The number of cells the pattern moves down every period.
+    pub dy: i64,
+    /// The first generation of the repeating part of the evolution.
+    pub start: u128,
+}
+
+impl Periodicity {
+    /// Returns true if the pattern doesn't move.
+    pub fn is_stationary(&self) -> bool {
+        self.dx == 0 && self.dy == 0
+    }
+}
+
+/// The direction a spaceship travels in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// Horizontally or vertically.
+    Orthogonal,
+    /// Along a diagonal.
+    Diagonal,
+    /// Any other direction, like a knightship.
+    Oblique,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Orthogonal => "orthogonal",
+            Direction::Diagonal => "diagonal",
+            Direction::Oblique => "oblique",
+        })
+    }
+}
+
+/// The velocity of a spaceship.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spaceship {
+    /// The number of cells the spaceship moves to the right every period.
+    pub dx: i64,
+    /// The number of cells the spaceship moves down every period.
+    pub dy: i64,
+    /// The number of generations after which the spaceship repeats.
+    pub period: u128,
+}
+
+impl Spaceship {
+    /// Returns the direction the spaceship travels in.
+    pub fn direction(&self) -> Direction {
+        let (x, y) = (self.dx.unsigned_abs(), self.dy.unsigned_abs());
+        if x == 0 || y == 0 {
+            Direction::Orthogonal
+        } else if x == y {
+            Direction::Diagonal
+        } else {
+            Direction::Oblique
+        }
+    }
+
+    /// Returns the speed of the spaceship in `c` notation.
+    ///
+    /// Orthogonal and diagonal spaceships are described by how many cells they move along each
+    /// axis every period as a fraction in lowest terms, like `2c/7` or `c/4`, so a spaceship moving
+    /// two cells every four generations has a speed of `c/2`. Oblique spaceships are described by
+    /// how many cells they move along both axes, largest first, and their period, like `(2,1)c/6`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let glider = smeagol::Spaceship {
+    ///     dx: 1,
+    ///     dy: 1,
+    ///     period: 4,
+    /// };
+    /// assert_eq!(glider.speed(), "c/4");
+    /// assert_eq!(glider.to_string(), "c/4 diagonal");
+    /// ```
+    pub fn speed(&self) -> String {
+        let (x, y) = (self.dx.unsigned_abs(), self.dy.unsigned_abs());
+        match self.direction() {
+            Direction::Orthogonal | Direction::Diagonal => {
+                let distance = u128::from(x.max(y));
+                let divisor = gcd(distance, self.period);
+                match (distance / divisor, self.period / divisor) {
+                    (1, period) => format!("c/{}", period),
+                    (distance, period) => format!("{}c/{}", distance, period),
+                }
+            }
+            Direction::Oblique => format!("({},{})c/{}", x.max(y), x.min(y), self.period),
+        }
+    }
+}
+
+/// Returns the greatest common divisor of two numbers, or the other number if one of them is zero.
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl fmt::Display for Spaceship {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.speed(), self.direction())
+    }
+}
+
+impl Life {
+    /// Finds the period of the pattern, advancing a copy of the Life grid one generation at a time
+    /// for at most the given number of generations.
+    ///
+    /// Returns `None` if the pattern doesn't return to an earlier shape in time. Still lifes have a
+    /// period of 1, and spaceships have a displacement. A pattern that settles down has a start
+    /// later than the current generation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let glider = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// let periodicity = glider.detect_period(100).unwrap();
+    /// assert_eq!(periodicity.period, 4);
+    /// assert_eq!((periodicity.dx, periodicity.dy), (1, 1));
+    /// assert_eq!(periodicity.start, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_period(&self, max_generations: u128) -> Option<Periodicity> {
+        let mut life = self.working_copy();
+        life.set_step_log_2(0);
+
+        let mut shapes = Store::new();
+        let mut seen = HashMap::<NodeId, (u128, Position)>::new();
+        for generation in 0..=max_generations {
+            if generation > 0 {
+                life.advance();
+            }
+            let (shape, upper_left) = life.shape(&mut shapes);
+            if let Some(&(start, first_upper_left)) = seen.get(&shape) {
+                return Some(Periodicity {
+                    period: life.generation - start,
+                    dx: upper_left.x - first_upper_left.x,
+                    dy: upper_left.y - first_upper_left.y,
+                    start,
+                });
+            }
+            seen.insert(shape, (life.generation, upper_left));
+        }
+        None
+    }
+
+    /// Finds the velocity of the pattern if it is a spaceship, advancing a copy of the Life grid
+    /// one generation at a time for at most the given number of generations.
+    ///
+    /// Returns `None` if the pattern doesn't repeat in time or repeats without moving.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let life = smeagol::Life::from_rle_file("./assets/sirrobin.rle")?;
+    /// let spaceship = life.detect_spaceship(100).unwrap();
+    /// assert_eq!(spaceship.speed(), "(2,1)c/6");
+    /// assert_eq!(spaceship.direction(), smeagol::Direction::Oblique);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_spaceship(&self, max_generations: u128) -> Option<Spaceship> {
+        self.detect_period(max_generations)
+            .filter(|periodicity| !periodicity.is_stationary())
+            .map(|periodicity| Spaceship {
+                dx: periodicity.dx,
+                dy: periodicity.dy,
+                period: periodicity.period,
+            })
+    }
+
+    /// Returns a Life grid with the same cells, topology and generation, whose store only contains
+    /// the nodes of the root node. The history, recorded samples and evolution results are left
+    /// behind.
+    fn working_copy(&self) -> Life {
+        let mut store = Store::new();
+        let root = copy_node(&self.store, self.root, &mut store, &mut HashMap::new());
+        Life {
+            root,
+            store,
+            origin: self.origin,
+            generation: self.generation,
+            bounding_box: self.bounding_box,
+            topology: self.topology,
+            step_log_2: self.step_log_2,
+            #[cfg(feature = "parallel")]
+            parallel: self.parallel,
+            history: History::new(),
+            recorder: None,
+        }
+    }
+
+    /// Returns a node in the given store with the alive cells moved so that their bounding box
+    /// starts at the upper left corner of the node, and the upper left corner of the bounding box.
+    pub(super) fn shape(&self, store: &mut Store) -> (NodeId, Position) {
+        let bounding_box = match self.bounding_box {
+            Some(bounding_box) => bounding_box,
+            None => return (store.create_empty(LEAF_LEVEL), Position::new(0, 0)),
+        };
+        let upper_left = bounding_box.upper_left;
+        let lower_right = bounding_box.lower_right;
+        let side_len = (i128::from(lower_right.x) - i128::from(upper_left.x))
+            .max(i128::from(lower_right.y) - i128::from(upper_left.y))
+            + 1;
+        let level = (LEAF_LEVEL.0..64)
+            .find(|&level| 1 << level >= side_len)
+            .unwrap_or(64);
+
+        let node = store.create_empty(Level(level));
+        let min_coord = node.min_coord(store);
+        let alive_cells = self.get_alive_cells().into_iter().map(|position| {
+            Position::new(
+                min_coord.wrapping_add(position.x.wrapping_sub(upper_left.x)),
+                min_coord.wrapping_add(position.y.wrapping_sub(upper_left.y)),
+            )
+        });
+        (node.set_cells_alive(store, alive_cells), upper_left)
+    }
+}
+
+/// Copies a node and its descendants from one store into another, returning the ID of the copy.
+fn copy_node(
+    from: &Store,
+    id: NodeId,
+    to: &mut Store,
+    copies: &mut HashMap<NodeId, NodeId>,
+) -> NodeId {
+    if let Some(&copy) = copies.get(&id) {
+        return copy;
+    }
+    let copy = match from.node(id) {
+        Node::Leaf { grid } => to.create_leaf(grid),
+        Node::StateLeaf { .. } => unreachable!(),
+        Node::Interior { nw, ne, sw, se, .. } => {
+            let nw = copy_node(from, nw, to, copies);
+            let ne = copy_node(from, ne, to, copies);
+            let sw = copy_node(from, sw, to, copies);
+            let se = copy_node(from, se, to, copies);
+            to.create_interior(NodeTemplate { nw, ne, sw, se })
+        }
+    };
+    copies.insert(id, copy);
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn still_life() {
+        // block
+        let life = Life::from_rle_pattern(b"2o$2o!").unwrap();
+        let periodicity = life.detect_period(10).unwrap();
+        assert_eq!(periodicity.period, 1);
+        assert!(periodicity.is_stationary());
+    }
+
+    #[test]
+    fn settles_down() {
+        // three cells of a block, which fills in after a generation
+        let mut life = Life::from_rle_pattern(b"2o$o!").unwrap();
+        let periodicity = life.detect_period(10).unwrap();
+        assert_eq!(periodicity.start, 1);
+        assert_eq!(periodicity.period, 1);
+
+        // the start is counted from the generation of the Life grid
+        life.set_step_log_2(2);
+        life.step();
+        assert_eq!(life.detect_period(10).unwrap().start, 4);
+    }
+
+    #[test]
+    fn spaceship() {
+        let life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        let periodicity = life.detect_period(10).unwrap();
+        assert_eq!(periodicity.period, 6);
+        assert_eq!((periodicity.dx, periodicity.dy), (-1, -2));
+    }
+
+    #[test]
+    fn working_copy() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_step_log_2(3);
+        for _ in 0..10 {
+            life.step();
+        }
+
+        let copy = life.working_copy();
+        assert_eq!(copy.generation(), life.generation());
+        assert_eq!(copy.get_alive_cells(), life.get_alive_cells());
+        assert!(copy.history.roots().is_empty());
+        assert!(copy.store.len() < life.store.len());
+    }
+
+    #[test]
+    fn speeds() {
+        for &(dx, dy, period, speed, direction) in &[
+            // a lightweight spaceship
+            (0, -2, 4, "c/2", Direction::Orthogonal),
+            (4, 0, 6, "2c/3", Direction::Orthogonal),
+            (-3, 0, 7, "3c/7", Direction::Orthogonal),
+            (-1, 1, 4, "c/4", Direction::Diagonal),
+            (1, -2, 6, "(2,1)c/6", Direction::Oblique),
+            (5, 6, 100, "(6,5)c/100", Direction::Oblique),
+        ] {
+            let spaceship = Spaceship { dx, dy, period };
+            assert_eq!(spaceship.speed(), speed);
+            assert_eq!(spaceship.direction(), direction);
+        }
+    }
+
+    #[test]
+    fn not_found() {
+        // the r-pentomino takes 1103 generations to settle down
+        let life = Life::from_rle_pattern(b"b2o$2o$bo!").unwrap();
+        assert_eq!(life.detect_period(100), None);
+        assert_eq!(life.generation(), 0);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Life::new().detect_period(0), None);
+        assert_eq!(Life::new().detect_period(1).unwrap().period, 1);
+    }
+}
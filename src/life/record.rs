@@ -0,0 +1,342 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Recording the population, bounding box and node count of a Life grid as it is stepped.
+
+use crate::{BoundingBox, Life};
+use std::io::Write;
+
+/// The state of a Life grid at one generation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample {
+    /// The generation of the Life grid.
+    pub generation: u128,
+    /// The number of alive cells.
+    pub population: u128,
+    /// A bounding box containing all alive cells.
+    pub bounding_box: Option<BoundingBox>,
+    /// The number of nodes in the store.
+    pub nodes: usize,
+}
+
+/// How the population of a pattern grows over time.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Growth {
+    /// The population never grows beyond what it has been before, like an oscillator.
+    Bounded,
+    /// The population keeps growing, but slower than any power of the generation, like a binary
+    /// counter.
+    LogPeriodic,
+    /// The population grows in proportion to the generation, like a gun.
+    Linear,
+    /// The population grows in proportion to the square of the generation, like a breeder.
+    Quadratic,
+    /// The population grows in some other way.
+    Other,
+}
+
+/// Samples of a Life grid recorded over time, sorted by generation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeSeries {
+    /// The recorded samples.
+    pub samples: Vec<Sample>,
+}
+
+impl TimeSeries {
+    /// Writes the samples as CSV, with a header line and one line per sample.
+    ///
+    /// The columns are the generation, population, the corners of the bounding box, which are
+    /// empty if there are no alive cells, and the node count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"3o!")?;
+    /// life.start_recording();
+    /// life.step();
+    ///
+    /// let mut csv = vec![];
+    /// life.time_series().unwrap().write_csv(&mut csv)?;
+    /// let csv = String::from_utf8(csv)?;
+    /// assert_eq!(csv.lines().nth(2).unwrap().split(',').nth(1), Some("3"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_csv<W>(&self, mut writer: W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        writeln!(
+            writer,
+            "generation,population,min_x,min_y,max_x,max_y,nodes"
+        )?;
+        for sample in &self.samples {
+            write!(writer, "{},{},", sample.generation, sample.population)?;
+            match sample.bounding_box {
+                Some(bounding_box) => write!(
+                    writer,
+                    "{},{},{},{},",
+                    bounding_box.upper_left.x,
+                    bounding_box.upper_left.y,
+                    bounding_box.lower_right.x,
+                    bounding_box.lower_right.y
+                )?,
+                None => write!(writer, ",,,,")?,
+            }
+            writeln!(writer, "{}", sample.nodes)?;
+        }
+        writer.flush()
+    }
+
+    /// Saves the samples to a CSV file. See `write_csv`.
+    pub fn save_csv<P>(&self, path: P) -> std::io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::create(path)?;
+        self.write_csv(std::io::BufWriter::new(file))
+    }
+
+    /// Estimates the exponent with which the population grows, as the slope of a least squares
+    /// fit of the logarithm of the population against the logarithm of the generation.
+    ///
+    /// Both are counted from the first sample, and only the last quarter of the samples on a
+    /// logarithmic scale is used, since patterns often take a while to start growing steadily.
+    /// Returns `None` if the population doesn't grow beyond the first sample in that range.
+    pub fn growth_exponent(&self) -> Option<f64> {
+        let first = self.samples.first()?;
+        let last = self.samples.last()?;
+        let duration = (last.generation - first.generation) as f64;
+        let points = self
+            .samples
+            .iter()
+            .filter(|sample| sample.population > first.population)
+            .map(|sample| {
+                (
+                    ((sample.generation - first.generation) as f64).ln(),
+                    ((sample.population - first.population) as f64).ln(),
+                )
+            })
+            .filter(|&(time, _)| time >= duration.ln() * 0.75)
+            .collect::<Vec<_>>();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let count = points.len() as f64;
+        let mean_time = points.iter().map(|&(time, _)| time).sum::<f64>() / count;
+        let mean_population = points
+            .iter()
+            .map(|&(_, population)| population)
+            .sum::<f64>()
+            / count;
+        let covariance = points
+            .iter()
+            .map(|&(time, population)| (time - mean_time) * (population - mean_population))
+            .sum::<f64>();
+        let variance = points
+            .iter()
+            .map(|&(time, _)| (time - mean_time).powi(2))
+            .sum::<f64>();
+        if variance == 0.0 {
+            None
+        } else {
+            Some(covariance / variance)
+        }
+    }
+
+    /// Classifies how the population grows over the recorded samples.
+    ///
+    /// The population is bounded if it never exceeds, during the second half of the generations,
+    /// the largest population seen during the first half. Otherwise the growth is classified by
+    /// its exponent, see `growth_exponent`. Returns `None` if there are too few samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_file("./assets/gosperglidergun.rle")?;
+    /// life.start_recording();
+    /// life.step_powers_of_two(10);
+    /// assert_eq!(
+    ///     life.time_series().unwrap().growth(),
+    ///     Some(smeagol::Growth::Linear)
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn growth(&self) -> Option<Growth> {
+        let first = self.samples.first()?;
+        let last = self.samples.last()?;
+        if self.samples.len() < 3 {
+            return None;
+        }
+        let middle = first.generation + (last.generation - first.generation) / 2;
+        let max_population = |early: bool| {
+            self.samples
+                .iter()
+                .filter(|sample| (sample.generation <= middle) == early)
+                .map(|sample| sample.population)
+                .max()
+        };
+        if max_population(false) <= max_population(true) {
+            return Some(Growth::Bounded);
+        }
+
+        Some(match self.growth_exponent() {
+            Some(exponent) if exponent < 0.5 => Growth::LogPeriodic,
+            Some(exponent) if exponent < 1.5 => Growth::Linear,
+            Some(exponent) if exponent < 2.5 => Growth::Quadratic,
+            _ => Growth::Other,
+        })
+    }
+}
+
+impl Life {
+    /// Starts recording a sample every time the Life grid is stepped, beginning with a sample of
+    /// the current generation.
+    ///
+    /// Any samples recorded before are discarded.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(TimeSeries::default());
+        self.record_sample();
+    }
+
+    /// Stops recording, and returns the recorded samples, if any.
+    pub fn stop_recording(&mut self) -> Option<TimeSeries> {
+        self.recorder.take()
+    }
+
+    /// Returns the samples recorded so far, if recording.
+    pub fn time_series(&self) -> Option<&TimeSeries> {
+        self.recorder.as_ref()
+    }
+
+    /// Steps the Life grid to the given number of generations after the current one, and to every
+    /// power of two generations after it up to `2^max_log_2`.
+    ///
+    /// Each step doubles the number of generations advanced so far, which only takes about as long
+    /// as computing the largest step on its own. The step size is unchanged afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// life.start_recording();
+    /// life.step_powers_of_two(3);
+    ///
+    /// let generations = life
+    ///     .time_series()
+    ///     .unwrap()
+    ///     .samples
+    ///     .iter()
+    ///     .map(|sample| sample.generation)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(generations, vec![0, 1, 2, 4, 8]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn step_powers_of_two(&mut self, max_log_2: u8) {
+        let step_log_2 = self.step_log_2;
+        self.set_step_log_2(0);
+        self.step();
+        for log_2 in 0..max_log_2 {
+            self.set_step_log_2(log_2);
+            self.step();
+        }
+        self.set_step_log_2(step_log_2);
+    }
+
+    /// Adds a sample of the current generation to the recorded samples, if recording.
+    pub(super) fn record_sample(&mut self) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let sample = Sample {
+            generation: self.generation,
+            population: self.population(),
+            bounding_box: self.bounding_box,
+            nodes: self.store.len(),
+        };
+        if let Some(recorder) = &mut self.recorder {
+            recorder.samples.push(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a time series with the given populations at powers of two generations.
+    fn series(populations: impl Fn(f64) -> f64) -> TimeSeries {
+        let samples = (0..16)
+            .map(|log_2| {
+                let generation = if log_2 == 0 { 0 } else { 1 << (log_2 - 1) };
+                Sample {
+                    generation,
+                    population: populations(generation as f64) as u128,
+                    bounding_box: None,
+                    nodes: 0,
+                }
+            })
+            .collect();
+        TimeSeries { samples }
+    }
+
+    #[test]
+    fn classify() {
+        assert_eq!(series(|_| 10.0).growth(), Some(Growth::Bounded));
+        assert_eq!(
+            series(|generation| 10.0 + (generation + 1.0).log2()).growth(),
+            Some(Growth::LogPeriodic)
+        );
+        assert_eq!(
+            series(|generation| 36.0 + generation / 6.0).growth(),
+            Some(Growth::Linear)
+        );
+        assert_eq!(
+            series(|generation| 50.0 + generation * generation / 100.0).growth(),
+            Some(Growth::Quadratic)
+        );
+        assert_eq!(TimeSeries::default().growth(), None);
+    }
+
+    #[test]
+    fn breeder() {
+        let mut life = Life::from_rle_file("./assets/breeder1.rle").unwrap();
+        life.start_recording();
+        life.step_powers_of_two(12);
+        let time_series = life.stop_recording().unwrap();
+        assert_eq!(time_series.samples.len(), 14);
+        assert_eq!(time_series.samples[13].generation, 1 << 12);
+        assert_eq!(time_series.growth(), Some(Growth::Quadratic));
+        assert!(life.time_series().is_none());
+    }
+
+    #[test]
+    fn csv() {
+        let mut life = Life::new();
+        life.start_recording();
+        life.step();
+        let mut csv = vec![];
+        life.time_series().unwrap().write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "generation,population,min_x,min_y,max_x,max_y,nodes"
+        );
+        assert!(lines[1].starts_with("0,0,,,,,"));
+        assert!(lines[2].starts_with("1,0,,,,,"));
+    }
+}
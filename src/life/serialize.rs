@@ -0,0 +1,253 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! `Serialize` and `Deserialize` for Life grids.
+//!
+//! A Life grid is serialized as the nodes reachable from its root rather than as a list of cells,
+//! so repetitive patterns stay small. Children come before their parents and are referred to by
+//! their position in the list, and the root is the last node. Memoized results are not included.
+
+use crate::{
+    life::{History, Topology, INITIAL_LEVEL},
+    node::{Node, NodeId, NodeTemplate, Store, LEAF_LEVEL},
+    Life, Position,
+};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// The serialized form of a Life grid.
+#[derive(Deserialize, Serialize)]
+struct LifeDag {
+    generation: u128,
+    step_log_2: u8,
+    topology: Topology,
+    /// The position of the center of the root node.
+    origin: Position,
+    nodes: Vec<DagNode>,
+}
+
+/// The serialized form of a node.
+#[derive(Deserialize, Serialize)]
+enum DagNode {
+    /// The rows of a leaf from top to bottom, with the leftmost cell in the most significant bit.
+    Leaf(Vec<u64>),
+    /// The positions of the northwest, northeast, southwest and southeast children.
+    Interior([usize; 4]),
+}
+
+impl Serialize for Life {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut positions = HashMap::new();
+        let mut nodes = vec![];
+        add_node(&self.store, self.root, &mut positions, &mut nodes);
+        LifeDag {
+            generation: self.generation,
+            step_log_2: self.step_log_2,
+            topology: self.topology,
+            origin: self.origin,
+            nodes,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Adds the node and its descendants to the list of nodes, children first, unless it is already
+/// there. Returns the position of the node in the list.
+fn add_node(
+    store: &Store,
+    id: NodeId,
+    positions: &mut HashMap<NodeId, usize>,
+    nodes: &mut Vec<DagNode>,
+) -> usize {
+    if let Some(&position) = positions.get(&id) {
+        return position;
+    }
+    let node = match store.node(id) {
+        Node::Leaf { .. } => {
+            let min_coord = id.min_coord(store);
+            let max_coord = id.max_coord(store);
+            let mut rows = vec![0; (max_coord - min_coord + 1) as usize];
+            for pos in id.get_alive_cells(store) {
+                rows[(pos.y - min_coord) as usize] |= 1 << (max_coord - pos.x);
+            }
+            DagNode::Leaf(rows)
+        }
+        // the store of a Life grid only has Life leaves
+        Node::StateLeaf { .. } => unreachable!(),
+        Node::Interior { nw, ne, sw, se, .. } => DagNode::Interior([
+            add_node(store, nw, positions, nodes),
+            add_node(store, ne, positions, nodes),
+            add_node(store, sw, positions, nodes),
+            add_node(store, se, positions, nodes),
+        ]),
+    };
+    nodes.push(node);
+    positions.insert(id, nodes.len() - 1);
+    nodes.len() - 1
+}
+
+impl<'de> Deserialize<'de> for Life {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let dag = LifeDag::deserialize(deserializer)?;
+
+        let mut store = Store::new();
+        let mut ids: Vec<NodeId> = Vec::with_capacity(dag.nodes.len());
+        let side_len = 1 << LEAF_LEVEL.0;
+        for node in dag.nodes {
+            let id = match node {
+                DagNode::Leaf(rows) => {
+                    if rows.len() != side_len {
+                        return Err(D::Error::custom(format!(
+                            "expected leaves with {} rows",
+                            side_len
+                        )));
+                    }
+                    let leaf = store.create_empty(LEAF_LEVEL);
+                    let min_coord = leaf.min_coord(&store);
+                    let max_coord = leaf.max_coord(&store);
+                    if rows
+                        .iter()
+                        .any(|&row| row.checked_shr(side_len as u32).unwrap_or(0) != 0)
+                    {
+                        return Err(D::Error::custom("leaf row out of range"));
+                    }
+                    let alive_cells = (min_coord..=max_coord)
+                        .flat_map(|y| (min_coord..=max_coord).map(move |x| Position::new(x, y)))
+                        .filter(|pos| {
+                            rows[(pos.y - min_coord) as usize] & (1 << (max_coord - pos.x)) != 0
+                        })
+                        .collect::<Vec<_>>();
+                    leaf.set_cells_alive(&mut store, alive_cells)
+                }
+                DagNode::Interior(children) => {
+                    let child_ids = children
+                        .iter()
+                        .map(|&position| ids.get(position).copied())
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| D::Error::custom("child after parent"))?;
+                    let level = child_ids[0].level(&store);
+                    if child_ids.iter().any(|child| child.level(&store) != level) {
+                        return Err(D::Error::custom("children with different levels"));
+                    }
+                    store.create_interior(NodeTemplate {
+                        nw: child_ids[0],
+                        ne: child_ids[1],
+                        sw: child_ids[2],
+                        se: child_ids[3],
+                    })
+                }
+            };
+            ids.push(id);
+        }
+
+        let mut root = *ids.last().ok_or_else(|| D::Error::custom("no nodes"))?;
+        while root.level(&store) < INITIAL_LEVEL {
+            root = root.expand(&mut store);
+        }
+        // the corners of the root node are at multiples of a quarter of its side length
+        let quarter = 1 << (root.level(&store).0 - 2);
+        if dag.origin.x % quarter != 0 || dag.origin.y % quarter != 0 {
+            return Err(D::Error::custom("misaligned origin"));
+        }
+        if !dag.topology.has_cells() {
+            return Err(D::Error::custom("empty bounded grid"));
+        }
+        if dag.topology.bounding_box().is_some() && dag.origin != Position::new(0, 0) {
+            return Err(D::Error::custom("bounded grid not centered on the origin"));
+        }
+        let mut life = Self {
+            bounding_box: None,
+            root,
+            origin: dag.origin,
+            store,
+            generation: dag.generation,
+            topology: dag.topology,
+            step_log_2: 0,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            history: History::new(),
+            recorder: None,
+        };
+        life.update_bounding_box();
+        life.set_step_log_2(dag.step_log_2);
+        Ok(life)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Life, Topology};
+
+    #[test]
+    fn json() {
+        let mut life = Life::from_rle_file("./assets/gosperglidergun.rle").unwrap();
+        life.set_step_log_2(4);
+        life.step();
+
+        let json = serde_json::to_string(&life).unwrap();
+        let mut deserialized: Life = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.generation(), 16);
+        assert_eq!(deserialized.step_log_2(), 4);
+        assert_eq!(deserialized.bounding_box(), life.bounding_box());
+        assert_eq!(deserialized.get_alive_cells(), life.get_alive_cells());
+
+        life.step();
+        deserialized.step();
+        assert_eq!(deserialized.get_alive_cells(), life.get_alive_cells());
+    }
+
+    #[test]
+    fn bincode() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_topology(Topology::Torus {
+            width: 100,
+            height: 200,
+        })
+        .unwrap();
+
+        let bytes = bincode::serialize(&life).unwrap();
+        let deserialized: Life = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized.topology(), life.topology());
+        assert_eq!(deserialized.get_alive_cells(), life.get_alive_cells());
+    }
+
+    #[test]
+    fn dag_is_smaller_than_cells() {
+        // the breeder contains many copies of the same few objects
+        let life = Life::from_rle_file("./assets/breeder1.rle").unwrap();
+        let dag = bincode::serialize(&life).unwrap();
+        let cells = bincode::serialize(&life.get_alive_cells()).unwrap();
+        assert!(dag.len() < cells.len());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(serde_json::from_str::<Life>(
+            r#"{"generation":0,"step_log_2":0,"topology":"Plane","origin":{"x":0,"y":0},"nodes":[{"Interior":[0,0,0,0]}]}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Life>(
+            r#"{"generation":0,"step_log_2":0,"topology":"Plane","origin":{"x":0,"y":0},"nodes":[{"Leaf":[1]}]}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Life>(
+            r#"{"generation":0,"step_log_2":0,"topology":"Plane","origin":{"x":0,"y":0},"nodes":[]}"#
+        )
+        .is_err());
+
+        let life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        let json = serde_json::to_string(&life)
+            .unwrap()
+            .replace(r#""origin":{"x":0,"y":0}"#, r#""origin":{"x":1,"y":0}"#);
+        assert!(serde_json::from_str::<Life>(&json).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Detecting when a pattern has settled down.
+//!
+//! A pattern has settled down once it repeats itself, ignoring gliders that are flying away from
+//! everything else. Such gliders can never come back, so after they are removed the rest of the
+//! pattern evolves as if they were never there.
+
+use super::census::connected_components;
+use crate::{BoundingBox, Life, Position};
+use std::collections::HashMap;
+
+/// How far a glider must be from every other cell it could meet before it counts as escaped.
+const ESCAPE_MARGIN: i64 = 8;
+
+/// When and how a pattern settled down.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stabilization {
+    /// The first generation of the repeating part of the evolution.
+    pub generation: u128,
+    /// The number of generations after which the pattern repeats, ignoring escaped gliders.
+    pub period: u128,
+}
+
+/// A glider, given by its cells and the direction it moves in.
+struct Glider {
+    cells: Vec<Position>,
+    bounding_box: BoundingBox,
+    direction: (i64, i64),
+}
+
+/// Returns true if the span from `min` to `max` lies beyond the other span in the given direction.
+fn is_beyond(min: i64, max: i64, other_min: i64, other_max: i64, direction: i64) -> bool {
+    if direction > 0 {
+        min > other_max.saturating_add(ESCAPE_MARGIN)
+    } else {
+        max < other_min.saturating_sub(ESCAPE_MARGIN)
+    }
+}
+
+impl Glider {
+    /// Returns true if the glider is moving away from all the cells in the bounding box on both
+    /// axes, so it can never reach them.
+    fn is_escaping_from(&self, bounding_box: BoundingBox) -> bool {
+        is_beyond(
+            self.bounding_box.upper_left.x,
+            self.bounding_box.lower_right.x,
+            bounding_box.upper_left.x,
+            bounding_box.lower_right.x,
+            self.direction.0,
+        ) && is_beyond(
+            self.bounding_box.upper_left.y,
+            self.bounding_box.lower_right.y,
+            bounding_box.upper_left.y,
+            bounding_box.lower_right.y,
+            self.direction.1,
+        )
+    }
+
+    /// Returns true if the two gliders can never meet, because they move in the same direction or
+    /// are moving apart on one of the axes.
+    fn is_separating_from(&self, other: &Glider) -> bool {
+        let apart = |direction: i64,
+                     other_direction: i64,
+                     (min, max): (i64, i64),
+                     (other_min, other_max): (i64, i64)| {
+            direction != other_direction && is_beyond(min, max, other_min, other_max, direction)
+        };
+        self.direction == other.direction
+            || apart(
+                self.direction.0,
+                other.direction.0,
+                (
+                    self.bounding_box.upper_left.x,
+                    self.bounding_box.lower_right.x,
+                ),
+                (
+                    other.bounding_box.upper_left.x,
+                    other.bounding_box.lower_right.x,
+                ),
+            )
+            || apart(
+                self.direction.1,
+                other.direction.1,
+                (
+                    self.bounding_box.upper_left.y,
+                    self.bounding_box.lower_right.y,
+                ),
+                (
+                    other.bounding_box.upper_left.y,
+                    other.bounding_box.lower_right.y,
+                ),
+            )
+    }
+}
+
+/// Returns the bounding box of the cells, if there are any.
+fn bounding_box<'a>(cells: impl IntoIterator<Item = &'a Position>) -> Option<BoundingBox> {
+    cells
+        .into_iter()
+        .map(|&position| BoundingBox::new(position, position))
+        .fold(None, |bounding_box, cell| {
+            Some(bounding_box.map_or(cell, |bounding_box: BoundingBox| bounding_box.combine(cell)))
+        })
+}
+
+/// Returns the direction of the component if it is a glider.
+fn glider_direction(component: &[Position]) -> Option<(i64, i64)> {
+    if component.len() != 5 {
+        return None;
+    }
+    let mut life = Life::new();
+    for &position in component {
+        life.set_cell_alive(position);
+    }
+    life.detect_spaceship(4)
+        .filter(|spaceship| {
+            spaceship.period == 4 && spaceship.dx.abs() == 1 && spaceship.dy.abs() == 1
+        })
+        .map(|spaceship| (spaceship.dx, spaceship.dy))
+}
+
+/// Splits the alive cells into gliders flying away from everything else and the remaining cells.
+fn remove_escaped_gliders(alive_cells: &[Position]) -> (Vec<Position>, usize) {
+    let mut rest = vec![];
+    let mut gliders = vec![];
+    for component in connected_components(alive_cells, 1) {
+        match glider_direction(&component) {
+            Some(direction) => gliders.push(Glider {
+                bounding_box: bounding_box(&component).unwrap(),
+                cells: component,
+                direction,
+            }),
+            None => rest.extend(component),
+        }
+    }
+
+    // a glider has escaped once it is flying away from the other cells and from every glider that
+    // hasn't escaped yet
+    let rest_box = bounding_box(&rest);
+    let mut escaped = vec![false; gliders.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, glider) in gliders.iter().enumerate() {
+            if escaped[index] {
+                continue;
+            }
+            let is_escaping = rest_box
+                .is_none_or(|bounding_box| glider.is_escaping_from(bounding_box))
+                && gliders
+                    .iter()
+                    .zip(&escaped)
+                    .all(|(other, &escaped)| escaped || glider.is_separating_from(other));
+            if is_escaping {
+                escaped[index] = true;
+                changed = true;
+            }
+        }
+    }
+
+    let mut escaped_count = 0;
+    for (glider, escaped) in gliders.into_iter().zip(escaped) {
+        if escaped {
+            escaped_count += 1;
+        } else {
+            rest.extend(glider.cells);
+        }
+    }
+    (rest, escaped_count)
+}
+
+impl Life {
+    /// Advances the Life grid one generation at a time until it has settled down into still lifes,
+    /// oscillators and gliders flying away, for at most the given number of generations.
+    ///
+    /// Returns `None` if the pattern hasn't settled down in time. Patterns that keep emitting
+    /// gliders, like guns, never settle down. Other spaceships are not recognized, so patterns that
+    /// emit them don't settle down either. The Life grid is left at the generation where the
+    /// pattern was first seen to repeat, and the step size is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // the r-pentomino settles down after 1103 generations, leaving six gliders flying away,
+    /// // which is only seen once the gliders are left out
+    /// let mut life = smeagol::Life::from_rle_pattern(b"b2o$2o$bo!")?;
+    /// let stabilization = life.run_until_stable(2000).unwrap();
+    /// assert_eq!(stabilization.generation, 1103);
+    /// assert_eq!(stabilization.period, 2);
+    /// assert_eq!(life.generation(), 1105);
+    /// assert_eq!(life.population(), 116);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_until_stable(&mut self, max_generations: u128) -> Option<Stabilization> {
+        let step_log_2 = self.step_log_2;
+        self.set_step_log_2(0);
+        self.record_history();
+
+        let mut seen = HashMap::new();
+        let end = self.generation + max_generations;
+        let stabilization = loop {
+            let key = self.settled_key();
+            if let Some(&generation) = seen.get(&key) {
+                break Some(Stabilization {
+                    generation,
+                    period: self.generation - generation,
+                });
+            }
+            seen.insert(key, self.generation);
+            if self.generation >= end {
+                break None;
+            }
+            self.advance();
+        };
+
+        self.set_step_log_2(step_log_2);
+        stabilization
+    }
+
+    /// Returns the hash of the pattern without the escaped gliders, and the number of escaped
+    /// gliders.
+    fn settled_key(&self) -> (u64, usize) {
+        let alive_cells = self.get_alive_cells();
+        let (rest, escaped_count) = remove_escaped_gliders(&alive_cells);
+        if escaped_count == 0 {
+            return (self.pattern_hash(), 0);
+        }
+        let mut life = Life::new();
+        for position in rest {
+            life.set_cell_alive(position);
+        }
+        (life.pattern_hash(), escaped_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oscillators() {
+        let mut life = Life::from_rle_file("./assets/pulsar.rle").unwrap();
+        let stabilization = life.run_until_stable(10).unwrap();
+        assert_eq!(stabilization.generation, 0);
+        assert_eq!(stabilization.period, 3);
+        assert_eq!(life.generation(), 3);
+    }
+
+    #[test]
+    fn escaped_gliders() {
+        // a glider flying away from a block
+        let mut life = Life::from_rle_pattern(b"3o$o$bo5$10b2o$10b2o!").unwrap();
+        let stabilization = life.run_until_stable(100).unwrap();
+        assert_eq!(stabilization.period, 1);
+        assert_eq!(life.get_alive_cells().len(), 9);
+
+        // a glider flying towards a block isn't escaping, and they destroy each other
+        let mut life = Life::from_rle_pattern(b"bo$2bo$3o4$6b2o$6b2o!").unwrap();
+        assert_eq!(remove_escaped_gliders(&life.get_alive_cells()).1, 0);
+        let stabilization = life.run_until_stable(100).unwrap();
+        assert_eq!(stabilization.generation, 14);
+        assert_eq!(stabilization.period, 1);
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn gun_never_settles() {
+        let mut life = Life::from_rle_file("./assets/gosperglidergun.rle").unwrap();
+        life.set_step_log_2(3);
+        assert_eq!(life.run_until_stable(300), None);
+        assert_eq!(life.generation(), 300);
+        assert_eq!(life.step_log_2(), 3);
+    }
+}
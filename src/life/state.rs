@@ -0,0 +1,231 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+    life::{History, Topology, Twist},
+    node::{Decoder, Encoder, StateError, Store},
+    Life, Position,
+};
+use std::io::{Read, Write};
+
+/// The magic number at the start of a saved Life grid.
+const LIFE_MAGIC: &[u8; 8] = b"smeagolL";
+
+impl Life {
+    /// Writes the Life grid, including its store of memoized results, in a compact binary format.
+    ///
+    /// A Life grid read back with `read_state` continues exactly where this one left off, without
+    /// recomputing the results it has already cached. The history, the recorded samples, the memory
+    /// limit and whether to evolve on multiple threads are not saved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let mut life = smeagol::Life::from_rle_file("./assets/glider.rle")?;
+    /// life.set_step_log_2(4);
+    /// life.step();
+    ///
+    /// let mut bytes = vec![];
+    /// life.write_state(&mut bytes)?;
+    ///
+    /// let resumed = smeagol::Life::read_state(&bytes[..])?;
+    /// assert_eq!(resumed.generation(), 16);
+    /// assert_eq!(resumed.get_alive_cells(), life.get_alive_cells());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_state<W>(&self, writer: W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        let mut encoder = Encoder::new(writer, LIFE_MAGIC)?;
+        encoder.u128(self.generation)?;
+        encoder.u8(self.step_log_2)?;
+        match self.topology {
+            Topology::Plane => encoder.u8(0)?,
+            Topology::BoundedPlane { width, height } => {
+                encoder.u8(1)?;
+                encoder.u32(width)?;
+                encoder.u32(height)?;
+            }
+            Topology::Torus { width, height } => {
+                encoder.u8(2)?;
+                encoder.u32(width)?;
+                encoder.u32(height)?;
+            }
+            Topology::KleinBottle {
+                width,
+                height,
+                twist,
+            } => {
+                encoder.u8(3)?;
+                encoder.u32(width)?;
+                encoder.u32(height)?;
+                encoder.u8(match twist {
+                    Twist::Horizontal => 0,
+                    Twist::Vertical => 1,
+                })?;
+            }
+        }
+        encoder.i64(self.origin.x)?;
+        encoder.i64(self.origin.y)?;
+        self.store.encode(&mut encoder, &[self.root])?;
+        encoder.finish()
+    }
+
+    /// Reads a Life grid written by `write_state`.
+    pub fn read_state<R>(reader: R) -> Result<Self, StateError>
+    where
+        R: Read,
+    {
+        let corrupt = |reason| StateError::Corrupt { reason };
+
+        let mut decoder = Decoder::new(reader, LIFE_MAGIC)?;
+        let generation = decoder.u128()?;
+        let step_log_2 = decoder.u8()?;
+        let topology = match decoder.u8()? {
+            0 => Topology::Plane,
+            1 => Topology::BoundedPlane {
+                width: decoder.u32()?,
+                height: decoder.u32()?,
+            },
+            2 => Topology::Torus {
+                width: decoder.u32()?,
+                height: decoder.u32()?,
+            },
+            3 => Topology::KleinBottle {
+                width: decoder.u32()?,
+                height: decoder.u32()?,
+                twist: match decoder.u8()? {
+                    0 => Twist::Horizontal,
+                    1 => Twist::Vertical,
+                    _ => return Err(corrupt("unknown twist")),
+                },
+            },
+            _ => return Err(corrupt("unknown topology")),
+        };
+        if !topology.has_cells() {
+            return Err(corrupt("empty bounded grid"));
+        }
+        // the root node of a Life grid was always centered on (0, 0) before version 2
+        let origin = if decoder.version() >= 2 {
+            Position::new(decoder.i64()?, decoder.i64()?)
+        } else {
+            Position::new(0, 0)
+        };
+        let (store, roots) = Store::decode(&mut decoder)?;
+        decoder.finish()?;
+
+        let root = match roots[..] {
+            [root] => root,
+            _ => return Err(corrupt("expected a single root")),
+        };
+        // the corners of the root node are at multiples of a quarter of its side length
+        let quarter = 1 << (root.level(&store).0 - 2);
+        if origin.x % quarter != 0 || origin.y % quarter != 0 {
+            return Err(corrupt("misaligned origin"));
+        }
+        if topology.bounding_box().is_some() && origin != Position::new(0, 0) {
+            return Err(corrupt("bounded grid not centered on the origin"));
+        }
+        let mut life = Self {
+            bounding_box: None,
+            root,
+            origin,
+            store,
+            generation,
+            topology,
+            step_log_2,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+            history: History::new(),
+            recorder: None,
+        };
+        life.update_bounding_box();
+        Ok(life)
+    }
+
+    /// Saves the Life grid to a file. See `write_state`.
+    pub fn save_state<P>(&self, path: P) -> std::io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::create(path)?;
+        self.write_state(std::io::BufWriter::new(file))
+    }
+
+    /// Loads a Life grid from a file written by `save_state`.
+    pub fn load_state<P>(path: P) -> Result<Self, StateError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::open(path)?;
+        Self::read_state(std::io::BufReader::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume() {
+        let mut life = Life::from_rle_file("./assets/sirrobin.rle").unwrap();
+        life.set_topology(Topology::KleinBottle {
+            width: 200,
+            height: 300,
+            twist: Twist::Vertical,
+        })
+        .unwrap();
+        life.set_step_log_2(3);
+        life.step();
+
+        let mut bytes = vec![];
+        life.write_state(&mut bytes).unwrap();
+        let mut resumed = Life::read_state(&bytes[..]).unwrap();
+        assert_eq!(resumed.topology(), life.topology());
+        assert_eq!(resumed.bounding_box(), life.bounding_box());
+
+        for _ in 0..4 {
+            life.step();
+            resumed.step();
+        }
+        assert_eq!(resumed.generation(), life.generation());
+        assert_eq!(resumed.get_alive_cells(), life.get_alive_cells());
+    }
+
+    #[test]
+    fn resume_after_moving() {
+        let mut life = Life::from_rle_file("./assets/glider.rle").unwrap();
+        life.set_step_log_2(4);
+        for _ in 0..64 {
+            life.step();
+        }
+        assert_ne!(life.origin, Position::new(0, 0));
+
+        let mut bytes = vec![];
+        life.write_state(&mut bytes).unwrap();
+        let mut resumed = Life::read_state(&bytes[..]).unwrap();
+        assert_eq!(resumed.get_alive_cells(), life.get_alive_cells());
+
+        life.step();
+        resumed.step();
+        assert_eq!(resumed.get_alive_cells(), life.get_alive_cells());
+    }
+
+    #[test]
+    fn wrong_kind() {
+        let mut store = Store::new();
+        let root = store.create_empty(crate::node::Level(7));
+        let mut bytes = vec![];
+        store.save(&mut bytes, &[root]).unwrap();
+        assert!(matches!(
+            Life::read_state(&bytes[..]),
+            Err(StateError::Magic)
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Detecting the rotations and reflections that leave a pattern unchanged.
+//!
+//! The transformed alive cells are moved so that their bounding box starts in the corner of a node
+//! in a separate store, as when detecting periods. Since nodes are hash-consed, a transformed
+//! pattern with the same shape as the pattern in some generation gets the same node ID.
+
+use crate::{
+    node::{NodeId, Store},
+    Life, Position,
+};
+use std::collections::HashMap;
+
+/// One of the eight rotations and reflections of the grid.
+///
+/// Rotations are clockwise, with `y` increasing downwards.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transformation {
+    /// Leaves every cell where it is.
+    Identity,
+    /// Rotates by a quarter turn.
+    Rotate90,
+    /// Rotates by a half turn.
+    Rotate180,
+    /// Rotates by three quarter turns.
+    Rotate270,
+    /// Reflects in a vertical axis, swapping left and right.
+    FlipLeftRight,
+    /// Reflects in a horizontal axis, swapping top and bottom.
+    FlipUpDown,
+    /// Reflects in the diagonal from the upper left to the lower right.
+    Transpose,
+    /// Reflects in the diagonal from the lower left to the upper right.
+    AntiTranspose,
+}
+
+impl Transformation {
+    /// All eight transformations.
+    pub const ALL: [Transformation; 8] = [
+        Transformation::Identity,
+        Transformation::Rotate90,
+        Transformation::Rotate180,
+        Transformation::Rotate270,
+        Transformation::FlipLeftRight,
+        Transformation::FlipUpDown,
+        Transformation::Transpose,
+        Transformation::AntiTranspose,
+    ];
+
+    /// Applies the transformation to a position, keeping the origin where it is.
+    pub fn apply(self, position: Position) -> Position {
+        let Position { x, y } = position;
+        let (x, y) = match self {
+            Transformation::Identity => (x, y),
+            Transformation::Rotate90 => (-y, x),
+            Transformation::Rotate180 => (-x, -y),
+            Transformation::Rotate270 => (y, -x),
+            Transformation::FlipLeftRight => (-x, y),
+            Transformation::FlipUpDown => (x, -y),
+            Transformation::Transpose => (y, x),
+            Transformation::AntiTranspose => (-y, -x),
+        };
+        Position::new(x, y)
+    }
+}
+
+/// A line that a reflection reflects in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    /// The vertical line through the given `x` coordinate.
+    Vertical { x: f64 },
+    /// The horizontal line through the given `y` coordinate.
+    Horizontal { y: f64 },
+    /// The line from the upper left to the lower right on which `x - y` has the given value.
+    Diagonal { difference: f64 },
+    /// The line from the lower left to the upper right on which `x + y` has the given value.
+    Antidiagonal { sum: f64 },
+}
+
+/// A transformation that turns the pattern into itself, possibly after some generations.
+///
+/// Every alive cell at `position` is alive at `transformation.apply(position)` moved by `dx` and
+/// `dy`, `generations` generations later.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymmetryElement {
+    /// The rotation or reflection.
+    pub transformation: Transformation,
+    /// The number of generations after which the transformed pattern appears.
+    pub generations: u128,
+    /// The number of cells the transformed pattern is moved to the right.
+    pub dx: i64,
+    /// The number of cells the transformed pattern is moved down.
+    pub dy: i64,
+}
+
+impl SymmetryElement {
+    /// Returns the point the pattern is rotated around, if the transformation is a rotation.
+    pub fn center(&self) -> Option<(f64, f64)> {
+        let (dx, dy) = (self.dx as f64, self.dy as f64);
+        match self.transformation {
+            Transformation::Rotate90 => Some(((dx - dy) / 2.0, (dx + dy) / 2.0)),
+            Transformation::Rotate180 => Some((dx / 2.0, dy / 2.0)),
+            Transformation::Rotate270 => Some(((dx + dy) / 2.0, (dy - dx) / 2.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the line the pattern is reflected in, if the transformation is a reflection.
+    pub fn axis(&self) -> Option<Axis> {
+        let (dx, dy) = (self.dx as f64, self.dy as f64);
+        match self.transformation {
+            Transformation::FlipLeftRight => Some(Axis::Vertical { x: dx / 2.0 }),
+            Transformation::FlipUpDown => Some(Axis::Horizontal { y: dy / 2.0 }),
+            Transformation::Transpose => Some(Axis::Diagonal {
+                difference: (dx - dy) / 2.0,
+            }),
+            Transformation::AntiTranspose => Some(Axis::Antidiagonal {
+                sum: (dx + dy) / 2.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the transformation is a reflection followed by a move along its axis, like
+    /// the glide symmetry of a glider after two generations.
+    pub fn is_glide(&self) -> bool {
+        match self.transformation {
+            Transformation::FlipLeftRight => self.dy != 0,
+            Transformation::FlipUpDown => self.dx != 0,
+            Transformation::Transpose => self.dx != -self.dy,
+            Transformation::AntiTranspose => self.dx != self.dy,
+            _ => false,
+        }
+    }
+}
+
+/// The transformations that turn a pattern into itself.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symmetry {
+    /// The transformations, at most one for each rotation and reflection, with the fewest
+    /// generations.
+    pub elements: Vec<SymmetryElement>,
+}
+
+impl Symmetry {
+    /// Returns the transformation that turns the pattern into itself without advancing it, if any.
+    pub fn get(&self, transformation: Transformation) -> Option<SymmetryElement> {
+        self.elements
+            .iter()
+            .find(|element| element.transformation == transformation && element.generations == 0)
+            .copied()
+    }
+
+    /// Returns the Catagolue name of the symmetry group of the pattern, ignoring transformations
+    /// that need the pattern to be advanced.
+    ///
+    /// The names are `C1`, `C2_1`, `C2_2`, `C2_4`, `C4_1`, `C4_4`, `D2_+1`, `D2_+2`, `D2_x`,
+    /// `D4_+1`, `D4_+2`, `D4_+4`, `D4_x1`, `D4_x4`, `D8_1` and `D8_4`. The number tells whether
+    /// the center of rotation is in the middle of a cell, on the middle of an edge, or on a corner,
+    /// or whether the axis goes through cells or between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let block = smeagol::Life::from_rle_pattern(b"2o$2o!")?;
+    /// assert_eq!(block.symmetry().name(), "D8_4");
+    ///
+    /// let blinker = smeagol::Life::from_rle_pattern(b"3o!")?;
+    /// assert_eq!(blinker.symmetry().name(), "D4_+1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn name(&self) -> String {
+        use self::Transformation::*;
+
+        let has = |transformation| self.get(transformation).is_some();
+        let half_turn_parity = || {
+            let element = self.get(Rotate180).unwrap();
+            match (element.dx % 2 != 0, element.dy % 2 != 0) {
+                (false, false) => 1,
+                (true, true) => 4,
+                _ => 2,
+            }
+        };
+        let quarter_turn_parity = || match half_turn_parity() {
+            4 => 4,
+            _ => 1,
+        };
+        let orthogonal = has(FlipLeftRight) || has(FlipUpDown);
+        let diagonal = has(Transpose) || has(AntiTranspose);
+
+        if has(Rotate90) {
+            let group = if orthogonal { "D8" } else { "C4" };
+            format!("{}_{}", group, quarter_turn_parity())
+        } else if has(Rotate180) && orthogonal {
+            format!("D4_+{}", half_turn_parity())
+        } else if has(Rotate180) && diagonal {
+            format!("D4_x{}", quarter_turn_parity())
+        } else if has(Rotate180) {
+            format!("C2_{}", half_turn_parity())
+        } else if let Some(element) = self.get(FlipLeftRight) {
+            format!("D2_+{}", if element.dx % 2 == 0 { 1 } else { 2 })
+        } else if let Some(element) = self.get(FlipUpDown) {
+            format!("D2_+{}", if element.dy % 2 == 0 { 1 } else { 2 })
+        } else if diagonal {
+            "D2_x".to_owned()
+        } else {
+            "C1".to_owned()
+        }
+    }
+}
+
+/// Returns a node in the store with the transformed cells moved so that their bounding box starts
+/// at the upper left corner of the node, and the upper left corner of the transformed bounding box.
+fn transformed_shape(
+    cells: &[Position],
+    transformation: Transformation,
+    store: &mut Store,
+) -> (NodeId, Position) {
+    let mut life = Life::new();
+    for &position in cells {
+        life.set_cell_alive(transformation.apply(position));
+    }
+    life.shape(store)
+}
+
+impl Life {
+    /// Returns the rotations and reflections that leave the pattern unchanged, apart from moving
+    /// it.
+    ///
+    /// The identity is always included. An empty pattern is symmetric under every transformation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use smeagol::Transformation;
+    ///
+    /// // boat
+    /// let life = smeagol::Life::from_rle_pattern(b"2o$obo$bo!")?;
+    /// let symmetry = life.symmetry();
+    /// assert_eq!(symmetry.elements.len(), 2);
+    /// assert_eq!(
+    ///     symmetry.get(Transformation::Transpose).unwrap().axis(),
+    ///     Some(smeagol::Axis::Diagonal { difference: 0.0 })
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn symmetry(&self) -> Symmetry {
+        let mut shapes = Store::new();
+        let (shape, upper_left) = self.shape(&mut shapes);
+        let mut seen = HashMap::new();
+        seen.insert(shape, (upper_left, 0));
+        self.symmetry_elements(&mut shapes, &seen)
+    }
+
+    /// Returns the rotations and reflections that turn the pattern into itself in some generation
+    /// of its period, like the glide reflection of a glider, advancing a copy of the Life grid one
+    /// generation at a time for at most the given number of generations to find the period.
+    ///
+    /// For each transformation, only the first generation it appears in is included. Returns
+    /// `None` if the pattern doesn't repeat in time, including patterns that only repeat after
+    /// they have settled down.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use smeagol::Transformation;
+    ///
+    /// // a glider is reflected and moved along the reflection axis after two generations
+    /// let life = smeagol::Life::from_rle_pattern(b"bob$2bo$3o!")?;
+    /// let symmetry = life.phase_symmetry(10).unwrap();
+    /// let glide = symmetry
+    ///     .elements
+    ///     .iter()
+    ///     .find(|element| element.transformation == Transformation::Transpose)
+    ///     .unwrap();
+    /// assert_eq!(glide.generations, 2);
+    /// assert!(glide.is_glide());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn phase_symmetry(&self, max_generations: u128) -> Option<Symmetry> {
+        let periodicity = self
+            .detect_period(max_generations)
+            .filter(|periodicity| periodicity.start == self.generation)?;
+
+        let mut life = self.clone();
+        life.set_step_log_2(0);
+        let mut shapes = Store::new();
+        let mut seen = HashMap::new();
+        for generation in 0..periodicity.period {
+            if generation > 0 {
+                life.advance();
+            }
+            let (shape, upper_left) = life.shape(&mut shapes);
+            seen.entry(shape).or_insert((upper_left, generation));
+        }
+        Some(self.symmetry_elements(&mut shapes, &seen))
+    }
+
+    /// Returns a symmetry element for every transformation of the pattern that has the same shape
+    /// as one of the seen shapes, which are mapped to their upper left corners and generations.
+    fn symmetry_elements(
+        &self,
+        shapes: &mut Store,
+        seen: &HashMap<NodeId, (Position, u128)>,
+    ) -> Symmetry {
+        let alive_cells = self.get_alive_cells();
+        let mut elements = vec![];
+        for &transformation in &Transformation::ALL {
+            let (shape, upper_left) = transformed_shape(&alive_cells, transformation, shapes);
+            if let Some(&(target, generations)) = seen.get(&shape) {
+                elements.push(SymmetryElement {
+                    transformation,
+                    generations,
+                    dx: target.x - upper_left.x,
+                    dy: target.y - upper_left.y,
+                });
+            }
+        }
+        Symmetry { elements }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for &(pattern, name) in &[
+            (&b"2o$2o!"[..], "D8_4"),
+            (b"b2o$o2bo$o2bo$b2o!", "D8_4"),
+            (b"bo$3o$bo!", "D8_1"),
+            (b"2b2o$o$obobo$4bo$b2o!", "C4_1"),
+            (b"3o!", "D4_+1"),
+            (b"2o!", "D4_+2"),
+            (b"b2o$o2bo$b2o!", "D4_+2"),
+            (b"4o$4o!", "D4_+4"),
+            (b"2o$obo$b2o!", "D4_x1"),
+            (b"2o$o$3bo$2b2o!", "D4_x4"),
+            (b"2o$obo$bo!", "D2_x"),
+            (b"3o$bo!", "D2_+1"),
+            (b"4o$o2bo!", "D2_+2"),
+            (b"b2o$2o!", "C2_2"),
+            (b"2o$bo$b2o!", "C2_1"),
+            (b"2o$2bo$bo$2b2o!", "C2_4"),
+            (b"b2o$2o$bo!", "C1"),
+        ] {
+            let life = Life::from_rle_pattern(pattern).unwrap();
+            assert_eq!(
+                life.symmetry().name(),
+                name,
+                "{}",
+                std::str::from_utf8(pattern).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn centers_and_axes() {
+        // a blinker centered on (1, 0)
+        let life = Life::from_rle_pattern(b"3o!").unwrap();
+        let symmetry = life.symmetry();
+        assert_eq!(symmetry.elements.len(), 4);
+        let half_turn = symmetry.get(Transformation::Rotate180).unwrap();
+        assert_eq!(half_turn.center(), Some((1.0, 0.0)));
+        let flip = symmetry.get(Transformation::FlipLeftRight).unwrap();
+        assert_eq!(flip.axis(), Some(Axis::Vertical { x: 1.0 }));
+        assert!(!flip.is_glide());
+
+        // a block's center of rotation is on the corner between its cells
+        let life = Life::from_rle_pattern(b"2o$2o!").unwrap();
+        let quarter_turn = life.symmetry().get(Transformation::Rotate90).unwrap();
+        assert_eq!(quarter_turn.center(), Some((0.5, 0.5)));
+    }
+
+    #[test]
+    fn phases() {
+        // a blinker turns into itself rotated by a quarter turn after one generation
+        let life = Life::from_rle_pattern(b"3o!").unwrap();
+        let symmetry = life.phase_symmetry(10).unwrap();
+        assert_eq!(symmetry.elements.len(), 8);
+        let quarter_turn = symmetry
+            .elements
+            .iter()
+            .find(|element| element.transformation == Transformation::Rotate90)
+            .unwrap();
+        assert_eq!(quarter_turn.generations, 1);
+        assert_eq!(quarter_turn.center(), Some((1.0, 0.0)));
+
+        // the r-pentomino doesn't repeat
+        let life = Life::from_rle_pattern(b"b2o$2o$bo!").unwrap();
+        assert_eq!(life.phase_symmetry(10), None);
+    }
+
+    #[test]
+    fn transformations() {
+        let position = Position::new(2, 1);
+        for &transformation in &Transformation::ALL {
+            let transformed = transformation.apply(position);
+            let twice = transformation.apply(transformed);
+            match transformation {
+                Transformation::Rotate90 | Transformation::Rotate270 => {
+                    assert_eq!(twice, Transformation::Rotate180.apply(position))
+                }
+                _ => assert_eq!(twice, position),
+            }
+        }
+    }
+}
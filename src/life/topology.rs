@@ -0,0 +1,356 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{BoundingBox, Position};
+
+/// An error than can occur while parsing a topology.
+#[derive(Debug, Fail)]
+pub enum TopologyError {
+    /// The topology is not a valid Golly bounded grid specification.
+    #[fail(display = "Invalid topology: {}", topology)]
+    Invalid { topology: String },
+    /// The grid of a bounded topology has no cells.
+    #[fail(
+        display = "Bounded grid with a width or height of zero: {:?}",
+        topology
+    )]
+    Empty { topology: Topology },
+}
+
+/// The pair of edges of a Klein bottle that are joined with a twist.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Twist {
+    /// The top and bottom edges are joined with a twist, written `Kw*,h` in Golly.
+    Horizontal,
+    /// The left and right edges are joined with a twist, written `Kw,h*` in Golly.
+    Vertical,
+}
+
+/// The shape of the universe a Life grid lives in.
+///
+/// Bounded grids span the positions from `(0, 0)` to `(width - 1, height - 1)`, which matches the
+/// coordinates of patterns loaded from RLE files. Their width and height must be positive, which
+/// `Life::set_topology` checks.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Topology {
+    /// An unbounded plane.
+    #[default]
+    Plane,
+    /// A bounded plane. Cells outside the grid are always dead.
+    BoundedPlane { width: u32, height: u32 },
+    /// A torus. The top and bottom edges are joined, as are the left and right edges.
+    Torus { width: u32, height: u32 },
+    /// A Klein bottle. One pair of edges is joined with a twist and the other pair is joined as in
+    /// a torus.
+    KleinBottle {
+        width: u32,
+        height: u32,
+        twist: Twist,
+    },
+}
+
+impl Topology {
+    /// Returns the topology given by the suffix of a Golly rule string, such as `B3/S23:T64,64`.
+    ///
+    /// Rules without a suffix are unbounded planes. The rule itself is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use smeagol::Topology;
+    ///
+    /// assert_eq!(Topology::from_rule("B3/S23")?, Topology::Plane);
+    /// assert_eq!(
+    ///     Topology::from_rule("B3/S23:T64,64")?,
+    ///     Topology::Torus {
+    ///         width: 64,
+    ///         height: 64
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_rule(rule: &str) -> Result<Self, TopologyError> {
+        match rule.find(':') {
+            Some(index) => rule[index + 1..].parse(),
+            None => Ok(Topology::Plane),
+        }
+    }
+
+    /// Returns the bounding box of the grid, or `None` for an unbounded plane.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let topology = smeagol::Topology::BoundedPlane {
+    ///     width: 100,
+    ///     height: 50,
+    /// };
+    /// assert_eq!(
+    ///     topology.bounding_box(),
+    ///     Some(smeagol::BoundingBox::new(
+    ///         smeagol::Position::new(0, 0),
+    ///         smeagol::Position::new(99, 49)
+    ///     ))
+    /// );
+    /// ```
+    pub fn bounding_box(self) -> Option<BoundingBox> {
+        match self {
+            Topology::Plane => None,
+            Topology::BoundedPlane { width, height }
+            | Topology::Torus { width, height }
+            | Topology::KleinBottle { width, height, .. } => Some(BoundingBox::new(
+                Position::new(0, 0),
+                Position::new(i64::from(width) - 1, i64::from(height) - 1),
+            )),
+        }
+    }
+
+    /// Returns true if the topology is unbounded or its grid has a positive width and height.
+    pub(crate) fn has_cells(self) -> bool {
+        match self {
+            Topology::Plane => true,
+            Topology::BoundedPlane { width, height }
+            | Topology::Torus { width, height }
+            | Topology::KleinBottle { width, height, .. } => width > 0 && height > 0,
+        }
+    }
+
+    /// Maps the given position onto the grid.
+    ///
+    /// Positions outside a torus or Klein bottle are wrapped around onto the grid. Returns `None`
+    /// if the position lies outside a bounded plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid of a torus or Klein bottle has a width or height of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let torus = smeagol::Topology::Torus {
+    ///     width: 10,
+    ///     height: 10,
+    /// };
+    /// assert_eq!(
+    ///     torus.wrap(smeagol::Position::new(-1, 12)),
+    ///     Some(smeagol::Position::new(9, 2))
+    /// );
+    /// ```
+    pub fn wrap(self, pos: Position) -> Option<Position> {
+        match self {
+            Topology::Plane => Some(pos),
+            Topology::BoundedPlane { .. } => {
+                let bounding_box = self.bounding_box().unwrap();
+                if bounding_box.intersect(BoundingBox::new(pos, pos)).is_some() {
+                    Some(pos)
+                } else {
+                    None
+                }
+            }
+            Topology::Torus { width, height } => {
+                let width = i64::from(width);
+                let height = i64::from(height);
+                Some(Position::new(
+                    pos.x.rem_euclid(width),
+                    pos.y.rem_euclid(height),
+                ))
+            }
+            Topology::KleinBottle {
+                width,
+                height,
+                twist,
+            } => {
+                let width = i64::from(width);
+                let height = i64::from(height);
+                let mut x = pos.x.rem_euclid(width);
+                let mut y = pos.y.rem_euclid(height);
+                match twist {
+                    // crossing the top or bottom edge an odd number of times reflects horizontally
+                    Twist::Horizontal if pos.y.div_euclid(height) % 2 != 0 => x = width - 1 - x,
+                    // crossing the left or right edge an odd number of times reflects vertically
+                    Twist::Vertical if pos.x.div_euclid(width) % 2 != 0 => y = height - 1 - y,
+                    _ => (),
+                }
+                Some(Position::new(x, y))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Topology {
+    type Err = TopologyError;
+
+    /// Parses a Golly bounded grid specification such as `T64,64`, `P100,100` or `K40*,20`.
+    ///
+    /// Torus shifts and spheres are not supported.
+    fn from_str(topology: &str) -> Result<Self, Self::Err> {
+        let invalid = || TopologyError::Invalid {
+            topology: topology.to_owned(),
+        };
+
+        let mut chars = topology.chars();
+        let kind = chars.next().ok_or_else(invalid)?;
+        let dimensions = chars.as_str().split(',').collect::<Vec<_>>();
+        if dimensions.len() != 2 {
+            return Err(invalid());
+        }
+        let (width, width_twist) = parse_dimension(dimensions[0]).ok_or_else(invalid)?;
+        let (height, height_twist) = parse_dimension(dimensions[1]).ok_or_else(invalid)?;
+
+        match (kind.to_ascii_uppercase(), width_twist, height_twist) {
+            ('P', false, false) => Ok(Topology::BoundedPlane { width, height }),
+            ('T', false, false) => Ok(Topology::Torus { width, height }),
+            ('K', true, false) => Ok(Topology::KleinBottle {
+                width,
+                height,
+                twist: Twist::Horizontal,
+            }),
+            ('K', false, true) => Ok(Topology::KleinBottle {
+                width,
+                height,
+                twist: Twist::Vertical,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Parses a positive grid dimension, returning the dimension and whether it is followed by `*`.
+fn parse_dimension(dimension: &str) -> Option<(u32, bool)> {
+    let (digits, twist) = match dimension.strip_suffix('*') {
+        Some(digits) => (digits, true),
+        None => (dimension, false),
+    };
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match digits.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(size) => Some((size, twist)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            "T64,64".parse::<Topology>().unwrap(),
+            Topology::Torus {
+                width: 64,
+                height: 64
+            }
+        );
+        assert_eq!(
+            "p100,50".parse::<Topology>().unwrap(),
+            Topology::BoundedPlane {
+                width: 100,
+                height: 50
+            }
+        );
+        assert_eq!(
+            "K40*,20".parse::<Topology>().unwrap(),
+            Topology::KleinBottle {
+                width: 40,
+                height: 20,
+                twist: Twist::Horizontal
+            }
+        );
+        assert_eq!(
+            "K40,20*".parse::<Topology>().unwrap(),
+            Topology::KleinBottle {
+                width: 40,
+                height: 20,
+                twist: Twist::Vertical
+            }
+        );
+
+        for invalid in &[
+            "",
+            "T",
+            "T64",
+            "T0,64",
+            "T64,-1",
+            "T64+1,64",
+            "T64*,64",
+            "K40,20",
+            "K40*,20*",
+            "S10,10",
+            "T64,64,64",
+        ] {
+            assert!(invalid.parse::<Topology>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn from_rule() {
+        assert_eq!(Topology::from_rule("b3/s23").unwrap(), Topology::Plane);
+        assert_eq!(
+            Topology::from_rule("B3/S23:P10,20").unwrap(),
+            Topology::BoundedPlane {
+                width: 10,
+                height: 20
+            }
+        );
+        assert!(Topology::from_rule("B3/S23:X").is_err());
+    }
+
+    #[test]
+    fn wrap() {
+        let plane = Topology::BoundedPlane {
+            width: 4,
+            height: 3,
+        };
+        assert_eq!(plane.wrap(Position::new(3, 2)), Some(Position::new(3, 2)));
+        assert_eq!(plane.wrap(Position::new(4, 2)), None);
+        assert_eq!(plane.wrap(Position::new(0, -1)), None);
+
+        let torus = Topology::Torus {
+            width: 4,
+            height: 3,
+        };
+        assert_eq!(torus.wrap(Position::new(4, -1)), Some(Position::new(0, 2)));
+        assert_eq!(torus.wrap(Position::new(-9, 7)), Some(Position::new(3, 1)));
+
+        let klein_bottle = Topology::KleinBottle {
+            width: 4,
+            height: 3,
+            twist: Twist::Horizontal,
+        };
+        assert_eq!(
+            klein_bottle.wrap(Position::new(0, -1)),
+            Some(Position::new(3, 2))
+        );
+        assert_eq!(
+            klein_bottle.wrap(Position::new(-1, 1)),
+            Some(Position::new(3, 1))
+        );
+        assert_eq!(
+            klein_bottle.wrap(Position::new(1, 6)),
+            Some(Position::new(1, 0))
+        );
+
+        let klein_bottle = Topology::KleinBottle {
+            width: 4,
+            height: 3,
+            twist: Twist::Vertical,
+        };
+        assert_eq!(
+            klein_bottle.wrap(Position::new(4, 0)),
+            Some(Position::new(0, 2))
+        );
+        assert_eq!(
+            klein_bottle.wrap(Position::new(1, 3)),
+            Some(Position::new(1, 0))
+        );
+    }
+}
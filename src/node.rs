@@ -5,157 +5,59 @@
  */
 
 //! Inner workings of `smeagol`.
+#[cfg(not(feature = "leaf64"))]
+#[macro_use]
+mod simd;
+
+mod hash;
 mod impls;
+mod leaf;
 mod store;
-
-pub use self::store::{NodeTemplate, Store};
-use packed_simd::u16x16;
+mod table;
+
+#[cfg(feature = "leaf64")]
+pub use self::leaf::u64x64;
+#[cfg(not(feature = "leaf64"))]
+pub use self::simd::u16x16;
+pub(crate) use self::{
+    hash::{
+        block_hash as block_content_hash, combine as combine_content_hashes,
+        empty_hash as empty_content_hash, positioned as positioned_content_hash,
+        square_hash as leaf_square_content_hash, BLOCK_LEVEL,
+    },
+    leaf::{get_row as get_leaf_row, MIN_COORD as LEAF_MIN_COORD},
+    store::{Decoder, Encoder},
+};
+pub use self::{
+    leaf::{
+        states::{StateGrid, STATE_LEAF_LEVEL},
+        Grid, LEAF_LEVEL,
+    },
+    store::{NodeTemplate, StateError, Store, StoreStats},
+};
 use std::hash::{Hash, Hasher};
 
-const LEVEL_4_UPPER_HALF_MASK: u16x16 = u16x16::new(
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-);
-
-const LEVEL_4_LOWER_HALF_MASK: u16x16 = u16x16::new(
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-    0b1111_1111_1111_1111,
-);
-
-const LEVEL_4_NW_MASK: u16x16 = u16x16::new(
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-);
-
-const LEVEL_4_NE_MASK: u16x16 = u16x16::new(
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-);
-
-const LEVEL_4_SW_MASK: u16x16 = u16x16::new(
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-    0b1111_1111_0000_0000,
-);
-
-const LEVEL_4_SE_MASK: u16x16 = u16x16::new(
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_0000_0000,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-    0b0000_0000_1111_1111,
-);
-
-fn center(nw_grid: u16x16, ne_grid: u16x16, sw_grid: u16x16, se_grid: u16x16) -> u16x16 {
-    let nw_grid = nw_grid << 8;
-    let sw_grid = sw_grid << 8;
-    let left: u16x16 = shuffle!(
-        nw_grid,
-        sw_grid,
-        [8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
-    );
-
-    let ne_grid = ne_grid >> 8;
-    let se_grid = se_grid >> 8;
-    let right: u16x16 = shuffle!(
-        ne_grid,
-        se_grid,
-        [8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
-    );
-
-    left | right
-}
+/// The integer type of an index in a store.
+///
+/// Indices are 32 bits wide by default, which limits a store to about four billion nodes. With the
+/// `index64` feature enabled they are 64 bits wide, which makes every node larger.
+#[cfg(not(feature = "index64"))]
+type RawIndex = u32;
+#[cfg(feature = "index64")]
+type RawIndex = u64;
 
 /// An index in a store.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-struct Index(u32);
+struct Index(RawIndex);
 
 /// The level of a node.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Level(pub u8);
 
 /// The four quadrants of a node.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Quadrant {
     /// The northwest quadrant.
     Northwest,
@@ -175,14 +77,23 @@ pub struct NodeId {
 }
 
 /// An immutable quadtree representation of a Life grid.
+// 64 by 64 leaves are stored inline, which makes every node as large as a leaf
+#[cfg_attr(feature = "leaf64", allow(clippy::large_enum_variant))]
 #[derive(Clone, Copy, Debug)]
 pub enum Node {
-    /// A leaf (16 by 16) node.
+    /// A leaf node, 16 by 16 or 64 by 64 with the `leaf64` feature.
     Leaf {
         /// The grid itself.
         ///
         /// 1 represents an alive cell, 0 represents a dead cell.
-        grid: u16x16,
+        grid: Grid,
+    },
+    /// A leaf node of a multi-state grid, 4 by 4.
+    StateLeaf {
+        /// The state of each cell, row by row.
+        ///
+        /// 0 represents an empty cell.
+        grid: StateGrid,
     },
     /// A non-leaf node.
     Interior {
@@ -205,6 +116,7 @@ impl PartialEq for Node {
     fn eq(&self, other: &Node) -> bool {
         match (self, other) {
             (Node::Leaf { grid }, Node::Leaf { grid: other_grid }) => grid == other_grid,
+            (Node::StateLeaf { grid }, Node::StateLeaf { grid: other_grid }) => grid == other_grid,
             (
                 Node::Interior { nw, ne, sw, se, .. },
                 Node::Interior {
@@ -229,6 +141,7 @@ impl Hash for Node {
     {
         match self {
             Node::Leaf { grid } => grid.hash(state),
+            Node::StateLeaf { grid } => grid.hash(state),
             Node::Interior { nw, ne, sw, se, .. } => {
                 nw.hash(state);
                 ne.hash(state);
@@ -246,10 +159,22 @@ impl Node {
     /// # Panics
     ///
     /// Panics if the node is not a leaf.
-    fn unwrap_leaf(&self) -> u16x16 {
+    fn unwrap_leaf(&self) -> Grid {
         match *self {
             Node::Leaf { grid } => grid,
-            Node::Interior { .. } => panic!(),
+            Node::StateLeaf { .. } | Node::Interior { .. } => panic!(),
+        }
+    }
+
+    /// Returns the inner grid of a multi-state leaf node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not a multi-state leaf.
+    fn unwrap_state_leaf(&self) -> StateGrid {
+        match *self {
+            Node::StateLeaf { grid } => grid,
+            Node::Leaf { .. } | Node::Interior { .. } => panic!(),
         }
     }
 }
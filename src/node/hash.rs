@@ -0,0 +1,135 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Content hashes of nodes.
+//!
+//! The content hash of a node only depends on its cells, not on node IDs or the store it is in,
+//! so it is the same in every store and every run. Squares of 8 by 8 cells are hashed directly and
+//! larger squares are hashed from their four quadrants, so leaves are hashed as if they were
+//! quadtrees themselves and the hash doesn't depend on the leaf size either.
+
+use crate::{
+    node::{leaf, Grid, Level, StateGrid, LEAF_LEVEL},
+    Position,
+};
+
+/// The level of the smallest squares of cells that are hashed directly.
+pub const BLOCK_LEVEL: u8 = 3;
+
+/// Mixed into the cells of a square of 8 by 8 cells before hashing.
+const BLOCK_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Mixed into the states of a multi-state leaf before hashing.
+const STATE_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Mixes the bits of a value, using the finalizer of SplitMix64.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Returns the content hash of a square of cells with the given level from the content hashes of
+/// its northwest, northeast, southwest and southeast quadrants.
+pub fn combine(level: Level, quadrants: [u64; 4]) -> u64 {
+    quadrants
+        .iter()
+        .fold(mix(u64::from(level.0)), |hash, &quadrant| {
+            mix(hash.rotate_left(23) ^ quadrant)
+        })
+}
+
+/// Returns the content hash of an empty square of cells with the given level.
+pub fn empty_hash(level: Level) -> u64 {
+    (BLOCK_LEVEL + 1..=level.0).fold(mix(BLOCK_SEED), |hash, level| {
+        combine(Level(level), [hash; 4])
+    })
+}
+
+/// Returns a hash of a content hash and the position of the upper left cell of its square.
+pub fn positioned(hash: u64, position: Position) -> u64 {
+    mix(mix(hash ^ position.x as u64).rotate_left(23) ^ position.y as u64)
+}
+
+/// Returns the content hash of a multi-state leaf.
+///
+/// Multi-state leaves are smaller than the squares hashed directly, so their states are hashed as
+/// they are. Their hashes never equal the hash of the same cells in a Life grid.
+pub fn state_leaf_hash(grid: StateGrid) -> u64 {
+    grid.chunks(8).fold(mix(STATE_SEED), |hash, chunk| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(chunk);
+        mix(hash.rotate_left(23) ^ u64::from_le_bytes(bytes))
+    })
+}
+
+/// Returns the content hash of a square of 8 by 8 cells, given as its rows from top to bottom with
+/// the leftmost cell of each row in the highest bit.
+pub fn block_hash(block: u64) -> u64 {
+    mix(block ^ BLOCK_SEED)
+}
+
+/// Returns the content hash of a leaf.
+pub fn leaf_hash(grid: Grid) -> u64 {
+    square_hash(grid, 0, 0, LEAF_LEVEL.0)
+}
+
+/// Returns the content hash of the square of the grid with the given level whose upper left cell
+/// is `x` columns and `y` rows from the upper left of the grid.
+///
+/// The level must be at least 3, and `x` and `y` must be multiples of the side length.
+pub fn square_hash(grid: Grid, x: i64, y: i64, level: u8) -> u64 {
+    if level == BLOCK_LEVEL {
+        let shift = leaf::MAX_COORD - leaf::MIN_COORD - x - 7;
+        let block = (0..8).fold(0, |block, row| {
+            let bits = (leaf::get_row(grid, leaf::MIN_COORD + y + row) >> shift) & 0xff;
+            block << 8 | bits
+        });
+        block_hash(block)
+    } else {
+        let half = 1 << (level - 1);
+        combine(
+            Level(level),
+            [
+                square_hash(grid, x, y, level - 1),
+                square_hash(grid, x + half, y, level - 1),
+                square_hash(grid, x, y + half, level - 1),
+                square_hash(grid, x + half, y + half, level - 1),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaf_hash_depends_on_cells() {
+        let empty = leaf::empty();
+        let mut hashes = vec![leaf_hash(empty)];
+        for &(x, y) in &[
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (-1, -1),
+            (leaf::MAX_COORD, leaf::MIN_COORD),
+        ] {
+            hashes.push(leaf_hash(leaf::set_cell_alive(empty, x, y)));
+        }
+        let mut unique = hashes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), hashes.len());
+    }
+
+    #[test]
+    fn empty_hash_matches_leaf_hash() {
+        assert_eq!(empty_hash(LEAF_LEVEL), leaf_hash(leaf::empty()));
+    }
+}
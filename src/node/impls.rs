@@ -6,5 +6,8 @@
 
 mod cells;
 mod evolve;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod properties;
 mod region;
+mod states;
@@ -4,7 +4,10 @@
  * obtain one at http://mozilla.org/MPL/2.0/.
  */
 
-use crate::{node::*, BoundingBox, Cell, Position, Quadrant};
+use crate::{
+    node::{leaf::states, *},
+    BoundingBox, Cell, Position, Quadrant,
+};
 
 impl NodeId {
     /// Gets the cell at the given position in the node.
@@ -14,7 +17,7 @@ impl NodeId {
     /// ```
     /// let mut store = smeagol::node::Store::new();
     ///
-    /// let node = store.create_empty(smeagol::node::Level(5));
+    /// let node = store.create_empty(smeagol::node::Level(7));
     /// let origin = smeagol::Position::new(0, 0);
     /// assert_eq!(node.get_cell(&store, origin), smeagol::Cell::Dead);
     ///
@@ -23,11 +26,8 @@ impl NodeId {
     /// ```
     pub fn get_cell(self, store: &Store, pos: Position) -> Cell {
         match store.node(self) {
-            Node::Leaf { grid } => {
-                let x_offset = (7 - pos.x) as usize;
-                let y_offset = (pos.y + 8) as usize;
-                Cell::new(grid.extract(y_offset) & (1 << x_offset) > 0)
-            }
+            Node::Leaf { grid } => Cell::new(leaf::get_cell(grid, pos.x, pos.y)),
+            Node::StateLeaf { grid } => Cell::new(states::get_state(grid, pos.x, pos.y) != 0),
             Node::Interior {
                 nw,
                 ne,
@@ -51,12 +51,14 @@ impl NodeId {
 
     /// Sets the cell at the given position in the node to be an alive cell.
     ///
+    /// In a store of multi-state nodes, alive cells have state 1.
+    ///
     /// # Examples
     ///
     /// ```
     /// let mut store = smeagol::node::Store::new();
     ///
-    /// let node = store.create_empty(smeagol::node::Level(5));
+    /// let node = store.create_empty(smeagol::node::Level(7));
     /// let origin = smeagol::Position::new(0, 0);
     /// assert_eq!(node.get_cell(&store, origin), smeagol::Cell::Dead);
     ///
@@ -65,12 +67,14 @@ impl NodeId {
     /// ```
     pub fn set_cell_alive(self, store: &mut Store, pos: Position) -> NodeId {
         match store.node(self) {
-            Node::Leaf { mut grid } => {
-                let x_offset = (7 - pos.x) as usize;
-                let y_offset = (pos.y + 8) as usize;
-                grid = grid.replace(y_offset, grid.extract(y_offset) | (1 << x_offset));
+            Node::Leaf { grid } => {
+                let grid = leaf::set_cell_alive(grid, pos.x, pos.y);
                 store.create_leaf(grid)
             }
+            Node::StateLeaf { grid } => {
+                let grid = states::set_state(grid, pos.x, pos.y, 1);
+                store.create_state_leaf(grid)
+            }
             Node::Interior {
                 nw,
                 ne,
@@ -111,7 +115,7 @@ impl NodeId {
     /// ```
     /// let mut store = smeagol::node::Store::new();
     ///
-    /// let node = store.create_empty(smeagol::node::Level(5));
+    /// let node = store.create_empty(smeagol::node::Level(7));
     /// assert_eq!(node.get_alive_cells(&store), vec![]);
     ///
     /// let pos = smeagol::Position::new(1, 2);
@@ -121,23 +125,25 @@ impl NodeId {
     pub fn get_alive_cells(self, store: &Store) -> Vec<Position> {
         match store.node(self) {
             Node::Leaf { grid } => {
-                if grid.count_ones().wrapping_sum() == 0 {
+                if leaf::population(grid) == 0 {
                     return vec![];
                 }
 
                 let mut alive_coords = vec![];
-                for y in -8..8 {
-                    let y_offset = (y + 8) as usize;
-                    let row = grid.extract(y_offset);
-                    for x in -8..8 {
-                        let x_offset = (7 - x) as usize;
-                        if row & (1 << x_offset) > 0 {
+                for y in leaf::MIN_COORD..=leaf::MAX_COORD {
+                    let row = leaf::get_row(grid, y);
+                    for x in leaf::MIN_COORD..=leaf::MAX_COORD {
+                        if row & leaf::column_bit(x) > 0 {
                             alive_coords.push(Position { x, y });
                         }
                     }
                 }
                 alive_coords
             }
+            Node::StateLeaf { grid } => states::positions()
+                .filter(|&(x, y)| states::get_state(grid, x, y) != 0)
+                .map(|(x, y)| Position { x, y })
+                .collect(),
             Node::Interior {
                 nw,
                 ne,
@@ -206,12 +212,16 @@ impl NodeId {
         match store.node(self) {
             Node::Leaf { mut grid } => {
                 for &mut pos in coords {
-                    let x = (7 - (pos.x - offset_x)) as usize;
-                    let y = ((pos.y - offset_y) + 8) as usize;
-                    grid = grid.replace(y, grid.extract(y) | (1 << x));
+                    grid = leaf::set_cell_alive(grid, pos.x - offset_x, pos.y - offset_y);
                 }
                 store.create_leaf(grid)
             }
+            Node::StateLeaf { mut grid } => {
+                for &mut pos in coords {
+                    grid = states::set_state(grid, pos.x - offset_x, pos.y - offset_y, 1);
+                }
+                store.create_state_leaf(grid)
+            }
             Node::Interior {
                 nw,
                 ne,
@@ -266,20 +276,21 @@ impl NodeId {
 
         match store.node(self) {
             Node::Leaf { grid } => {
-                if grid.count_ones().wrapping_sum() == 0 {
+                if leaf::population(grid) == 0 {
                     return false;
                 }
                 for x in upper_left.x..=lower_right.x {
                     for y in upper_left.y..=lower_right.y {
-                        let x_offset = (7 - x) as usize;
-                        let y_offset = (y + 8) as usize;
-                        if grid.extract(y_offset) & (1 << x_offset) > 0 {
+                        if leaf::get_cell(grid, x, y) {
                             return true;
                         }
                     }
                 }
                 false
             }
+            Node::StateLeaf { grid } => (upper_left.y..=lower_right.y).any(|y| {
+                (upper_left.x..=lower_right.x).any(|x| states::get_state(grid, x, y) != 0)
+            }),
             Node::Interior {
                 nw,
                 ne,
@@ -390,33 +401,66 @@ impl NodeId {
     }
 
     /// Returns a bounding box that contains all the alive cells in the node.
+    ///
+    /// Bounding boxes are calculated once when nodes are created, so this takes constant time.
     pub fn bounding_box(self, store: &Store) -> Option<BoundingBox> {
+        store.bounding_box(self)
+    }
+
+    /// Removes all the alive cells in the node that lie outside the given bounding box.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut store = smeagol::node::Store::new();
+    ///
+    /// let node = store.create_empty(smeagol::node::Level(7));
+    /// let node = node.set_cell_alive(&mut store, smeagol::Position::new(0, 0));
+    /// let node = node.set_cell_alive(&mut store, smeagol::Position::new(3, 3));
+    ///
+    /// let origin = smeagol::Position::new(0, 0);
+    /// let node = node.crop(&mut store, smeagol::BoundingBox::new(origin, origin));
+    /// assert_eq!(node.get_alive_cells(&store), vec![origin]);
+    /// ```
+    pub fn crop(self, store: &mut Store, bounding_box: BoundingBox) -> NodeId {
+        let min = self.min_coord(store);
+        let max = self.max_coord(store);
+        let node_bounding_box = BoundingBox::new(Position::new(min, min), Position::new(max, max));
+
+        let bounding_box = match node_bounding_box.intersect(bounding_box) {
+            Some(bounding_box) => bounding_box,
+            None => return store.create_empty(self.level(store)),
+        };
+        if bounding_box == node_bounding_box {
+            return self;
+        }
+
+        let upper_left = bounding_box.upper_left;
+        let lower_right = bounding_box.lower_right;
+
         match store.node(self) {
             Node::Leaf { grid } => {
-                if grid.count_ones().wrapping_sum() == 0 {
-                    return None;
+                let mut row_mask = 0;
+                for x in upper_left.x..=lower_right.x {
+                    row_mask |= leaf::column_bit(x);
                 }
-                let mut min_x = i64::max_value();
-                let mut min_y = i64::max_value();
-                let mut max_x = i64::min_value();
-                let mut max_y = i64::min_value();
-                for y in -8..8 {
-                    let y_offset = (y + 8) as usize;
-                    let row = grid.extract(y_offset);
-                    for x in -8..8 {
-                        let x_offset = (7 - x) as usize;
-                        if row & (1 << x_offset) > 0 {
-                            min_x = min_x.min(x);
-                            min_y = min_y.min(y);
-                            max_x = max_x.max(x);
-                            max_y = max_y.max(y);
-                        }
+
+                let mut cropped = leaf::empty();
+                for y in upper_left.y..=lower_right.y {
+                    cropped = leaf::set_row(cropped, y, leaf::get_row(grid, y) & row_mask);
+                }
+
+                store.create_leaf(cropped)
+            }
+            Node::StateLeaf { grid } => {
+                let mut cropped = states::empty();
+                for y in upper_left.y..=lower_right.y {
+                    for x in upper_left.x..=lower_right.x {
+                        let state = states::get_state(grid, x, y);
+                        cropped = states::set_state(cropped, x, y, state);
                     }
                 }
-                Some(BoundingBox::new(
-                    Position::new(min_x, min_y),
-                    Position::new(max_x, max_y),
-                ))
+                store.create_state_leaf(cropped)
             }
             Node::Interior {
                 nw,
@@ -427,47 +471,18 @@ impl NodeId {
                 population,
             } => {
                 if population == 0 {
-                    return None;
+                    return self;
                 }
 
                 // quarter side length
                 let offset = 1 << (level.0 - 2);
 
-                let mut bounding_box = None::<BoundingBox>;
-
-                if let Some(nw_bounding_box) = nw.bounding_box(store) {
-                    let nw_bounding_box = nw_bounding_box.offset(-offset, -offset);
-                    bounding_box = Some(nw_bounding_box);
-                };
-
-                if let Some(ne_bounding_box) = ne.bounding_box(store) {
-                    let ne_bounding_box = ne_bounding_box.offset(offset, -offset);
-                    bounding_box = if let Some(bbox) = bounding_box {
-                        Some(bbox.combine(ne_bounding_box))
-                    } else {
-                        Some(ne_bounding_box)
-                    }
-                };
-
-                if let Some(sw_bounding_box) = sw.bounding_box(store) {
-                    let sw_bounding_box = sw_bounding_box.offset(-offset, offset);
-                    bounding_box = if let Some(bbox) = bounding_box {
-                        Some(bbox.combine(sw_bounding_box))
-                    } else {
-                        Some(sw_bounding_box)
-                    }
-                };
-
-                if let Some(se_bounding_box) = se.bounding_box(store) {
-                    let se_bounding_box = se_bounding_box.offset(offset, offset);
-                    bounding_box = if let Some(bbox) = bounding_box {
-                        Some(bbox.combine(se_bounding_box))
-                    } else {
-                        Some(se_bounding_box)
-                    }
-                };
+                let nw = nw.crop(store, bounding_box.offset(offset, offset));
+                let ne = ne.crop(store, bounding_box.offset(-offset, offset));
+                let sw = sw.crop(store, bounding_box.offset(offset, -offset));
+                let se = se.crop(store, bounding_box.offset(-offset, -offset));
 
-                bounding_box
+                store.create_interior(NodeTemplate { nw, ne, sw, se })
             }
         }
     }
@@ -535,21 +550,42 @@ mod tests {
         }
     }
 
-    mod level_4 {
+    #[test]
+    fn bounding_box() {
+        let mut store = Store::new();
+        let empty = store.create_empty(Level(leaf::BASE_LEVEL.0 + 2));
+        assert_eq!(empty.bounding_box(&store), None);
+
+        let cells = vec![
+            Position::new(-20, 3),
+            Position::new(5, -31),
+            Position::new(17, 30),
+        ];
+        let node = empty.set_cells_alive(&mut store, cells);
+        assert_eq!(
+            node.bounding_box(&store),
+            Some(BoundingBox::new(
+                Position::new(-20, -31),
+                Position::new(17, 30)
+            ))
+        );
+    }
+
+    mod leaf_level {
         use super::*;
 
         #[test]
         fn get_set() {
-            get_set_helper(4);
+            get_set_helper(LEAF_LEVEL.0);
         }
     }
 
-    mod level_5 {
+    mod base_level {
         use super::*;
 
         #[test]
         fn get_set() {
-            get_set_helper(5);
+            get_set_helper(leaf::BASE_LEVEL.0);
         }
     }
 }
@@ -5,173 +5,9 @@
  */
 
 use crate::node::*;
-use packed_simd::u16x16;
-
-#[derive(Clone, Copy, Debug)]
-struct Counts {
-    low: u16x16,
-    mid: u16x16,
-    high: u16x16,
-}
-
-impl Counts {
-    fn new() -> Self {
-        Self {
-            low: u16x16::splat(0),
-            mid: u16x16::splat(0),
-            high: u16x16::splat(0),
-        }
-    }
-
-    fn add(&mut self, neighbors: u16x16) {
-        // low bit half adder
-        let low_carry = self.low & neighbors;
-        self.low ^= neighbors;
-
-        // middle bit half adder
-        let mid_carry = self.mid & low_carry;
-        self.mid ^= low_carry;
-
-        // high bit saturating add
-        self.high |= mid_carry;
-    }
-}
-
-fn rotate_lanes_up(board: u16x16) -> u16x16 {
-    shuffle!(
-        board,
-        [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0]
-    )
-}
-
-fn rotate_lanes_down(board: u16x16) -> u16x16 {
-    shuffle!(
-        board,
-        [15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]
-    )
-}
-
-fn step_once_u16x16(board: u16x16) -> u16x16 {
-    let mut neighbors = Counts::new();
-
-    // +---+---+---+
-    // | * | * | * |
-    // +---+---+---+
-    // | * |   | * |
-    // +---+---+---+
-    // | * | * | * |
-    // +---+---+---+
-
-    // top row
-    neighbors.add(rotate_lanes_down(board) >> 1);
-    neighbors.add(rotate_lanes_down(board));
-    neighbors.add(rotate_lanes_down(board) << 1);
-
-    // middle row
-    neighbors.add(board >> 1);
-    neighbors.add(board << 1);
-
-    // bottom row
-    neighbors.add(rotate_lanes_up(board) >> 1);
-    neighbors.add(rotate_lanes_up(board));
-    neighbors.add(rotate_lanes_up(board) << 1);
-
-    // 2 is 010 in binary
-    let two_neighbors = !neighbors.high & neighbors.mid & !neighbors.low;
-    // 3 is 011 in binary
-    let three_neighbors = !neighbors.high & neighbors.mid & neighbors.low;
-
-    // if 2 neighbors, the cell doesn't change
-    // if 3 neighbors, the cell is alive
-    (two_neighbors & board) | three_neighbors
-}
-
-fn jump_u16x16(mut board: u16x16) -> u16x16 {
-    board = step_once_u16x16(board);
-    board = step_once_u16x16(board);
-    board = step_once_u16x16(board);
-    board = step_once_u16x16(board);
-    board
-}
-
-fn step_u16x16(mut board: u16x16, step_log_2: u8) -> u16x16 {
-    for _ in 0..(1 << step_log_2) {
-        board = step_once_u16x16(board);
-    }
-    board
-}
-
-fn horiz_jump_u16x16(w: u16x16, e: u16x16) -> u16x16 {
-    let grid = (w << 8) | (e >> 8);
-    jump_u16x16(grid)
-}
-
-fn vert_jump_u16x16(n: u16x16, s: u16x16) -> u16x16 {
-    let n = shuffle!(n, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
-        & LEVEL_4_UPPER_HALF_MASK;
-    let s = shuffle!(s, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
-        & LEVEL_4_LOWER_HALF_MASK;
-    let grid = n | s;
-    jump_u16x16(grid)
-}
-
-fn center_jump_u16x16(
-    nw_grid: u16x16,
-    ne_grid: u16x16,
-    sw_grid: u16x16,
-    se_grid: u16x16,
-) -> u16x16 {
-    let grid = center(nw_grid, ne_grid, sw_grid, se_grid);
-    jump_u16x16(grid)
-}
-
-fn combine_results_u16x16(
-    nw_grid: u16x16,
-    ne_grid: u16x16,
-    sw_grid: u16x16,
-    se_grid: u16x16,
-) -> u16x16 {
-    let nw_grid = nw_grid << 4;
-    let nw_grid = shuffle!(
-        nw_grid,
-        [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3]
-    ) & LEVEL_4_NW_MASK;
-
-    let ne_grid = ne_grid >> 4;
-    let ne_grid = shuffle!(
-        ne_grid,
-        [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3]
-    ) & LEVEL_4_NE_MASK;
-
-    let sw_grid = sw_grid << 4;
-    let sw_grid = shuffle!(
-        sw_grid,
-        [12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
-    ) & LEVEL_4_SW_MASK;
-
-    let se_grid = se_grid >> 4;
-    let se_grid = shuffle!(
-        se_grid,
-        [12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
-    ) & LEVEL_4_SE_MASK;
-
-    nw_grid | ne_grid | sw_grid | se_grid
-}
-
-fn horiz_u16x16(w: u16x16, e: u16x16) -> u16x16 {
-    (w << 8) | (e >> 8)
-}
-
-fn vert_u16x16(n: u16x16, s: u16x16) -> u16x16 {
-    let n = shuffle!(n, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
-        & LEVEL_4_UPPER_HALF_MASK;
-    let s = shuffle!(s, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
-        & LEVEL_4_LOWER_HALF_MASK;
-    n | s
-}
 
 #[allow(clippy::many_single_char_names)]
-fn step_level_5(
+fn step_base_level(
     store: &mut Store,
     step_log_2: u8,
     nw: NodeId,
@@ -203,13 +39,13 @@ fn step_level_5(
     // +---+---+---+---+---+---+---+---+
 
     let a = nw_grid;
-    let b = horiz_u16x16(nw_grid, ne_grid);
+    let b = leaf::horiz(nw_grid, ne_grid);
     let c = ne_grid;
-    let d = vert_u16x16(nw_grid, sw_grid);
-    let e = center(nw_grid, ne_grid, sw_grid, se_grid);
-    let f = vert_u16x16(ne_grid, se_grid);
+    let d = leaf::vert(nw_grid, sw_grid);
+    let e = leaf::center(nw_grid, ne_grid, sw_grid, se_grid);
+    let f = leaf::vert(ne_grid, se_grid);
     let g = sw_grid;
-    let h = horiz_u16x16(sw_grid, se_grid);
+    let h = leaf::horiz(sw_grid, se_grid);
     let i = se_grid;
 
     // +---+---+---+---+---+---+---+---+
@@ -230,16 +66,16 @@ fn step_level_5(
     // |   |   |   |   |   |   |   |   |
     // +---+---+---+---+---+---+---+---+
 
-    let w = step_u16x16(combine_results_u16x16(a, b, d, e), step_log_2);
-    let x = step_u16x16(combine_results_u16x16(b, c, e, f), step_log_2);
-    let y = step_u16x16(combine_results_u16x16(d, e, g, h), step_log_2);
-    let z = step_u16x16(combine_results_u16x16(e, f, h, i), step_log_2);
+    let w = leaf::step(leaf::combine_results(a, b, d, e), step_log_2);
+    let x = leaf::step(leaf::combine_results(b, c, e, f), step_log_2);
+    let y = leaf::step(leaf::combine_results(d, e, g, h), step_log_2);
+    let z = leaf::step(leaf::combine_results(e, f, h, i), step_log_2);
 
-    store.create_leaf(combine_results_u16x16(w, x, y, z))
+    store.create_leaf(leaf::combine_results(w, x, y, z))
 }
 
 #[allow(clippy::many_single_char_names)]
-fn jump_level_5(store: &mut Store, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+fn jump_base_level(store: &mut Store, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
     let nw_grid = store.node(nw).unwrap_leaf();
     let ne_grid = store.node(ne).unwrap_leaf();
     let sw_grid = store.node(sw).unwrap_leaf();
@@ -263,15 +99,15 @@ fn jump_level_5(store: &mut Store, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeI
     // |   |   |   |   |   |   |   |   |
     // +---+---+---+---+---+---+---+---+
 
-    let a = jump_u16x16(nw_grid);
-    let b = horiz_jump_u16x16(nw_grid, ne_grid);
-    let c = jump_u16x16(ne_grid);
-    let d = vert_jump_u16x16(nw_grid, sw_grid);;
-    let e = center_jump_u16x16(nw_grid, ne_grid, sw_grid, se_grid);
-    let f = vert_jump_u16x16(ne_grid, se_grid);;
-    let g = jump_u16x16(sw_grid);
-    let h = horiz_jump_u16x16(sw_grid, se_grid);
-    let i = jump_u16x16(se_grid);
+    let a = leaf::jump(nw_grid);
+    let b = leaf::jump(leaf::horiz(nw_grid, ne_grid));
+    let c = leaf::jump(ne_grid);
+    let d = leaf::jump(leaf::vert(nw_grid, sw_grid));
+    let e = leaf::jump(leaf::center(nw_grid, ne_grid, sw_grid, se_grid));
+    let f = leaf::jump(leaf::vert(ne_grid, se_grid));
+    let g = leaf::jump(sw_grid);
+    let h = leaf::jump(leaf::horiz(sw_grid, se_grid));
+    let i = leaf::jump(se_grid);
 
     // +---+---+---+---+---+---+---+---+
     // |   |   |   |   |   |   |   |   |
@@ -291,12 +127,34 @@ fn jump_level_5(store: &mut Store, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeI
     // |   |   |   |   |   |   |   |   |
     // +---+---+---+---+---+---+---+---+
 
-    let w = jump_u16x16(combine_results_u16x16(a, b, d, e));
-    let x = jump_u16x16(combine_results_u16x16(b, c, e, f));
-    let y = jump_u16x16(combine_results_u16x16(d, e, g, h));
-    let z = jump_u16x16(combine_results_u16x16(e, f, h, i));
+    let w = leaf::jump(leaf::combine_results(a, b, d, e));
+    let x = leaf::jump(leaf::combine_results(b, c, e, f));
+    let y = leaf::jump(leaf::combine_results(d, e, g, h));
+    let z = leaf::jump(leaf::combine_results(e, f, h, i));
 
-    store.create_leaf(combine_results_u16x16(w, x, y, z))
+    store.create_leaf(leaf::combine_results(w, x, y, z))
+}
+
+/// Advances four multi-state leaves `2^step_log_2` generations into the future according to the
+/// rule of the store.
+fn step_state_base_level(
+    store: &mut Store,
+    step_log_2: u8,
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+) -> NodeId {
+    let rule = store.rule().expect("store has no rule");
+    let grid = leaf::states::step(
+        rule,
+        store.node(nw).unwrap_state_leaf(),
+        store.node(ne).unwrap_state_leaf(),
+        store.node(sw).unwrap_state_leaf(),
+        store.node(se).unwrap_state_leaf(),
+        step_log_2,
+    );
+    store.create_state_leaf(grid)
 }
 
 fn horiz_jump(store: &mut Store, w: NodeId, e: NodeId) -> NodeId {
@@ -327,12 +185,12 @@ impl NodeId {
     /// Returns a level `n-1` node.
     #[allow(clippy::many_single_char_names)]
     pub fn jump(self, store: &mut Store) -> NodeId {
-        if let Some(jump) = store.get_jump(self) {
+        if let Some(jump) = store.find_jump(self) {
             return jump;
         }
 
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior {
                 nw,
                 ne,
@@ -345,8 +203,12 @@ impl NodeId {
                     return store.create_empty(Level(level.0 - 1));
                 }
 
-                if level == Level(5) {
-                    jump_level_5(store, nw, ne, sw, se)
+                if level == store.base_level() && store.rule().is_some() {
+                    let jump = step_state_base_level(store, level.0 - 2, nw, ne, sw, se);
+                    store.add_jump(self, jump);
+                    jump
+                } else if level == store.base_level() {
+                    jump_base_level(store, nw, ne, sw, se)
                 } else {
                     // +---+---+---+---+---+---+---+---+
                     // |   |   |   |   |   |   |   |   |
@@ -450,14 +312,14 @@ impl NodeId {
     /// Returns a level `n-1` node.
     #[allow(clippy::many_single_char_names)]
     pub fn step(self, store: &mut Store) -> NodeId {
-        if let Some(step) = store.get_step(self) {
+        if let Some(step) = store.find_step(self) {
             return step;
         }
 
         let step_log_2 = store.step_log_2();
 
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior {
                 nw,
                 ne,
@@ -476,8 +338,12 @@ impl NodeId {
                     return store.create_empty(Level(level.0 - 1));
                 }
 
-                if level == Level(5) {
-                    let step = step_level_5(store, step_log_2, nw, ne, sw, se);
+                if level == store.base_level() {
+                    let step = if store.rule().is_some() {
+                        step_state_base_level(store, step_log_2, nw, ne, sw, se)
+                    } else {
+                        step_base_level(store, step_log_2, nw, ne, sw, se)
+                    };
                     store.add_step(self, step);
                     step
                 } else {
@@ -580,275 +446,73 @@ impl NodeId {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Position;
+
+    /// A glider in the northwest quadrant of a node, next to the center, travelling southeast.
+    const SE_GLIDER: [(i64, i64); 5] = [(-2, -3), (-1, -2), (-3, -1), (-2, -1), (-1, -1)];
+
+    /// Returns the cells of the glider, mirrored into another quadrant.
+    fn glider(flip_x: bool, flip_y: bool) -> Vec<Position> {
+        let flip = |coord: i64, flip: bool| if flip { -1 - coord } else { coord };
+        SE_GLIDER
+            .iter()
+            .map(|&(x, y)| Position::new(flip(x, flip_x), flip(y, flip_y)))
+            .collect()
+    }
 
-    #[test]
-    fn nw_glider_jump() {
+    /// Advances a glider in a base level node by the given number of generations, and checks that
+    /// it moved diagonally away from its quadrant.
+    fn glider_helper(flip_x: bool, flip_y: bool, step_log_2: Option<u8>) {
         let mut store = Store::new();
+        let generations: i64 = match step_log_2 {
+            Some(step_log_2) => {
+                store.set_step_log_2(step_log_2);
+                1 << step_log_2
+            }
+            None => 1 << (leaf::BASE_LEVEL.0 - 2),
+        };
+
+        let cells = glider(flip_x, flip_y);
+        let empty = store.create_empty(leaf::BASE_LEVEL);
+        let node = empty.set_cells_alive(&mut store, cells.clone());
+        let result = match step_log_2 {
+            Some(_) => node.step(&mut store),
+            None => node.jump(&mut store),
+        };
+
+        // a glider moves one cell diagonally every four generations
+        let distance = generations / 4;
+        let dx = if flip_x { -distance } else { distance };
+        let dy = if flip_y { -distance } else { distance };
+        let moved = cells.into_iter().map(|pos| pos.offset(dx, dy));
+        let empty = store.create_empty(LEAF_LEVEL);
+        let expected = empty.set_cells_alive(&mut store, moved);
+
+        assert_eq!(result, expected);
+    }
 
-        let empty = store.create_empty(Level(4));
-        let glider = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0010,
-            0b0000_0000_0000_0001,
-            0b0000_0000_0000_0111,
-        ));
-
-        let level_5 = store.create_interior(NodeTemplate {
-            nw: glider,
-            ne: empty,
-            sw: empty,
-            se: empty,
-        });
-
-        let jump = level_5.jump(&mut store);
-        let expected_jump = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_1000_0000,
-            0b0000_0000_0100_0000,
-            0b0000_0001_1100_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        assert_eq!(jump, expected_jump);
+    #[test]
+    fn nw_glider_jump() {
+        glider_helper(false, false, None);
     }
 
     #[test]
     fn ne_glider_jump() {
-        let mut store = Store::new();
-
-        let empty = store.create_empty(Level(4));
-        let glider = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0100_0000_0000_0000,
-            0b1000_0000_0000_0000,
-            0b1110_0000_0000_0000,
-        ));
-
-        let level_5 = store.create_interior(NodeTemplate {
-            nw: empty,
-            ne: glider,
-            sw: empty,
-            se: empty,
-        });
-
-        let jump = level_5.jump(&mut store);
-        let expected_jump = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0001_0000_0000,
-            0b0000_0010_0000_0000,
-            0b0000_0011_1000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        assert_eq!(jump, expected_jump);
+        glider_helper(true, false, None);
     }
 
     #[test]
     fn sw_glider_jump() {
-        let mut store = Store::new();
-
-        let empty = store.create_empty(Level(4));
-        let glider = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0111,
-            0b0000_0000_0000_0001,
-            0b0000_0000_0000_0010,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        let level_5 = store.create_interior(NodeTemplate {
-            nw: empty,
-            ne: empty,
-            sw: glider,
-            se: empty,
-        });
-
-        let jump = level_5.jump(&mut store);
-        let expected_jump = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0001_1100_0000,
-            0b0000_0000_0100_0000,
-            0b0000_0000_1000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        assert_eq!(jump, expected_jump);
+        glider_helper(false, true, None);
     }
 
     #[test]
     fn se_glider_jump() {
-        let mut store = Store::new();
-
-        let empty = store.create_empty(Level(4));
-        let glider = store.create_leaf(u16x16::new(
-            0b1110_0000_0000_0000,
-            0b1000_0000_0000_0000,
-            0b0100_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        let level_5 = store.create_interior(NodeTemplate {
-            nw: empty,
-            ne: empty,
-            sw: empty,
-            se: glider,
-        });
-
-        let jump = level_5.jump(&mut store);
-        let expected_jump = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0011_1000_0000,
-            0b0000_0010_0000_0000,
-            0b0000_0001_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        assert_eq!(jump, expected_jump);
+        glider_helper(true, true, None);
     }
 
     #[test]
     fn nw_glider_step() {
-        let mut store = Store::new();
-        store.set_step_log_2(2);
-
-        let empty = store.create_empty(Level(4));
-        let glider = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0010,
-            0b0000_0000_0000_0001,
-            0b0000_0000_0000_0111,
-        ));
-
-        let level_5 = store.create_interior(NodeTemplate {
-            nw: glider,
-            ne: empty,
-            sw: empty,
-            se: empty,
-        });
-
-        let step = level_5.step(&mut store);
-        let expected_step = store.create_leaf(u16x16::new(
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0001_0000_0000,
-            0b0000_0000_1000_0000,
-            0b0000_0011_1000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-            0b0000_0000_0000_0000,
-        ));
-
-        assert_eq!(step, expected_step);
+        glider_helper(false, false, Some(2));
     }
 }
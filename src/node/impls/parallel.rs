@@ -0,0 +1,268 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::node::*;
+
+/// Nodes below this level are always evolved on a single thread, since there isn't enough work in
+/// them to make up for layering and merging stores.
+const MIN_PARALLEL_LEVEL: Level = Level(leaf::BASE_LEVEL.0 + 5);
+
+/// Returns how many levels of the quadtree to split across worker threads.
+///
+/// Every level splits the work into at least four parts, so this is enough to give each thread of
+/// the current rayon thread pool at least one part.
+fn parallel_depth() -> u8 {
+    let threads = rayon::current_num_threads();
+    let mut depth = 0;
+    while 1 << (2 * depth) < threads {
+        depth += 1;
+    }
+    depth
+}
+
+impl NodeId {
+    /// For a level `n` node, advances the node `2^(n-2)` generations into the future, using the
+    /// threads of the current rayon thread pool.
+    ///
+    /// Returns a level `n-1` node with the same cells as `jump`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smeagol::node::{Level, Store};
+    ///
+    /// let mut store = Store::new();
+    /// let empty = store.create_empty(Level(12));
+    /// let node = empty.set_cell_alive(&mut store, smeagol::Position::new(0, 0));
+    ///
+    /// let parallel = node.par_jump(&mut store);
+    /// let sequential = node.jump(&mut store);
+    /// assert_eq!(parallel, sequential);
+    /// ```
+    pub fn par_jump(self, store: &mut Store) -> NodeId {
+        self.par_jump_to_depth(store, parallel_depth())
+    }
+
+    /// For a level `n` node, advances the node `step_size` generations into the future, using the
+    /// threads of the current rayon thread pool.
+    ///
+    /// The step size is determined by the store.
+    ///
+    /// Returns a level `n-1` node with the same cells as `step`.
+    pub fn par_step(self, store: &mut Store) -> NodeId {
+        self.par_step_to_depth(store, parallel_depth())
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    fn par_jump_to_depth(self, store: &mut Store, depth: u8) -> NodeId {
+        if let Some(jump) = store.find_jump(self) {
+            return jump;
+        }
+
+        let level = self.level(store);
+        if depth == 0 || level < MIN_PARALLEL_LEVEL || self.population(store) == 0 {
+            return self.jump(store);
+        }
+
+        let nw = self.nw(store);
+        let ne = self.ne(store);
+        let sw = self.sw(store);
+        let se = self.se(store);
+
+        // the nine overlapping nodes A through I, as in `jump`
+        let nodes = [
+            nw,
+            store.create_interior(NodeTemplate {
+                nw: nw.ne(store),
+                ne: ne.nw(store),
+                sw: nw.se(store),
+                se: ne.sw(store),
+            }),
+            ne,
+            store.create_interior(NodeTemplate {
+                nw: nw.sw(store),
+                ne: nw.se(store),
+                sw: sw.nw(store),
+                se: sw.ne(store),
+            }),
+            self.center_subnode(store),
+            store.create_interior(NodeTemplate {
+                nw: ne.sw(store),
+                ne: ne.se(store),
+                sw: se.nw(store),
+                se: se.ne(store),
+            }),
+            sw,
+            store.create_interior(NodeTemplate {
+                nw: sw.ne(store),
+                ne: se.nw(store),
+                sw: sw.se(store),
+                se: se.sw(store),
+            }),
+            se,
+        ];
+        let jumps = store.par_map(&nodes, |store, id| id.par_jump_to_depth(store, depth - 1));
+
+        let mut parts = [nw; 9];
+        parts.copy_from_slice(&jumps);
+        let jump = par_combine(store, parts, |store, id| {
+            id.par_jump_to_depth(store, depth - 1)
+        });
+        store.add_jump(self, jump);
+        jump
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    fn par_step_to_depth(self, store: &mut Store, depth: u8) -> NodeId {
+        if let Some(step) = store.find_step(self) {
+            return step;
+        }
+
+        let level = self.level(store);
+        if depth == 0 || level < MIN_PARALLEL_LEVEL || self.population(store) == 0 {
+            return self.step(store);
+        }
+
+        if store.step_log_2() == level.0 - 2 {
+            let step = self.par_jump_to_depth(store, depth);
+            store.add_step(self, step);
+            return step;
+        }
+
+        // the nine overlapping nodes A through I, as in `step`
+        let nw = self.nw(store);
+        let ne = self.ne(store);
+        let sw = self.sw(store);
+        let se = self.se(store);
+        let a = nw.center_subnode(store);
+        let b = self.north_subsubnode(store);
+        let c = ne.center_subnode(store);
+        let d = self.west_subsubnode(store);
+        let e = self.center_subnode(store).center_subnode(store);
+        let f = self.east_subsubnode(store);
+        let g = sw.center_subnode(store);
+        let h = self.south_subsubnode(store);
+        let i = se.center_subnode(store);
+
+        let step = par_combine(store, [a, b, c, d, e, f, g, h, i], |store, id| {
+            id.par_step_to_depth(store, depth - 1)
+        });
+        store.add_step(self, step);
+        step
+    }
+}
+
+/// Combines the nine overlapping nodes A through I into the four nodes W through Z, evolves them in
+/// parallel with the given function, and combines the results into one node.
+#[allow(clippy::many_single_char_names)]
+fn par_combine<F>(store: &mut Store, nodes: [NodeId; 9], evolve: F) -> NodeId
+where
+    F: Fn(&mut Store, NodeId) -> NodeId + Sync,
+{
+    let [a, b, c, d, e, f, g, h, i] = nodes;
+    let nodes = [
+        store.create_interior(NodeTemplate {
+            nw: a,
+            ne: b,
+            sw: d,
+            se: e,
+        }),
+        store.create_interior(NodeTemplate {
+            nw: b,
+            ne: c,
+            sw: e,
+            se: f,
+        }),
+        store.create_interior(NodeTemplate {
+            nw: d,
+            ne: e,
+            sw: g,
+            se: h,
+        }),
+        store.create_interior(NodeTemplate {
+            nw: e,
+            ne: f,
+            sw: h,
+            se: i,
+        }),
+    ];
+    let results = store.par_map(&nodes, evolve);
+    store.create_interior(NodeTemplate {
+        nw: results[0],
+        ne: results[1],
+        sw: results[2],
+        se: results[3],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn soup(store: &mut Store, level: Level) -> NodeId {
+        let mut state: u64 = 1;
+        let mut cells = vec![];
+        for y in -32..32 {
+            for x in -32..32 {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                if state >> 62 == 0 {
+                    cells.push(Position::new(x, y));
+                }
+            }
+        }
+        store.create_empty(level).set_cells_alive(store, cells)
+    }
+
+    #[test]
+    fn par_jump() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        let mut store = Store::new();
+        let node = soup(&mut store, MIN_PARALLEL_LEVEL);
+        let jump = node.jump(&mut store);
+
+        let mut par_store = Store::new();
+        let par_node = soup(&mut par_store, MIN_PARALLEL_LEVEL);
+        let par_jump = pool.install(|| par_node.par_jump(&mut par_store));
+
+        assert_eq!(
+            par_jump.get_alive_cells(&par_store),
+            jump.get_alive_cells(&store)
+        );
+        assert_eq!(par_node.par_jump(&mut par_store), par_jump);
+    }
+
+    #[test]
+    fn par_step() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        for &step_log_2 in &[0, 3] {
+            let mut store = Store::new();
+            store.set_step_log_2(step_log_2);
+            let node = soup(&mut store, MIN_PARALLEL_LEVEL);
+            let step = node.step(&mut store);
+
+            let mut par_store = Store::new();
+            par_store.set_step_log_2(step_log_2);
+            let par_node = soup(&mut par_store, MIN_PARALLEL_LEVEL);
+            let par_step = pool.install(|| par_node.par_step(&mut par_store));
+
+            assert_eq!(
+                par_step.get_alive_cells(&par_store),
+                step.get_alive_cells(&store)
+            );
+        }
+    }
+}
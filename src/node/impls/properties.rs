@@ -10,7 +10,8 @@ impl NodeId {
     /// Returns the level of the node.
     pub fn level(self, store: &Store) -> Level {
         match store.node(self) {
-            Node::Leaf { .. } => Level(4),
+            Node::Leaf { .. } => LEAF_LEVEL,
+            Node::StateLeaf { .. } => STATE_LEAF_LEVEL,
             Node::Interior { level, .. } => level,
         }
     }
@@ -18,20 +19,30 @@ impl NodeId {
     /// Returns the number of alive cells in the node.
     pub fn population(self, store: &Store) -> u128 {
         match store.node(self) {
-            Node::Leaf { grid } => u128::from(grid.count_ones().wrapping_sum()),
+            Node::Leaf { grid } => leaf::population(grid),
+            Node::StateLeaf { grid } => leaf::states::population(grid),
             Node::Interior { population, .. } => population,
         }
     }
 
+    /// Returns a hash of the cells in the node.
+    ///
+    /// Nodes with the same cells have the same content hash, even in different stores or different
+    /// runs of the program.
+    pub fn content_hash(self, store: &Store) -> u64 {
+        store.content_hash(self)
+    }
+
     /// Returns the minimum coordinate that can be used in a position for the node.
     ///
     /// For a level `n` node, this is equal to `-2^(n-1)`.
     pub fn min_coord(self, store: &Store) -> i64 {
         match store.node(self) {
-            Node::Leaf { .. } => -8,
+            Node::Leaf { .. } => leaf::MIN_COORD,
+            Node::StateLeaf { .. } => leaf::states::MIN_COORD,
             Node::Interior { level, .. } => {
                 if level == Level(64) {
-                    i64::min_value()
+                    i64::MIN
                 } else {
                     -(1 << (level.0 - 1))
                 }
@@ -44,10 +55,11 @@ impl NodeId {
     /// For a level `n` node, this is equal to `2^(n-1) - 1`.
     pub fn max_coord(self, store: &Store) -> i64 {
         match store.node(self) {
-            Node::Leaf { .. } => 7,
+            Node::Leaf { .. } => leaf::MAX_COORD,
+            Node::StateLeaf { .. } => leaf::states::MAX_COORD,
             Node::Interior { level, .. } => {
                 if level == Level(64) {
-                    i64::max_value()
+                    i64::MAX
                 } else {
                     (1 << (level.0 - 1)) - 1
                 }
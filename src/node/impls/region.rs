@@ -14,7 +14,7 @@ impl NodeId {
     /// Panics if the node is a leaf node.
     pub fn expand(self, store: &mut Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior {
                 nw,
                 ne,
@@ -75,7 +75,7 @@ impl NodeId {
     /// ```
     pub fn nw(self, store: &Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior { nw, .. } => nw,
         }
     }
@@ -97,7 +97,7 @@ impl NodeId {
     /// ```
     pub fn ne(self, store: &Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior { ne, .. } => ne,
         }
     }
@@ -119,7 +119,7 @@ impl NodeId {
     /// ```
     pub fn sw(self, store: &Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior { sw, .. } => sw,
         }
     }
@@ -141,7 +141,7 @@ impl NodeId {
     /// ```
     pub fn se(self, store: &Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior { se, .. } => se,
         }
     }
@@ -167,7 +167,7 @@ impl NodeId {
     /// ```
     pub fn center_subnode(self, store: &mut Store) -> NodeId {
         match store.node(self) {
-            Node::Leaf { .. } => panic!(),
+            Node::Leaf { .. } | Node::StateLeaf { .. } => panic!(),
             Node::Interior {
                 nw,
                 ne,
@@ -176,12 +176,8 @@ impl NodeId {
                 level,
                 ..
             } => {
-                if level == Level(5) {
-                    let nw_grid = store.node(nw).unwrap_leaf();
-                    let ne_grid = store.node(ne).unwrap_leaf();
-                    let sw_grid = store.node(sw).unwrap_leaf();
-                    let se_grid = store.node(se).unwrap_leaf();
-                    store.create_leaf(center(nw_grid, ne_grid, sw_grid, se_grid))
+                if level == store.base_level() {
+                    center_of_leaves(store, nw, ne, sw, se)
                 } else {
                     let template = NodeTemplate {
                         nw: nw.se(store),
@@ -199,7 +195,7 @@ impl NodeId {
     ///
     /// # Panics
     ///
-    /// Panics if the node is a leaf node or level 5.
+    /// Panics if the node is a leaf node or at the base level.
     ///
     /// # Diagram
     ///
@@ -232,7 +228,7 @@ impl NodeId {
     ///
     /// # Panics
     ///
-    /// Panics if the node is a leaf node or level 5.
+    /// Panics if the node is a leaf node or at the base level.
     ///
     /// # Diagram
     ///
@@ -265,7 +261,7 @@ impl NodeId {
     ///
     /// # Panics
     ///
-    /// Panics if the node is a leaf node or level 5.
+    /// Panics if the node is a leaf node or at the base level.
     ///
     /// # Diagram
     ///
@@ -298,7 +294,7 @@ impl NodeId {
     ///
     /// # Panics
     ///
-    /// Panics if the node is a leaf node or level 5.
+    /// Panics if the node is a leaf node or at the base level.
     ///
     /// # Diagram
     ///
@@ -350,7 +346,6 @@ impl NodeId {
 /// ```
 fn centered_horiz(store: &mut Store, w: NodeId, e: NodeId) -> NodeId {
     match (store.node(w), store.node(e)) {
-        (Node::Leaf { .. }, Node::Leaf { .. }) => panic!(),
         (
             Node::Interior {
                 level,
@@ -362,12 +357,8 @@ fn centered_horiz(store: &mut Store, w: NodeId, e: NodeId) -> NodeId {
                 nw: e_nw, sw: e_sw, ..
             },
         ) => {
-            if level == Level(5) {
-                let nw_grid = store.node(w_ne).unwrap_leaf();
-                let ne_grid = store.node(e_nw).unwrap_leaf();
-                let sw_grid = store.node(w_se).unwrap_leaf();
-                let se_grid = store.node(e_sw).unwrap_leaf();
-                store.create_leaf(center(nw_grid, ne_grid, sw_grid, se_grid))
+            if level == store.base_level() {
+                center_of_leaves(store, w_ne, e_nw, w_se, e_sw)
             } else {
                 let nw = w_ne.se(store);
                 let ne = e_nw.sw(store);
@@ -376,7 +367,7 @@ fn centered_horiz(store: &mut Store, w: NodeId, e: NodeId) -> NodeId {
                 store.create_interior(NodeTemplate { nw, ne, sw, se })
             }
         }
-        _ => unreachable!(),
+        _ => panic!(),
     }
 }
 
@@ -410,7 +401,6 @@ fn centered_horiz(store: &mut Store, w: NodeId, e: NodeId) -> NodeId {
 /// ```
 fn centered_vert(store: &mut Store, n: NodeId, s: NodeId) -> NodeId {
     match (store.node(n), store.node(s)) {
-        (Node::Leaf { .. }, Node::Leaf { .. }) => panic!(),
         (
             Node::Interior {
                 level,
@@ -422,12 +412,8 @@ fn centered_vert(store: &mut Store, n: NodeId, s: NodeId) -> NodeId {
                 nw: s_nw, ne: s_ne, ..
             },
         ) => {
-            if level == Level(5) {
-                let nw_grid = store.node(n_sw).unwrap_leaf();
-                let ne_grid = store.node(n_se).unwrap_leaf();
-                let sw_grid = store.node(s_nw).unwrap_leaf();
-                let se_grid = store.node(s_ne).unwrap_leaf();
-                store.create_leaf(center(nw_grid, ne_grid, sw_grid, se_grid))
+            if level == store.base_level() {
+                center_of_leaves(store, n_sw, n_se, s_nw, s_ne)
             } else {
                 let nw = n_sw.se(store);
                 let ne = n_se.sw(store);
@@ -436,11 +422,32 @@ fn centered_vert(store: &mut Store, n: NodeId, s: NodeId) -> NodeId {
                 store.create_interior(NodeTemplate { nw, ne, sw, se })
             }
         }
-        _ => unreachable!(),
+        _ => panic!(),
     }
 }
 
-#[cfg(test)]
+/// Returns the leaf in the center of four leaves arranged in a square.
+fn center_of_leaves(store: &mut Store, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+    match store.node(nw) {
+        Node::Leaf { .. } => {
+            let nw_grid = store.node(nw).unwrap_leaf();
+            let ne_grid = store.node(ne).unwrap_leaf();
+            let sw_grid = store.node(sw).unwrap_leaf();
+            let se_grid = store.node(se).unwrap_leaf();
+            store.create_leaf(leaf::center(nw_grid, ne_grid, sw_grid, se_grid))
+        }
+        Node::StateLeaf { .. } => {
+            let nw_grid = store.node(nw).unwrap_state_leaf();
+            let ne_grid = store.node(ne).unwrap_state_leaf();
+            let sw_grid = store.node(sw).unwrap_state_leaf();
+            let se_grid = store.node(se).unwrap_state_leaf();
+            store.create_state_leaf(leaf::states::center(nw_grid, ne_grid, sw_grid, se_grid))
+        }
+        Node::Interior { .. } => panic!(),
+    }
+}
+
+#[cfg(all(test, not(feature = "leaf64")))]
 mod tests {
     use super::*;
     use crate::Position;
@@ -0,0 +1,178 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+    node::{leaf::states, *},
+    Position, Quadrant,
+};
+
+impl NodeId {
+    /// Gets the state of the cell at the given position in a multi-state node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not in a store of multi-state nodes.
+    pub fn get_state(self, store: &Store, pos: Position) -> u8 {
+        match store.node(self) {
+            Node::StateLeaf { grid } => states::get_state(grid, pos.x, pos.y),
+            Node::Leaf { .. } => panic!(),
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                ..
+            } => {
+                // quarter side length
+                let offset = 1 << (level.0 - 2);
+
+                match pos.quadrant() {
+                    Quadrant::Northwest => nw.get_state(store, pos.offset(offset, offset)),
+                    Quadrant::Northeast => ne.get_state(store, pos.offset(-offset, offset)),
+                    Quadrant::Southwest => sw.get_state(store, pos.offset(offset, -offset)),
+                    Quadrant::Southeast => se.get_state(store, pos.offset(-offset, -offset)),
+                }
+            }
+        }
+    }
+
+    /// Sets the state of the cell at the given position in a multi-state node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not in a store of multi-state nodes.
+    pub fn set_state(self, store: &mut Store, pos: Position, state: u8) -> NodeId {
+        match store.node(self) {
+            Node::StateLeaf { grid } => {
+                let grid = states::set_state(grid, pos.x, pos.y, state);
+                store.create_state_leaf(grid)
+            }
+            Node::Leaf { .. } => panic!(),
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                ..
+            } => {
+                // quarter side length
+                let offset = 1 << (level.0 - 2);
+
+                let (nw, ne, sw, se) = match pos.quadrant() {
+                    Quadrant::Northwest => {
+                        let nw = nw.set_state(store, pos.offset(offset, offset), state);
+                        (nw, ne, sw, se)
+                    }
+                    Quadrant::Northeast => {
+                        let ne = ne.set_state(store, pos.offset(-offset, offset), state);
+                        (nw, ne, sw, se)
+                    }
+                    Quadrant::Southwest => {
+                        let sw = sw.set_state(store, pos.offset(offset, -offset), state);
+                        (nw, ne, sw, se)
+                    }
+                    Quadrant::Southeast => {
+                        let se = se.set_state(store, pos.offset(-offset, -offset), state);
+                        (nw, ne, sw, se)
+                    }
+                };
+                store.create_interior(NodeTemplate { nw, ne, sw, se })
+            }
+        }
+    }
+
+    /// Returns a list of the positions and states of all the non-empty cells in a multi-state
+    /// node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is not in a store of multi-state nodes.
+    pub fn get_states(self, store: &Store) -> Vec<(Position, u8)> {
+        match store.node(self) {
+            Node::StateLeaf { grid } => states::positions()
+                .map(|(x, y)| (Position { x, y }, states::get_state(grid, x, y)))
+                .filter(|&(_, state)| state != 0)
+                .collect(),
+            Node::Leaf { .. } => panic!(),
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                population,
+            } => {
+                if population == 0 {
+                    return vec![];
+                }
+
+                // quarter side length
+                let offset = 1 << (level.0 - 2);
+
+                let mut cells = vec![];
+                for &(child, dx, dy) in &[
+                    (nw, -offset, -offset),
+                    (ne, offset, -offset),
+                    (sw, -offset, offset),
+                    (se, offset, offset),
+                ] {
+                    cells.extend(
+                        child
+                            .get_states(store)
+                            .into_iter()
+                            .map(|(pos, state)| (pos.offset(dx, dy), state)),
+                    );
+                }
+                cells
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::table::Rule;
+
+    #[test]
+    fn get_set_states() {
+        let rule = Rule::from_file("./assets/wireworld.rule").unwrap();
+        let mut store = Store::with_rule(rule);
+        let mut node = store.create_empty(Level(5));
+        let positions = [
+            Position::new(-16, -16),
+            Position::new(15, 15),
+            Position::new(0, -1),
+            Position::new(3, 7),
+        ];
+        for (i, &pos) in positions.iter().enumerate() {
+            node = node.set_state(&mut store, pos, (i % 3 + 1) as u8);
+        }
+        for (i, &pos) in positions.iter().enumerate() {
+            assert_eq!(node.get_state(&store, pos), (i % 3 + 1) as u8);
+        }
+        assert_eq!(node.population(&store), 4);
+
+        let mut cells = node.get_states(&store);
+        cells.sort();
+        let mut expected = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &pos)| (pos, (i % 3 + 1) as u8))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(cells, expected);
+        assert_eq!(
+            node.bounding_box(&store),
+            Some(crate::BoundingBox::new(
+                Position::new(-16, -16),
+                Position::new(15, 15)
+            ))
+        );
+    }
+}
@@ -0,0 +1,170 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Leaf grids and the base case of the HashLife algorithm.
+//!
+//! By default leaves are 16 by 16 grids stored as `u16x16`. With the `leaf64` feature enabled,
+//! leaves are 64 by 64 grids stored as 64 rows of `u64`, which means fewer nodes in the store and
+//! larger blocks of cells evolved at once. There is no explicit vector code for 64 by 64 leaves;
+//! how much of it runs on SIMD instructions is up to the compiler.
+//!
+//! Rows are numbered from the top of the grid, and in each row the leftmost cell is the most
+//! significant bit.
+
+#[cfg(not(feature = "leaf64"))]
+mod grid16;
+#[cfg(feature = "leaf64")]
+mod grid64;
+pub mod states;
+
+#[cfg(not(feature = "leaf64"))]
+pub use self::grid16::*;
+#[cfg(feature = "leaf64")]
+pub use self::grid64::*;
+
+use crate::{node::Level, BoundingBox, Position};
+
+/// The level above the leaf level, where the base case of the HashLife algorithm is computed.
+pub const BASE_LEVEL: Level = Level(LEAF_LEVEL.0 + 1);
+
+/// The minimum coordinate that can be used in a position for a leaf.
+pub const MIN_COORD: i64 = -(1 << (LEAF_LEVEL.0 - 1));
+
+/// The maximum coordinate that can be used in a position for a leaf.
+pub const MAX_COORD: i64 = (1 << (LEAF_LEVEL.0 - 1)) - 1;
+
+/// Returns the bit of a row corresponding to the given x coordinate.
+pub fn column_bit(x: i64) -> u64 {
+    1 << (MAX_COORD - x)
+}
+
+/// Returns the row of the grid at the given y coordinate.
+pub fn get_row(grid: Grid, y: i64) -> u64 {
+    row(grid, (y - MIN_COORD) as usize)
+}
+
+/// Returns a copy of the grid with the row at the given y coordinate replaced.
+pub fn set_row(grid: Grid, y: i64, value: u64) -> Grid {
+    replace_row(grid, (y - MIN_COORD) as usize, value)
+}
+
+/// Returns true if the cell at the given position is alive.
+pub fn get_cell(grid: Grid, x: i64, y: i64) -> bool {
+    get_row(grid, y) & column_bit(x) != 0
+}
+
+/// Returns a copy of the grid with the cell at the given position set to be alive.
+pub fn set_cell_alive(grid: Grid, x: i64, y: i64) -> Grid {
+    set_row(grid, y, get_row(grid, y) | column_bit(x))
+}
+
+/// Returns the smallest bounding box that contains all the alive cells in the grid.
+pub fn bounding_box(grid: Grid) -> Option<BoundingBox> {
+    let mut columns = 0;
+    let mut rows = None;
+    for y in MIN_COORD..=MAX_COORD {
+        let row = get_row(grid, y);
+        if row != 0 {
+            columns |= row;
+            rows = Some(rows.map_or((y, y), |(min_y, _)| (min_y, y)));
+        }
+    }
+    rows.map(|(min_y, max_y)| {
+        // the leftmost column is the most significant bit
+        let min_x = MAX_COORD - (63 - i64::from(columns.leading_zeros()));
+        let max_x = MAX_COORD - i64::from(columns.trailing_zeros());
+        BoundingBox::new(Position::new(min_x, min_y), Position::new(max_x, max_y))
+    })
+}
+
+/// Advances the grid `2^(LEAF_LEVEL - 2)` generations into the future.
+///
+/// Only the center of the result, half the size of the grid, is valid.
+pub fn jump(grid: Grid) -> Grid {
+    step(grid, LEAF_LEVEL.0 - 2)
+}
+
+/// Advances the grid `2^step_log_2` generations into the future.
+pub fn step(mut grid: Grid, step_log_2: u8) -> Grid {
+    for _ in 0..(1 << step_log_2) {
+        grid = step_once(grid);
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(cells: &[(i64, i64)]) -> Grid {
+        cells
+            .iter()
+            .fold(empty(), |grid, &(x, y)| set_cell_alive(grid, x, y))
+    }
+
+    #[test]
+    fn get_set() {
+        for &(x, y) in &[(MIN_COORD, MIN_COORD), (0, 0), (MAX_COORD, -1)] {
+            let grid = grid(&[(x, y)]);
+            assert!(get_cell(grid, x, y));
+            assert_eq!(population(grid), 1);
+        }
+    }
+
+    #[test]
+    fn bounding_box() {
+        assert_eq!(super::bounding_box(empty()), None);
+        for cells in &[
+            vec![(0, 0)],
+            vec![(MIN_COORD, MAX_COORD), (MAX_COORD, MIN_COORD)],
+            vec![(-1, 2), (3, -4), (0, 1)],
+        ] {
+            let min_x = cells.iter().map(|&(x, _)| x).min().unwrap();
+            let min_y = cells.iter().map(|&(_, y)| y).min().unwrap();
+            let max_x = cells.iter().map(|&(x, _)| x).max().unwrap();
+            let max_y = cells.iter().map(|&(_, y)| y).max().unwrap();
+            assert_eq!(
+                super::bounding_box(grid(cells)),
+                Some(BoundingBox::new(
+                    Position::new(min_x, min_y),
+                    Position::new(max_x, max_y)
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn step_blinker() {
+        let horizontal = grid(&[(-1, 0), (0, 0), (1, 0)]);
+        let vertical = grid(&[(0, -1), (0, 0), (0, 1)]);
+        assert_eq!(step_once(horizontal), vertical);
+        assert_eq!(step(horizontal, 1), horizontal);
+    }
+
+    #[test]
+    fn center_and_combine() {
+        // one cell in the middle of each quadrant
+        let quarter = (MAX_COORD + 1) / 2;
+        let cells = [
+            (-quarter, -quarter),
+            (quarter, -quarter),
+            (-quarter, quarter),
+            (quarter, quarter),
+        ];
+        let full = grid(&cells);
+
+        let nw = grid(&[(quarter, quarter)]);
+        let ne = grid(&[(-quarter, quarter)]);
+        let sw = grid(&[(quarter, -quarter)]);
+        let se = grid(&[(-quarter, -quarter)]);
+        assert_eq!(center(nw, ne, sw, se), full);
+
+        let single = grid(&[(0, 0)]);
+        assert_eq!(combine_results(single, single, single, single), full);
+        assert_eq!(horiz(single, single), grid(&[(-quarter * 2, 0)]));
+        assert_eq!(vert(single, single), grid(&[(0, -quarter * 2)]));
+    }
+}
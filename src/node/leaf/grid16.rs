@@ -0,0 +1,298 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! 16 by 16 leaf grids.
+
+use crate::node::{simd::u16x16, Level};
+
+/// A leaf grid.
+pub type Grid = u16x16;
+
+/// The level of a leaf.
+pub const LEAF_LEVEL: Level = Level(4);
+
+const LEVEL_4_UPPER_HALF_MASK: u16x16 = u16x16::new(
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+);
+
+const LEVEL_4_LOWER_HALF_MASK: u16x16 = u16x16::new(
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+    0b1111_1111_1111_1111,
+);
+
+const LEVEL_4_NW_MASK: u16x16 = u16x16::new(
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+);
+
+const LEVEL_4_NE_MASK: u16x16 = u16x16::new(
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+);
+
+const LEVEL_4_SW_MASK: u16x16 = u16x16::new(
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+    0b1111_1111_0000_0000,
+);
+
+const LEVEL_4_SE_MASK: u16x16 = u16x16::new(
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_0000_0000,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+    0b0000_0000_1111_1111,
+);
+
+/// Returns the grid centered on the corner shared by four grids.
+pub fn center(nw_grid: u16x16, ne_grid: u16x16, sw_grid: u16x16, se_grid: u16x16) -> u16x16 {
+    let nw_grid = nw_grid << 8;
+    let sw_grid = sw_grid << 8;
+    let left: u16x16 = shuffle!(
+        nw_grid,
+        sw_grid,
+        [8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
+    );
+
+    let ne_grid = ne_grid >> 8;
+    let se_grid = se_grid >> 8;
+    let right: u16x16 = shuffle!(
+        ne_grid,
+        se_grid,
+        [8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
+    );
+
+    left | right
+}
+
+/// Returns an empty grid.
+pub fn empty() -> u16x16 {
+    u16x16::splat(0)
+}
+
+/// Returns the row of the grid with the given index.
+pub fn row(grid: u16x16, index: usize) -> u64 {
+    u64::from(grid.extract(index))
+}
+
+/// Returns a copy of the grid with the row with the given index replaced.
+pub fn replace_row(grid: u16x16, index: usize, value: u64) -> u16x16 {
+    grid.replace(index, value as u16)
+}
+
+/// Returns the number of alive cells in the grid.
+pub fn population(grid: u16x16) -> u128 {
+    u128::from(grid.count_ones().wrapping_sum())
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Counts {
+    low: u16x16,
+    mid: u16x16,
+    high: u16x16,
+}
+
+impl Counts {
+    fn new() -> Self {
+        Self {
+            low: u16x16::splat(0),
+            mid: u16x16::splat(0),
+            high: u16x16::splat(0),
+        }
+    }
+
+    fn add(&mut self, neighbors: u16x16) {
+        // low bit half adder
+        let low_carry = self.low & neighbors;
+        self.low ^= neighbors;
+
+        // middle bit half adder
+        let mid_carry = self.mid & low_carry;
+        self.mid ^= low_carry;
+
+        // high bit saturating add
+        self.high |= mid_carry;
+    }
+}
+
+fn rotate_lanes_up(board: u16x16) -> u16x16 {
+    shuffle!(
+        board,
+        [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0]
+    )
+}
+
+fn rotate_lanes_down(board: u16x16) -> u16x16 {
+    shuffle!(
+        board,
+        [15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+    )
+}
+
+/// Advances the grid one generation into the future.
+pub fn step_once(board: u16x16) -> u16x16 {
+    let mut neighbors = Counts::new();
+
+    // +---+---+---+
+    // | * | * | * |
+    // +---+---+---+
+    // | * |   | * |
+    // +---+---+---+
+    // | * | * | * |
+    // +---+---+---+
+
+    // top row
+    neighbors.add(rotate_lanes_down(board) >> 1);
+    neighbors.add(rotate_lanes_down(board));
+    neighbors.add(rotate_lanes_down(board) << 1);
+
+    // middle row
+    neighbors.add(board >> 1);
+    neighbors.add(board << 1);
+
+    // bottom row
+    neighbors.add(rotate_lanes_up(board) >> 1);
+    neighbors.add(rotate_lanes_up(board));
+    neighbors.add(rotate_lanes_up(board) << 1);
+
+    // 2 is 010 in binary
+    let two_neighbors = !neighbors.high & neighbors.mid & !neighbors.low;
+    // 3 is 011 in binary
+    let three_neighbors = !neighbors.high & neighbors.mid & neighbors.low;
+
+    // if 2 neighbors, the cell doesn't change
+    // if 3 neighbors, the cell is alive
+    (two_neighbors & board) | three_neighbors
+}
+
+/// Combines the center quarters of four grids into one grid.
+pub fn combine_results(
+    nw_grid: u16x16,
+    ne_grid: u16x16,
+    sw_grid: u16x16,
+    se_grid: u16x16,
+) -> u16x16 {
+    let nw_grid = nw_grid << 4;
+    let nw_grid = shuffle!(
+        nw_grid,
+        [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3]
+    ) & LEVEL_4_NW_MASK;
+
+    let ne_grid = ne_grid >> 4;
+    let ne_grid = shuffle!(
+        ne_grid,
+        [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3]
+    ) & LEVEL_4_NE_MASK;
+
+    let sw_grid = sw_grid << 4;
+    let sw_grid = shuffle!(
+        sw_grid,
+        [12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+    ) & LEVEL_4_SW_MASK;
+
+    let se_grid = se_grid >> 4;
+    let se_grid = shuffle!(
+        se_grid,
+        [12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+    ) & LEVEL_4_SE_MASK;
+
+    nw_grid | ne_grid | sw_grid | se_grid
+}
+
+/// Returns the grid halfway between two horizontally adjacent grids.
+pub fn horiz(w: u16x16, e: u16x16) -> u16x16 {
+    (w << 8) | (e >> 8)
+}
+
+/// Returns the grid halfway between two vertically adjacent grids.
+pub fn vert(n: u16x16, s: u16x16) -> u16x16 {
+    let n = shuffle!(n, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
+        & LEVEL_4_UPPER_HALF_MASK;
+    let s = shuffle!(s, [8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3, 4, 5, 6, 7])
+        & LEVEL_4_LOWER_HALF_MASK;
+    n | s
+}
@@ -0,0 +1,162 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! 64 by 64 leaf grids.
+//!
+//! The operations work on whole arrays of rows so that the compiler can autovectorize them. They
+//! are plain scalar code, and don't use `packed_simd` even when the `simd` feature is enabled.
+
+use crate::node::Level;
+
+/// A 64 by 64 grid of cells, stored as 64 rows.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct u64x64(pub [u64; 64]);
+
+impl std::fmt::Debug for u64x64 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+/// A leaf grid.
+pub type Grid = u64x64;
+
+/// The level of a leaf.
+pub const LEAF_LEVEL: Level = Level(6);
+
+const SIZE: usize = 64;
+const HALF: usize = SIZE / 2;
+const QUARTER: usize = SIZE / 4;
+
+const LEFT_HALF_MASK: u64 = 0xffff_ffff_0000_0000;
+const RIGHT_HALF_MASK: u64 = 0x0000_0000_ffff_ffff;
+
+/// Returns an empty grid.
+pub fn empty() -> u64x64 {
+    u64x64([0; SIZE])
+}
+
+/// Returns the row of the grid with the given index.
+pub fn row(grid: u64x64, index: usize) -> u64 {
+    grid.0[index]
+}
+
+/// Returns a copy of the grid with the row with the given index replaced.
+pub fn replace_row(mut grid: u64x64, index: usize, value: u64) -> u64x64 {
+    grid.0[index] = value;
+    grid
+}
+
+/// Returns the number of alive cells in the grid.
+pub fn population(grid: u64x64) -> u128 {
+    u128::from(grid.0.iter().map(|row| row.count_ones()).sum::<u32>())
+}
+
+/// Returns the grid centered on the corner shared by four grids.
+pub fn center(nw_grid: u64x64, ne_grid: u64x64, sw_grid: u64x64, se_grid: u64x64) -> u64x64 {
+    let mut grid = [0; SIZE];
+    for (i, row) in grid.iter_mut().enumerate().take(HALF) {
+        *row = (nw_grid.0[i + HALF] << HALF) | (ne_grid.0[i + HALF] >> HALF);
+    }
+    for (i, row) in grid.iter_mut().enumerate().skip(HALF) {
+        *row = (sw_grid.0[i - HALF] << HALF) | (se_grid.0[i - HALF] >> HALF);
+    }
+    u64x64(grid)
+}
+
+/// Advances the grid one generation into the future.
+pub fn step_once(board: u64x64) -> u64x64 {
+    let board = board.0;
+    let mut grid = [0; SIZE];
+
+    for (i, row) in grid.iter_mut().enumerate() {
+        let above = board[(i + SIZE - 1) % SIZE];
+        let middle = board[i];
+        let below = board[(i + 1) % SIZE];
+
+        let mut low = 0;
+        let mut mid = 0;
+        let mut high = 0;
+
+        // +---+---+---+
+        // | * | * | * |
+        // +---+---+---+
+        // | * |   | * |
+        // +---+---+---+
+        // | * | * | * |
+        // +---+---+---+
+
+        for &neighbors in &[
+            above >> 1,
+            above,
+            above << 1,
+            middle >> 1,
+            middle << 1,
+            below >> 1,
+            below,
+            below << 1,
+        ] {
+            // low bit half adder
+            let low_carry = low & neighbors;
+            low ^= neighbors;
+
+            // middle bit half adder
+            let mid_carry = mid & low_carry;
+            mid ^= low_carry;
+
+            // high bit saturating add
+            high |= mid_carry;
+        }
+
+        // 2 is 010 in binary
+        let two_neighbors = !high & mid & !low;
+        // 3 is 011 in binary
+        let three_neighbors = !high & mid & low;
+
+        // if 2 neighbors, the cell doesn't change
+        // if 3 neighbors, the cell is alive
+        *row = (two_neighbors & middle) | three_neighbors;
+    }
+
+    u64x64(grid)
+}
+
+/// Combines the center quarters of four grids into one grid.
+pub fn combine_results(
+    nw_grid: u64x64,
+    ne_grid: u64x64,
+    sw_grid: u64x64,
+    se_grid: u64x64,
+) -> u64x64 {
+    let mut grid = [0; SIZE];
+    for (i, row) in grid.iter_mut().enumerate().take(HALF) {
+        *row = ((nw_grid.0[i + QUARTER] << QUARTER) & LEFT_HALF_MASK)
+            | ((ne_grid.0[i + QUARTER] >> QUARTER) & RIGHT_HALF_MASK);
+    }
+    for (i, row) in grid.iter_mut().enumerate().skip(HALF) {
+        *row = ((sw_grid.0[i - QUARTER] << QUARTER) & LEFT_HALF_MASK)
+            | ((se_grid.0[i - QUARTER] >> QUARTER) & RIGHT_HALF_MASK);
+    }
+    u64x64(grid)
+}
+
+/// Returns the grid halfway between two horizontally adjacent grids.
+pub fn horiz(w: u64x64, e: u64x64) -> u64x64 {
+    let mut grid = [0; SIZE];
+    for (row, (&w, &e)) in grid.iter_mut().zip(w.0.iter().zip(e.0.iter())) {
+        *row = (w << HALF) | (e >> HALF);
+    }
+    u64x64(grid)
+}
+
+/// Returns the grid halfway between two vertically adjacent grids.
+pub fn vert(n: u64x64, s: u64x64) -> u64x64 {
+    let mut grid = [0; SIZE];
+    grid[..HALF].copy_from_slice(&n.0[HALF..]);
+    grid[HALF..].copy_from_slice(&s.0[..HALF]);
+    u64x64(grid)
+}
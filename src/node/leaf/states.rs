@@ -0,0 +1,190 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Leaves of multi-state grids, and the base case of the HashLife algorithm for them.
+//!
+//! A leaf is a 4 by 4 grid with one byte per cell, small enough that a node holding one is no
+//! larger than a node holding a Life leaf. State 0 is the empty state.
+
+use crate::{node::Level, rules::table::Rule, BoundingBox, Position};
+
+/// A 4 by 4 grid of cell states, row by row.
+pub type StateGrid = [u8; 16];
+
+/// The level of a multi-state leaf.
+pub const STATE_LEAF_LEVEL: Level = Level(2);
+
+/// The minimum coordinate that can be used in a position for a multi-state leaf.
+pub const MIN_COORD: i64 = -2;
+
+/// The maximum coordinate that can be used in a position for a multi-state leaf.
+pub const MAX_COORD: i64 = 1;
+
+/// The side length of a multi-state leaf.
+const SIDE: usize = 4;
+
+/// Returns the index in the grid of the cell at the given position.
+fn index(x: i64, y: i64) -> usize {
+    (y - MIN_COORD) as usize * SIDE + (x - MIN_COORD) as usize
+}
+
+/// Returns an empty grid.
+pub fn empty() -> StateGrid {
+    [0; SIDE * SIDE]
+}
+
+/// Returns the state of the cell at the given position.
+pub fn get_state(grid: StateGrid, x: i64, y: i64) -> u8 {
+    grid[index(x, y)]
+}
+
+/// Returns a copy of the grid with the cell at the given position set to the given state.
+pub fn set_state(mut grid: StateGrid, x: i64, y: i64, state: u8) -> StateGrid {
+    grid[index(x, y)] = state;
+    grid
+}
+
+/// Returns the number of non-empty cells in the grid.
+pub fn population(grid: StateGrid) -> u128 {
+    grid.iter().filter(|&&state| state != 0).count() as u128
+}
+
+/// Returns the smallest bounding box that contains all the non-empty cells in the grid.
+pub fn bounding_box(grid: StateGrid) -> Option<BoundingBox> {
+    positions()
+        .filter(|&(x, y)| get_state(grid, x, y) != 0)
+        .map(|(x, y)| BoundingBox::new(Position::new(x, y), Position::new(x, y)))
+        .fold(None, |combined: Option<BoundingBox>, cell| {
+            Some(combined.map_or(cell, |combined| combined.combine(cell)))
+        })
+}
+
+/// Returns the positions of all cells of a grid, row by row.
+pub fn positions() -> impl Iterator<Item = (i64, i64)> {
+    (MIN_COORD..=MAX_COORD).flat_map(|y| (MIN_COORD..=MAX_COORD).map(move |x| (x, y)))
+}
+
+/// Returns the states of four grids arranged in a square, as an 8 by 8 array indexed by row and
+/// column.
+fn combine(
+    nw: StateGrid,
+    ne: StateGrid,
+    sw: StateGrid,
+    se: StateGrid,
+) -> [[u8; 2 * SIDE]; 2 * SIDE] {
+    let mut cells = [[0; 2 * SIDE]; 2 * SIDE];
+    for &(grid, column, row) in &[(nw, 0, 0), (ne, SIDE, 0), (sw, 0, SIDE), (se, SIDE, SIDE)] {
+        for (index, &state) in grid.iter().enumerate() {
+            cells[row + index / SIDE][column + index % SIDE] = state;
+        }
+    }
+    cells
+}
+
+/// Returns the center of an 8 by 8 array as a grid.
+fn center_of(cells: &[[u8; 2 * SIDE]; 2 * SIDE]) -> StateGrid {
+    let mut grid = empty();
+    for (index, state) in grid.iter_mut().enumerate() {
+        *state = cells[SIDE / 2 + index / SIDE][SIDE / 2 + index % SIDE];
+    }
+    grid
+}
+
+/// Returns the grid in the center of four grids arranged in a square.
+pub fn center(nw: StateGrid, ne: StateGrid, sw: StateGrid, se: StateGrid) -> StateGrid {
+    center_of(&combine(nw, ne, sw, se))
+}
+
+/// Advances four grids arranged in a square `2^step_log_2` generations into the future, returning
+/// the grid in the center.
+///
+/// # Panics
+///
+/// Panics if `step_log_2` is greater than 1, since the center can only be advanced two
+/// generations before the edges interfere.
+pub fn step(
+    rule: &Rule,
+    nw: StateGrid,
+    ne: StateGrid,
+    sw: StateGrid,
+    se: StateGrid,
+    step_log_2: u8,
+) -> StateGrid {
+    assert!(step_log_2 <= 1);
+    let mut cells = combine(nw, ne, sw, se);
+    let mut neighbors = [0; 8];
+    let size = rule.neighborhood().size();
+
+    // each generation the valid region shrinks by one cell on every side
+    for generation in 1..=1 << step_log_2 {
+        let mut next = cells;
+        for y in generation..2 * SIDE - generation {
+            for x in generation..2 * SIDE - generation {
+                let (n, s, w, e) = (y - 1, y + 1, x - 1, x + 1);
+                if size == 8 {
+                    neighbors = [
+                        cells[n][x],
+                        cells[n][e],
+                        cells[y][e],
+                        cells[s][e],
+                        cells[s][x],
+                        cells[s][w],
+                        cells[y][w],
+                        cells[n][w],
+                    ];
+                } else {
+                    neighbors[..4].copy_from_slice(&[
+                        cells[n][x],
+                        cells[y][e],
+                        cells[s][x],
+                        cells[y][w],
+                    ]);
+                }
+                next[y][x] = rule.transition(cells[y][x], &neighbors[..size]);
+            }
+        }
+        cells = next;
+    }
+
+    center_of(&cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_set() {
+        let grid = set_state(empty(), MIN_COORD, MAX_COORD, 3);
+        let grid = set_state(grid, 0, -1, 1);
+        assert_eq!(get_state(grid, MIN_COORD, MAX_COORD), 3);
+        assert_eq!(get_state(grid, 0, -1), 1);
+        assert_eq!(get_state(grid, 0, 0), 0);
+        assert_eq!(population(grid), 2);
+        assert_eq!(
+            bounding_box(grid),
+            Some(BoundingBox::new(
+                Position::new(MIN_COORD, -1),
+                Position::new(0, MAX_COORD)
+            ))
+        );
+        assert_eq!(bounding_box(empty()), None);
+    }
+
+    #[test]
+    fn center_of_four() {
+        let nw = set_state(empty(), MAX_COORD, MAX_COORD, 1);
+        let ne = set_state(empty(), MIN_COORD, MAX_COORD, 2);
+        let sw = set_state(empty(), MAX_COORD, MIN_COORD, 3);
+        let se = set_state(empty(), MIN_COORD, MIN_COORD, 4);
+        let center = center(nw, ne, sw, se);
+        assert_eq!(get_state(center, -1, -1), 1);
+        assert_eq!(get_state(center, 0, -1), 2);
+        assert_eq!(get_state(center, -1, 0), 3);
+        assert_eq!(get_state(center, 0, 0), 4);
+        assert_eq!(population(center), 4);
+    }
+}
@@ -0,0 +1,235 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! The vector type used to store leaf grids.
+//!
+//! With the `simd` feature enabled this is `packed_simd::u16x16`, which requires a nightly
+//! compiler. Otherwise a portable scalar implementation with the same interface is used.
+
+#[cfg(feature = "simd")]
+pub use packed_simd::u16x16;
+
+#[cfg(not(feature = "simd"))]
+pub use self::scalar::u16x16;
+
+/// Shuffles the lanes of one or two vectors.
+///
+/// Mirrors the `shuffle!` macro from `packed_simd`: index `i` refers to lane `i` of the first
+/// vector, and index `16 + i` refers to lane `i` of the second vector.
+#[cfg(not(feature = "simd"))]
+macro_rules! shuffle {
+    ($vec:expr, [$($index:expr),* $(,)*]) => {
+        $crate::node::simd::u16x16::shuffle1($vec, [$($index),*])
+    };
+    ($vec0:expr, $vec1:expr, [$($index:expr),* $(,)*]) => {
+        $crate::node::simd::u16x16::shuffle2($vec0, $vec1, [$($index),*])
+    };
+}
+
+#[cfg(not(feature = "simd"))]
+mod scalar {
+    use std::ops::{BitAnd, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+    /// A vector of sixteen `u16` lanes.
+    #[allow(non_camel_case_types)]
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+    pub struct u16x16([u16; 16]);
+
+    impl u16x16 {
+        /// Creates a new vector from the given lanes.
+        #[allow(clippy::too_many_arguments)]
+        pub const fn new(
+            x0: u16,
+            x1: u16,
+            x2: u16,
+            x3: u16,
+            x4: u16,
+            x5: u16,
+            x6: u16,
+            x7: u16,
+            x8: u16,
+            x9: u16,
+            x10: u16,
+            x11: u16,
+            x12: u16,
+            x13: u16,
+            x14: u16,
+            x15: u16,
+        ) -> Self {
+            u16x16([
+                x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11, x12, x13, x14, x15,
+            ])
+        }
+
+        /// Creates a new vector with every lane set to `value`.
+        pub const fn splat(value: u16) -> Self {
+            u16x16([value; 16])
+        }
+
+        /// Returns the value of the given lane.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `index >= 16`.
+        pub fn extract(self, index: usize) -> u16 {
+            self.0[index]
+        }
+
+        /// Returns a new vector with the given lane replaced by `value`.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `index >= 16`.
+        pub fn replace(mut self, index: usize, value: u16) -> Self {
+            self.0[index] = value;
+            self
+        }
+
+        /// Returns the number of ones in each lane.
+        pub fn count_ones(self) -> Self {
+            self.map(|x| x.count_ones() as u16)
+        }
+
+        /// Returns the wrapping sum of all the lanes.
+        pub fn wrapping_sum(self) -> u16 {
+            self.0.iter().fold(0, |sum, &x| sum.wrapping_add(x))
+        }
+
+        /// Selects lanes from the vector by index.
+        pub fn shuffle1(self, indices: [usize; 16]) -> Self {
+            let mut lanes = [0; 16];
+            for (lane, &index) in lanes.iter_mut().zip(indices.iter()) {
+                *lane = self.0[index];
+            }
+            u16x16(lanes)
+        }
+
+        /// Selects lanes from two concatenated vectors by index.
+        pub fn shuffle2(self, other: Self, indices: [usize; 16]) -> Self {
+            let mut lanes = [0; 16];
+            for (lane, &index) in lanes.iter_mut().zip(indices.iter()) {
+                *lane = if index < 16 {
+                    self.0[index]
+                } else {
+                    other.0[index - 16]
+                };
+            }
+            u16x16(lanes)
+        }
+
+        fn map<F>(self, f: F) -> Self
+        where
+            F: Fn(u16) -> u16,
+        {
+            let mut lanes = self.0;
+            for lane in lanes.iter_mut() {
+                *lane = f(*lane);
+            }
+            u16x16(lanes)
+        }
+
+        fn zip<F>(self, other: Self, f: F) -> Self
+        where
+            F: Fn(u16, u16) -> u16,
+        {
+            let mut lanes = self.0;
+            for (lane, &x) in lanes.iter_mut().zip(other.0.iter()) {
+                *lane = f(*lane, x);
+            }
+            u16x16(lanes)
+        }
+    }
+
+    impl BitAnd for u16x16 {
+        type Output = Self;
+
+        fn bitand(self, other: Self) -> Self {
+            self.zip(other, |x, y| x & y)
+        }
+    }
+
+    impl BitOr for u16x16 {
+        type Output = Self;
+
+        fn bitor(self, other: Self) -> Self {
+            self.zip(other, |x, y| x | y)
+        }
+    }
+
+    impl BitXor for u16x16 {
+        type Output = Self;
+
+        fn bitxor(self, other: Self) -> Self {
+            self.zip(other, |x, y| x ^ y)
+        }
+    }
+
+    impl BitOrAssign for u16x16 {
+        fn bitor_assign(&mut self, other: Self) {
+            *self = *self | other;
+        }
+    }
+
+    impl BitXorAssign for u16x16 {
+        fn bitxor_assign(&mut self, other: Self) {
+            *self = *self ^ other;
+        }
+    }
+
+    impl Not for u16x16 {
+        type Output = Self;
+
+        fn not(self) -> Self {
+            self.map(|x| !x)
+        }
+    }
+
+    impl Shl<u32> for u16x16 {
+        type Output = Self;
+
+        fn shl(self, amount: u32) -> Self {
+            self.map(|x| x.wrapping_shl(amount))
+        }
+    }
+
+    impl Shr<u32> for u16x16 {
+        type Output = Self;
+
+        fn shr(self, amount: u32) -> Self {
+            self.map(|x| x.wrapping_shr(amount))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn shuffle() {
+            let a = u16x16::new(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+            let b = a << 8;
+
+            let rotated = shuffle!(a, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0]);
+            assert_eq!(rotated.extract(0), 1);
+            assert_eq!(rotated.extract(15), 0);
+
+            let joined = shuffle!(
+                a,
+                b,
+                [8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
+            );
+            assert_eq!(joined.extract(0), 8);
+            assert_eq!(joined.extract(8), 0);
+            assert_eq!(joined.extract(9), 1 << 8);
+        }
+
+        #[test]
+        fn count_ones() {
+            let grid = u16x16::splat(0b1010_0000_0000_0001);
+            assert_eq!(grid.count_ones().wrapping_sum(), 48);
+        }
+    }
+}
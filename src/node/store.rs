@@ -4,8 +4,23 @@
  * obtain one at http://mozilla.org/MPL/2.0/.
  */
 
-use crate::node::{Index, Level, Node, NodeId};
-use packed_simd::u16x16;
+mod persist;
+
+pub use self::persist::StateError;
+pub(crate) use self::persist::{Decoder, Encoder};
+use crate::{
+    node::{
+        hash,
+        leaf::{self, states},
+        table::NodeTable,
+        Grid, Index, Level, Node, NodeId, RawIndex, StateGrid, LEAF_LEVEL, STATE_LEAF_LEVEL,
+    },
+    rules::table::Rule,
+    BoundingBox,
+};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{collections::BTreeMap, convert::TryFrom, sync::Arc};
 
 /// A template to create a node from four child nodes.
 pub struct NodeTemplate {
@@ -19,15 +34,115 @@ pub struct NodeTemplate {
     pub se: NodeId,
 }
 
+/// Statistics about the contents and use of a store.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StoreStats {
+    /// The number of nodes with each level.
+    pub nodes_per_level: BTreeMap<Level, usize>,
+    /// The number of leaf nodes.
+    pub leaves: usize,
+    /// The number of interior nodes.
+    pub interiors: usize,
+    /// The number of nodes the hash table of nodes can hold without reallocating.
+    pub capacity: usize,
+    /// The number of previously calculated steps found while evolving nodes.
+    pub step_hits: u64,
+    /// The number of steps that had to be calculated while evolving nodes.
+    pub step_misses: u64,
+    /// The number of previously calculated jumps found while evolving nodes.
+    pub jump_hits: u64,
+    /// The number of jumps that had to be calculated while evolving nodes.
+    pub jump_misses: u64,
+    /// The number of bytes allocated by the store.
+    pub memory_usage: usize,
+}
+
+impl StoreStats {
+    /// Returns the total number of nodes.
+    pub fn nodes(&self) -> usize {
+        self.leaves + self.interiors
+    }
+
+    /// Returns the fraction of the capacity of the hash table of nodes that is in use.
+    pub fn load_factor(&self) -> f64 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            self.nodes() as f64 / self.capacity as f64
+        }
+    }
+
+    /// Returns the fraction of steps needed while evolving nodes that were found, if any.
+    pub fn step_hit_rate(&self) -> Option<f64> {
+        hit_rate(self.step_hits, self.step_misses)
+    }
+
+    /// Returns the fraction of jumps needed while evolving nodes that were found, if any.
+    pub fn jump_hit_rate(&self) -> Option<f64> {
+        hit_rate(self.jump_hits, self.jump_misses)
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> Option<f64> {
+    if hits + misses == 0 {
+        None
+    } else {
+        Some(hits as f64 / (hits + misses) as f64)
+    }
+}
+
+/// Counters of memoized evolution results found and not found.
+#[derive(Clone, Copy, Debug, Default)]
+struct Counters {
+    step_hits: u64,
+    step_misses: u64,
+    jump_hits: u64,
+    jump_misses: u64,
+}
+
+impl std::ops::AddAssign for Counters {
+    fn add_assign(&mut self, other: Counters) {
+        self.step_hits += other.step_hits;
+        self.step_misses += other.step_misses;
+        self.jump_hits += other.jump_hits;
+        self.jump_misses += other.jump_misses;
+    }
+}
+
 /// A struct to store nodes and node evolution results.
+///
+/// While nodes are evolved in parallel, each worker thread gets its own store layered on top of a
+/// shared read-only base store. Nodes in the base store keep their IDs, and nodes created by the
+/// worker get IDs following the last node of the base store. The layers are merged back into the
+/// base store afterwards.
 #[derive(Clone, Debug)]
 pub struct Store {
-    indices: hashbrown::HashMap<Node, NodeId>,
+    table: NodeTable,
     nodes: Vec<Node>,
+    bounding_boxes: Vec<Option<BoundingBox>>,
+    content_hashes: Vec<u64>,
     steps: Vec<Option<NodeId>>,
     jumps: Vec<Option<NodeId>>,
     empties: Vec<NodeId>,
     step_log_2: u8,
+    /// The store this store is layered on top of, if any.
+    base: Option<Arc<Store>>,
+    /// The number of nodes in the base store, which is the index of the first node in this store.
+    offset: RawIndex,
+    /// Steps of nodes in the base store calculated in this store.
+    base_steps: hashbrown::HashMap<Index, NodeId>,
+    /// Jumps of nodes in the base store calculated in this store.
+    base_jumps: hashbrown::HashMap<Index, NodeId>,
+    /// The maximum number of bytes allocated by the store, if any.
+    memory_limit: Option<usize>,
+    /// For each node, the value of the clock when its evolution results were last used.
+    last_used: Vec<u64>,
+    /// A counter incremented every time evolution results are used.
+    clock: u64,
+    /// Counters of memoized evolution results found and not found.
+    counters: Counters,
+    /// The rule of a store of multi-state nodes, or `None` for a store of Life nodes.
+    rule: Option<Arc<Rule>>,
 }
 
 impl Store {
@@ -40,26 +155,124 @@ impl Store {
     /// ```
     pub fn new() -> Self {
         Self {
-            indices: hashbrown::HashMap::default(),
+            table: NodeTable::new(),
             nodes: vec![],
+            bounding_boxes: vec![],
+            content_hashes: vec![],
             steps: vec![],
             jumps: vec![],
             empties: vec![],
             step_log_2: 0,
+            base: None,
+            offset: 0,
+            base_steps: hashbrown::HashMap::default(),
+            base_jumps: hashbrown::HashMap::default(),
+            memory_limit: None,
+            last_used: vec![],
+            clock: 0,
+            counters: Counters::default(),
+            rule: None,
+        }
+    }
+
+    /// Creates a new empty store of multi-state nodes evolving according to the given rule.
+    ///
+    /// Leaves in the store are `Node::StateLeaf`s, and the methods of `NodeId` that deal with
+    /// alive cells treat non-empty cells as alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let rule = smeagol::rules::table::Rule::from_file("./assets/wireworld.rule")?;
+    /// let mut store = smeagol::node::Store::with_rule(rule);
+    /// let empty = store.create_empty(smeagol::node::Level(4));
+    /// let node = empty.set_state(&mut store, smeagol::Position::new(0, 0), 3);
+    /// assert_eq!(node.get_state(&store, smeagol::Position::new(0, 0)), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_rule(rule: Rule) -> Self {
+        Self {
+            rule: Some(Arc::new(rule)),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the rule of a store of multi-state nodes, or `None` for a store of Life nodes.
+    pub fn rule(&self) -> Option<&Rule> {
+        self.rule.as_deref()
+    }
+
+    /// Returns the level of the leaves in the store.
+    pub fn leaf_level(&self) -> Level {
+        if self.rule.is_some() {
+            STATE_LEAF_LEVEL
+        } else {
+            LEAF_LEVEL
+        }
+    }
+
+    /// Returns the level above the leaf level, where the base case of the HashLife algorithm is
+    /// computed.
+    pub(crate) fn base_level(&self) -> Level {
+        if self.rule.is_some() {
+            Level(STATE_LEAF_LEVEL.0 + 1)
+        } else {
+            leaf::BASE_LEVEL
+        }
+    }
+
+    /// Creates a new empty store layered on top of the given base store.
+    #[cfg(feature = "parallel")]
+    fn layered(base: &Arc<Store>) -> Self {
+        Self {
+            empties: base.empties.clone(),
+            step_log_2: base.step_log_2,
+            offset: base.next_index().0,
+            base: Some(Arc::clone(base)),
+            rule: base.rule.clone(),
+            ..Self::new()
         }
     }
 
+    /// Returns the index of the given node in this store, or `None` if it is in the base store.
+    fn local_index(&self, id: NodeId) -> Option<usize> {
+        id.index
+            .0
+            .checked_sub(self.offset)
+            .map(|index| index as usize)
+    }
+
+    /// Returns the base store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store is not layered on top of another store.
+    fn base(&self) -> &Store {
+        self.base.as_ref().unwrap()
+    }
+
     /// Returns the node corresponding to the given node ID.
     pub fn node(&self, id: NodeId) -> Node {
-        self.nodes[id.index.0 as usize]
+        match self.local_index(id) {
+            Some(index) => self.nodes[index],
+            None => self.base().node(id),
+        }
     }
 
-    /// Creates a leaf node corresponding to the given 16 by 16 grid.
-    pub fn create_leaf(&mut self, grid: u16x16) -> NodeId {
+    /// Creates a leaf node corresponding to the given grid.
+    pub fn create_leaf(&mut self, grid: Grid) -> NodeId {
         let node = Node::Leaf { grid };
         self.add_node(node)
     }
 
+    /// Creates a multi-state leaf node corresponding to the given grid.
+    pub fn create_state_leaf(&mut self, grid: StateGrid) -> NodeId {
+        let node = Node::StateLeaf { grid };
+        self.add_node(node)
+    }
+
     /// Creates an interior node from the given node template.
     pub fn create_interior(&mut self, template: NodeTemplate) -> NodeId {
         let level = template.nw.level(self);
@@ -83,37 +296,158 @@ impl Store {
     }
 
     /// Creates an empty node with the given level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the level is below the level of a leaf.
     pub fn create_empty(&mut self, level: Level) -> NodeId {
-        if level.0 < self.empties.len() as u8 {
-            return self.empties[level.0 as usize];
+        let leaf_level = self.leaf_level();
+        assert!(level >= leaf_level);
+        // empties[i] is the empty node with the level of a leaf plus i
+        let index = (level.0 - leaf_level.0) as usize;
+        while self.empties.len() <= index {
+            let empty = match self.empties.last() {
+                Some(&empty) => self.create_interior(NodeTemplate {
+                    nw: empty,
+                    ne: empty,
+                    sw: empty,
+                    se: empty,
+                }),
+                None if self.rule.is_some() => self.create_state_leaf(states::empty()),
+                None => self.create_leaf(leaf::empty()),
+            };
+            self.empties.push(empty);
         }
-        let empty = if level == Level(4) {
-            self.create_leaf(u16x16::splat(0))
-        } else {
-            let empty = self.create_empty(Level(level.0 - 1));
-            self.create_interior(NodeTemplate {
-                nw: empty,
-                ne: empty,
-                sw: empty,
-                se: empty,
+        self.empties[index]
+    }
+
+    /// Returns the ID of the given node if it is in the store or one of its base stores.
+    fn find(&self, node: &Node) -> Option<NodeId> {
+        match self.table.find(&self.nodes, node) {
+            Some(position) => Some(NodeId {
+                index: Index(self.offset + position as RawIndex),
+            }),
+            None => self.base.as_ref().and_then(|base| base.find(node)),
+        }
+    }
+
+    /// Returns a bounding box that contains all the alive cells in the given node.
+    pub(crate) fn bounding_box(&self, id: NodeId) -> Option<BoundingBox> {
+        match self.local_index(id) {
+            Some(index) => self.bounding_boxes[index],
+            None => self.base().bounding_box(id),
+        }
+    }
+
+    /// Calculates the bounding box of a node that is about to be added to the store from the
+    /// bounding boxes of its children.
+    fn calculate_bounding_box(&self, node: Node) -> Option<BoundingBox> {
+        match node {
+            Node::Leaf { grid } => leaf::bounding_box(grid),
+            Node::StateLeaf { grid } => states::bounding_box(grid),
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                population,
+            } => {
+                if population == 0 {
+                    return None;
+                }
+
+                // quarter side length
+                let offset = 1 << (level.0 - 2);
+
+                [
+                    (nw, -offset, -offset),
+                    (ne, offset, -offset),
+                    (sw, -offset, offset),
+                    (se, offset, offset),
+                ]
+                .iter()
+                .filter_map(|&(child, x_offset, y_offset)| {
+                    self.bounding_box(child)
+                        .map(|bounding_box| bounding_box.offset(x_offset, y_offset))
+                })
+                .fold(None, |combined: Option<BoundingBox>, bounding_box| {
+                    Some(combined.map_or(bounding_box, |combined| combined.combine(bounding_box)))
+                })
+            }
+        }
+    }
+
+    /// Returns the content hash of the given node.
+    pub(crate) fn content_hash(&self, id: NodeId) -> u64 {
+        match self.local_index(id) {
+            Some(index) => self.content_hashes[index],
+            None => self.base().content_hash(id),
+        }
+    }
+
+    /// Calculates the content hash of a node that is about to be added to the store from the
+    /// content hashes of its children.
+    fn calculate_content_hash(&self, node: Node) -> u64 {
+        match node {
+            Node::Leaf { grid } => hash::leaf_hash(grid),
+            Node::StateLeaf { grid } => hash::state_leaf_hash(grid),
+            Node::Interior {
+                nw,
+                ne,
+                sw,
+                se,
+                level,
+                ..
+            } => hash::combine(
+                level,
+                [
+                    self.content_hash(nw),
+                    self.content_hash(ne),
+                    self.content_hash(sw),
+                    self.content_hash(se),
+                ],
+            ),
+        }
+    }
+
+    /// Returns the index of the next node added to the store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store already contains `Store::MAX_NODES` nodes.
+    fn next_index(&self) -> Index {
+        RawIndex::try_from(self.nodes.len())
+            .ok()
+            .and_then(|len| self.offset.checked_add(len))
+            .filter(|&index| index < RawIndex::MAX)
+            .map(Index)
+            .unwrap_or_else(|| {
+                panic!(
+                    "store is full: node indices are limited to {} bits. Evict unused nodes \
+                     between steps or enable the `index64` feature",
+                    RawIndex::BITS
+                )
             })
-        };
-        self.empties.push(empty);
-        empty
     }
 
     /// Adds a node to the store, returning a node ID.
     fn add_node(&mut self, node: Node) -> NodeId {
-        if let Some(id) = self.indices.get(&node) {
-            *id
+        if let Some(id) = self.find(&node) {
+            id
         } else {
             let id = NodeId {
-                index: Index(self.nodes.len() as u32),
+                index: self.next_index(),
             };
-            self.indices.insert(node, id);
+            let bounding_box = self.calculate_bounding_box(node);
+            let content_hash = self.calculate_content_hash(node);
             self.nodes.push(node);
+            self.table.insert(&self.nodes, self.nodes.len() - 1);
+            self.bounding_boxes.push(bounding_box);
+            self.content_hashes.push(content_hash);
             self.steps.push(None);
             self.jumps.push(None);
+            self.last_used.push(0);
             id
         }
     }
@@ -131,28 +465,458 @@ impl Store {
     pub fn set_step_log_2(&mut self, step_log_2: u8) {
         if step_log_2 != self.step_log_2 {
             self.step_log_2 = step_log_2;
-            self.steps = vec![None; self.steps.len()]
+            self.steps = vec![None; self.steps.len()];
+            self.base_steps.clear();
         }
     }
 
     /// Gets the step of the given node, if it has been previously calculated.
     pub fn get_step(&self, id: NodeId) -> Option<NodeId> {
-        self.steps[id.index.0 as usize]
+        match self.local_index(id) {
+            Some(index) => self.steps[index],
+            None => match self.base_steps.get(&id.index) {
+                Some(&step) => Some(step),
+                None => self.base().get_step(id),
+            },
+        }
+    }
+
+    /// Gets the step of the given node while evolving it, marking its evolution results as
+    /// recently used and counting whether the step was found.
+    pub(crate) fn find_step(&mut self, id: NodeId) -> Option<NodeId> {
+        self.touch(id);
+        let step = self.get_step(id);
+        if step.is_some() {
+            self.counters.step_hits += 1;
+        } else {
+            self.counters.step_misses += 1;
+        }
+        step
     }
 
     /// Sets the step of the given node.
     pub fn add_step(&mut self, id: NodeId, step: NodeId) {
-        self.steps[id.index.0 as usize] = Some(step);
+        self.touch(id);
+        match self.local_index(id) {
+            Some(index) => self.steps[index] = Some(step),
+            None => {
+                self.base_steps.insert(id.index, step);
+            }
+        }
     }
 
     /// Gets the jump of the given node, if it has been previously calculated.
     pub fn get_jump(&self, id: NodeId) -> Option<NodeId> {
-        self.jumps[id.index.0 as usize]
+        match self.local_index(id) {
+            Some(index) => self.jumps[index],
+            None => match self.base_jumps.get(&id.index) {
+                Some(&jump) => Some(jump),
+                None => self.base().get_jump(id),
+            },
+        }
+    }
+
+    /// Gets the jump of the given node while evolving it, marking its evolution results as
+    /// recently used and counting whether the jump was found.
+    pub(crate) fn find_jump(&mut self, id: NodeId) -> Option<NodeId> {
+        self.touch(id);
+        let jump = self.get_jump(id);
+        if jump.is_some() {
+            self.counters.jump_hits += 1;
+        } else {
+            self.counters.jump_misses += 1;
+        }
+        jump
     }
 
     /// Sets the jump of the given node.
     pub fn add_jump(&mut self, id: NodeId, jump: NodeId) {
-        self.jumps[id.index.0 as usize] = Some(jump);
+        self.touch(id);
+        match self.local_index(id) {
+            Some(index) => self.jumps[index] = Some(jump),
+            None => {
+                self.base_jumps.insert(id.index, jump);
+            }
+        }
+    }
+
+    /// Marks the evolution results of the given node as recently used.
+    fn touch(&mut self, id: NodeId) {
+        self.clock += 1;
+        if let Some(index) = self.local_index(id) {
+            self.last_used[index] = self.clock;
+        }
+    }
+}
+
+impl Store {
+    /// The estimated number of bytes used by each node in the store, including its evolution
+    /// results.
+    ///
+    /// To limit the number of nodes instead of the number of bytes, multiply the maximum number of
+    /// nodes by this.
+    ///
+    /// The hash table of nodes is counted as two slots per node, since it is between a third and
+    /// seven tenths full.
+    ///
+    /// Since the tables of a store grow by doubling, `memory_usage` can be up to about twice this
+    /// times the number of nodes.
+    pub const BYTES_PER_NODE: usize = std::mem::size_of::<Node>()
+        + 2 * std::mem::size_of::<RawIndex>()
+        + std::mem::size_of::<Option<BoundingBox>>()
+        + 2 * std::mem::size_of::<Option<NodeId>>()
+        + 2 * std::mem::size_of::<u64>();
+
+    /// The maximum number of nodes in a store, which depends on the width of node indices.
+    ///
+    /// Adding more nodes panics rather than reusing indices. `evict` removes unused nodes once half
+    /// of them are taken.
+    pub const MAX_NODES: usize = RawIndex::MAX as usize;
+
+    /// Returns the number of nodes in the store.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the store contains no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the number of bytes allocated by the store.
+    ///
+    /// This counts the store itself, the nodes with their cached bounding boxes and content
+    /// hashes, the hash table of nodes, and the tables of evolution results, including unused
+    /// capacity. A store layered on top of another store doesn't count the base store.
+    pub fn memory_usage(&self) -> usize {
+        fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+            vec.capacity() * std::mem::size_of::<T>()
+        }
+        // hashbrown uses one control byte per bucket
+        fn map_bytes(map: &hashbrown::HashMap<Index, NodeId>) -> usize {
+            map.capacity() * (std::mem::size_of::<(Index, NodeId)>() + 1)
+        }
+
+        std::mem::size_of::<Self>()
+            + self.table.memory_usage()
+            + vec_bytes(&self.nodes)
+            + vec_bytes(&self.bounding_boxes)
+            + vec_bytes(&self.content_hashes)
+            + vec_bytes(&self.steps)
+            + vec_bytes(&self.jumps)
+            + vec_bytes(&self.empties)
+            + vec_bytes(&self.last_used)
+            + map_bytes(&self.base_steps)
+            + map_bytes(&self.base_jumps)
+    }
+
+    /// Returns statistics about the contents and use of the store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smeagol::node::{Level, Store};
+    ///
+    /// let mut store = Store::new();
+    /// let empty = store.create_empty(Level(7));
+    /// let node = empty.set_cell_alive(&mut store, smeagol::Position::new(0, 0));
+    /// node.jump(&mut store);
+    ///
+    /// let stats = store.stats();
+    /// assert_eq!(stats.nodes(), store.len());
+    /// assert_eq!(stats.nodes_per_level[&Level(7)], 2);
+    /// assert!(stats.jump_misses > 0);
+    /// ```
+    pub fn stats(&self) -> StoreStats {
+        let mut stats = StoreStats {
+            capacity: self.table.capacity(),
+            step_hits: self.counters.step_hits,
+            step_misses: self.counters.step_misses,
+            jump_hits: self.counters.jump_hits,
+            jump_misses: self.counters.jump_misses,
+            memory_usage: self.memory_usage(),
+            ..StoreStats::default()
+        };
+        for node in &self.nodes {
+            let level = match *node {
+                Node::Leaf { .. } => {
+                    stats.leaves += 1;
+                    LEAF_LEVEL
+                }
+                Node::StateLeaf { .. } => {
+                    stats.leaves += 1;
+                    STATE_LEAF_LEVEL
+                }
+                Node::Interior { level, .. } => {
+                    stats.interiors += 1;
+                    level
+                }
+            };
+            *stats.nodes_per_level.entry(level).or_insert(0) += 1;
+        }
+        stats
+    }
+
+    /// Resets the counters of evolution results found and not found.
+    pub fn reset_stats(&mut self) {
+        self.counters = Counters::default();
+    }
+
+    /// Returns the memory limit of the store in bytes, if any.
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    /// Sets the maximum number of bytes the store should allocate, or removes the limit.
+    ///
+    /// The limit is enforced by `evict`, which should be called regularly between steps. While a
+    /// node is being evolved the store can temporarily grow beyond the limit.
+    pub fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.memory_limit = memory_limit;
+    }
+
+    /// Brings the store back under its memory limit and below half of `Store::MAX_NODES` nodes,
+    /// returning the new IDs of the given root nodes.
+    ///
+    /// If the store is over either limit, nodes that can't be reached from the roots or from the
+    /// evolution results of reachable nodes are removed first. If that isn't enough, the least
+    /// recently used evolution results are dropped, half at a time, until the store is under its
+    /// limits or only the roots are left. Dropped results are recalculated when needed again.
+    ///
+    /// Calling this between steps keeps node indices free for the next step, so the store only
+    /// fills up if the nodes reachable from the roots, or created by a single step, don't fit.
+    ///
+    /// All node IDs other than the returned ones are invalidated if any nodes are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store is layered on top of another store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smeagol::{node::{Level, Store}, Position};
+    ///
+    /// let mut store = Store::new();
+    /// let empty = store.create_empty(Level(7));
+    /// let mut root = empty;
+    /// for x in 0..10 {
+    ///     root = root.set_cell_alive(&mut store, Position::new(x, 0));
+    /// }
+    ///
+    /// store.set_memory_limit(Some(0));
+    /// let roots = store.evict(&[root]);
+    /// assert_eq!(roots[0].population(&store), 10);
+    /// ```
+    pub fn evict(&mut self, roots: &[NodeId]) -> Vec<NodeId> {
+        assert!(self.base.is_none());
+        let over_limit = |store: &Store| {
+            store.len() > Self::MAX_NODES / 2
+                || store
+                    .memory_limit
+                    .is_some_and(|limit| store.memory_usage() > limit)
+        };
+        if !over_limit(self) {
+            return roots.to_vec();
+        }
+
+        let mut roots = roots.to_vec();
+        let mut cutoff = 0;
+        loop {
+            roots = self.compact(&roots, cutoff);
+            if !over_limit(self) {
+                break;
+            }
+
+            let mut times = (0..self.nodes.len())
+                .filter(|&index| self.steps[index].is_some() || self.jumps[index].is_some())
+                .map(|index| self.last_used[index])
+                .collect::<Vec<_>>();
+            if times.is_empty() {
+                break;
+            }
+            // drop the least recently used half of the evolution results, and at least one
+            times.sort_unstable();
+            cutoff = times[(times.len() - 1) / 2] + 1;
+        }
+        roots
+    }
+
+    /// Removes all nodes that can't be reached from the roots, the empty nodes, or the evolution
+    /// results of reachable nodes. Evolution results last used before the cutoff are dropped.
+    ///
+    /// Returns the new IDs of the roots.
+    fn compact(&mut self, roots: &[NodeId], cutoff: u64) -> Vec<NodeId> {
+        let keep_results = |store: &Store, index: usize| store.last_used[index] >= cutoff;
+
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = roots
+            .iter()
+            .chain(&self.empties)
+            .map(|id| id.index.0 as usize)
+            .collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            if reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            if let Node::Interior { nw, ne, sw, se, .. } = self.nodes[index] {
+                stack.extend([nw, ne, sw, se].iter().map(|id| id.index.0 as usize));
+            }
+            if keep_results(self, index) {
+                stack.extend(
+                    self.steps[index]
+                        .iter()
+                        .chain(&self.jumps[index])
+                        .map(|id| id.index.0 as usize),
+                );
+            }
+        }
+
+        // children are always created before their parents, so their new IDs are known first
+        let mut new_ids = vec![None; self.nodes.len()];
+        let mut store = Store {
+            memory_limit: self.memory_limit,
+            step_log_2: self.step_log_2,
+            clock: self.clock,
+            counters: self.counters,
+            rule: self.rule.clone(),
+            ..Store::new()
+        };
+        let remap = |new_ids: &[Option<NodeId>], id: NodeId| new_ids[id.index.0 as usize].unwrap();
+        for (index, &node) in self.nodes.iter().enumerate() {
+            if !reachable[index] {
+                continue;
+            }
+            let node = match node {
+                Node::Leaf { .. } | Node::StateLeaf { .. } => node,
+                Node::Interior {
+                    nw,
+                    ne,
+                    sw,
+                    se,
+                    level,
+                    population,
+                } => Node::Interior {
+                    nw: remap(&new_ids, nw),
+                    ne: remap(&new_ids, ne),
+                    sw: remap(&new_ids, sw),
+                    se: remap(&new_ids, se),
+                    level,
+                    population,
+                },
+            };
+            let id = store.add_node(node);
+            store.last_used[id.index.0 as usize] = self.last_used[index];
+            new_ids[index] = Some(id);
+        }
+
+        // results can point to nodes created later, so they are copied once every node is added
+        for (index, &new_id) in new_ids.iter().enumerate() {
+            if let Some(new_id) = new_id {
+                if keep_results(self, index) {
+                    let new_index = new_id.index.0 as usize;
+                    store.steps[new_index] = self.steps[index].map(|id| remap(&new_ids, id));
+                    store.jumps[new_index] = self.jumps[index].map(|id| remap(&new_ids, id));
+                }
+            }
+        }
+        store.empties = self.empties.iter().map(|&id| remap(&new_ids, id)).collect();
+        store.shrink_to_fit();
+
+        *self = store;
+        roots.iter().map(|&id| remap(&new_ids, id)).collect()
+    }
+
+    /// Frees the unused capacity of the lists of nodes and evolution results.
+    fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        self.bounding_boxes.shrink_to_fit();
+        self.content_hashes.shrink_to_fit();
+        self.steps.shrink_to_fit();
+        self.jumps.shrink_to_fit();
+        self.last_used.shrink_to_fit();
+    }
+
+    /// Applies the given function to each node on a separate worker thread, returning the results
+    /// in order.
+    ///
+    /// Each worker gets its own store layered on top of this one. Once all workers are finished,
+    /// the layers are merged back into this store in order, so the resulting node IDs do not depend
+    /// on how the work was scheduled.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn par_map<F>(&mut self, ids: &[NodeId], f: F) -> Vec<NodeId>
+    where
+        F: Fn(&mut Store, NodeId) -> NodeId + Sync,
+    {
+        let base = Arc::new(std::mem::take(self));
+        let layers = ids
+            .par_iter()
+            .map(|&id| {
+                let mut layer = Store::layered(&base);
+                let result = f(&mut layer, id);
+                layer.base = None;
+                (layer, result)
+            })
+            .collect::<Vec<_>>();
+
+        *self = Arc::try_unwrap(base).expect("all layers have been detached from the base store");
+        layers
+            .into_iter()
+            .map(|(layer, result)| self.merge(layer, result))
+            .collect()
+    }
+
+    /// Adds the nodes and evolution results of a layer on top of this store, returning the ID of
+    /// the given node of the layer in this store.
+    #[cfg(feature = "parallel")]
+    fn merge(&mut self, layer: Store, id: NodeId) -> NodeId {
+        self.counters += layer.counters;
+        let mut ids = Vec::with_capacity(layer.nodes.len());
+        let remap = |ids: &[NodeId], id: NodeId| match layer.local_index(id) {
+            Some(index) => ids[index],
+            None => id,
+        };
+
+        // children are always created before their parents
+        for &node in &layer.nodes {
+            let node = match node {
+                Node::Leaf { .. } | Node::StateLeaf { .. } => node,
+                Node::Interior {
+                    nw,
+                    ne,
+                    sw,
+                    se,
+                    level,
+                    population,
+                } => Node::Interior {
+                    nw: remap(&ids, nw),
+                    ne: remap(&ids, ne),
+                    sw: remap(&ids, sw),
+                    se: remap(&ids, se),
+                    level,
+                    population,
+                },
+            };
+            ids.push(self.add_node(node));
+        }
+
+        for (index, (step, jump)) in layer.steps.iter().zip(&layer.jumps).enumerate() {
+            if let Some(step) = *step {
+                self.add_step(ids[index], remap(&ids, step));
+            }
+            if let Some(jump) = *jump {
+                self.add_jump(ids[index], remap(&ids, jump));
+            }
+        }
+        for (&index, &step) in &layer.base_steps {
+            self.add_step(NodeId { index }, remap(&ids, step));
+        }
+        for (&index, &jump) in &layer.base_jumps {
+            self.add_jump(NodeId { index }, remap(&ids, jump));
+        }
+
+        remap(&ids, id)
     }
 }
 
@@ -170,4 +934,98 @@ mod tests {
     fn default() {
         let _store = Store::default();
     }
+
+    fn blocks(store: &mut Store, level: Level, count: i64) -> NodeId {
+        let mut node = store.create_empty(level);
+        for i in 0..count {
+            for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                node = node.set_cell_alive(store, crate::Position::new(4 * i + x, y));
+            }
+        }
+        node
+    }
+
+    #[test]
+    #[should_panic(expected = "store is full")]
+    fn full() {
+        let mut store = Store::new();
+        store.offset = RawIndex::MAX - 1;
+        store.create_leaf(leaf::empty());
+        store.create_empty(leaf::BASE_LEVEL);
+    }
+
+    #[test]
+    fn stats() {
+        let mut store = Store::new();
+        assert_eq!(store.stats().nodes(), 0);
+        assert_eq!(store.stats().load_factor(), 0.0);
+
+        let node = blocks(&mut store, Level(leaf::BASE_LEVEL.0 + 2), 3);
+        node.step(&mut store);
+        node.step(&mut store);
+        let stats = store.stats();
+        assert_eq!(stats.nodes(), store.len());
+        assert_eq!(stats.nodes_per_level.values().sum::<usize>(), store.len());
+        assert_eq!(stats.leaves, stats.nodes_per_level[&LEAF_LEVEL]);
+        assert_eq!(stats.step_hits, 1);
+        assert!(stats.step_misses > 0);
+        assert_eq!(
+            stats.step_hit_rate(),
+            Some(1.0 / (1 + stats.step_misses) as f64)
+        );
+        assert!(stats.load_factor() > 0.0 && stats.load_factor() <= 1.0);
+        assert_eq!(stats.memory_usage, store.memory_usage());
+        assert!(stats.memory_usage >= store.len() * Store::BYTES_PER_NODE);
+
+        // looking up results directly doesn't count as using them
+        assert!(store.get_step(node).is_some());
+        assert_eq!(store.stats().step_hits, 1);
+
+        store.reset_stats();
+        assert_eq!(store.stats().step_hits, 0);
+        assert_eq!(store.stats().jump_hit_rate(), None);
+    }
+
+    #[test]
+    fn evict_unreachable() {
+        let mut store = Store::new();
+        let root = blocks(&mut store, Level(leaf::BASE_LEVEL.0 + 3), 4);
+        let cells = root.get_alive_cells(&store);
+        root.jump(&mut store);
+
+        // without a limit nothing is removed
+        let len = store.len();
+        assert_eq!(store.evict(&[root]), vec![root]);
+        assert_eq!(store.len(), len);
+
+        store.set_memory_limit(Some(0));
+        let root = store.evict(&[root])[0];
+        assert!(store.len() < len);
+        assert_eq!(store.get_jump(root), None);
+        assert_eq!(root.get_alive_cells(&store), cells);
+        assert_eq!(root.jump(&mut store).get_alive_cells(&store), cells);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let mut store = Store::new();
+        let a = blocks(&mut store, Level(leaf::BASE_LEVEL.0 + 1), 1);
+        let b = blocks(&mut store, Level(leaf::BASE_LEVEL.0 + 1), 2);
+        a.jump(&mut store);
+        b.jump(&mut store);
+
+        // removing unreachable nodes is enough the first time
+        store.set_memory_limit(Some(store.memory_usage() - 1));
+        let roots = store.evict(&[a, b]);
+        assert!(store.get_jump(roots[1]).is_some());
+        assert!(store.get_jump(roots[0]).is_some());
+
+        // using a result again makes it the most recently used
+        roots[0].jump(&mut store);
+
+        store.set_memory_limit(Some(store.memory_usage() - 1));
+        let roots = store.evict(&roots);
+        assert!(store.get_jump(roots[0]).is_some());
+        assert!(store.get_jump(roots[1]).is_none());
+    }
 }
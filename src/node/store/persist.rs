@@ -0,0 +1,520 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! A compact binary format for saving and loading stores.
+//!
+//! A file starts with an eight byte magic number identifying what it contains and a four byte
+//! version number, and ends with an eight byte FNV-1a checksum of everything before it. Integers are
+//! little-endian, and node positions are written as LEB128 variable-length integers. Children are
+//! written relative to their parent, since they are usually created shortly before it.
+
+use crate::node::{leaf, Index, Level, Node, NodeId, NodeTemplate, RawIndex, Store, LEAF_LEVEL};
+use std::io::{Read, Write};
+
+/// The magic number at the start of a saved store.
+const STORE_MAGIC: &[u8; 8] = b"smeagolS";
+
+/// The current version of the format.
+///
+/// Version 2 added the origin of saved Life grids. Stores are the same in both versions.
+const VERSION: u32 = 2;
+
+/// The oldest version of the format that can still be read.
+const MIN_VERSION: u32 = 1;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// An error that can occur while loading a saved state.
+#[derive(Debug, Fail)]
+pub enum StateError {
+    /// An IO error.
+    #[fail(display = "IO error: {}", io)]
+    Io { io: std::io::Error },
+    /// The data doesn't start with the expected magic number.
+    #[fail(display = "Not a smeagol state")]
+    Magic,
+    /// The data was saved in a newer version of the format.
+    #[fail(display = "Unsupported state version: {}", version)]
+    Version { version: u32 },
+    /// The data was saved with a different leaf size.
+    #[fail(display = "State was saved with {} by {} leaves", size, size)]
+    LeafSize { size: u64 },
+    /// The checksum doesn't match the data.
+    #[fail(display = "State checksum mismatch")]
+    Checksum,
+    /// The data is inconsistent.
+    #[fail(display = "Corrupt state: {}", reason)]
+    Corrupt { reason: &'static str },
+}
+
+impl From<std::io::Error> for StateError {
+    fn from(io: std::io::Error) -> Self {
+        StateError::Io { io }
+    }
+}
+
+/// Writes primitive values, keeping a running checksum.
+pub(crate) struct Encoder<W> {
+    writer: W,
+    checksum: u64,
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder and writes the given magic number and the current version.
+    pub fn new(writer: W, magic: &[u8; 8]) -> std::io::Result<Self> {
+        let mut encoder = Self {
+            writer,
+            checksum: FNV_OFFSET_BASIS,
+        };
+        encoder.bytes(magic)?;
+        encoder.u32(VERSION)?;
+        Ok(encoder)
+    }
+
+    /// Writes the checksum and flushes the writer.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        let checksum = self.checksum;
+        self.writer.write_all(&checksum.to_le_bytes())?;
+        self.writer.flush()
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        for &byte in bytes {
+            self.checksum = (self.checksum ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+        self.writer.write_all(bytes)
+    }
+
+    pub fn u8(&mut self, value: u8) -> std::io::Result<()> {
+        self.bytes(&[value])
+    }
+
+    pub fn u32(&mut self, value: u32) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn i64(&mut self, value: i64) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn u128(&mut self, value: u128) -> std::io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes an unsigned LEB128 variable-length integer.
+    pub fn varint(&mut self, mut value: u64) -> std::io::Result<()> {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.u8(byte);
+            }
+            self.u8(byte | 0x80)?;
+        }
+    }
+}
+
+/// Reads primitive values, keeping a running checksum.
+pub(crate) struct Decoder<R> {
+    reader: R,
+    checksum: u64,
+    version: u32,
+}
+
+impl<R> Decoder<R>
+where
+    R: Read,
+{
+    /// Creates a decoder and checks the magic number and version.
+    pub fn new(reader: R, magic: &[u8; 8]) -> Result<Self, StateError> {
+        let mut decoder = Self {
+            reader,
+            checksum: FNV_OFFSET_BASIS,
+            version: VERSION,
+        };
+        let mut actual_magic = [0; 8];
+        decoder.bytes(&mut actual_magic)?;
+        if actual_magic != *magic {
+            return Err(StateError::Magic);
+        }
+        let version = decoder.u32()?;
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(StateError::Version { version });
+        }
+        decoder.version = version;
+        Ok(decoder)
+    }
+
+    /// Returns the version of the format being read.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Reads the checksum and checks that it matches the data read so far.
+    pub fn finish(mut self) -> Result<(), StateError> {
+        let mut checksum = [0; 8];
+        self.reader.read_exact(&mut checksum)?;
+        if u64::from_le_bytes(checksum) == self.checksum {
+            Ok(())
+        } else {
+            Err(StateError::Checksum)
+        }
+    }
+
+    pub fn bytes(&mut self, bytes: &mut [u8]) -> Result<(), StateError> {
+        self.reader.read_exact(bytes)?;
+        for &byte in bytes.iter() {
+            self.checksum = (self.checksum ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+        Ok(())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        let mut bytes = [0; 1];
+        self.bytes(&mut bytes)?;
+        Ok(bytes[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        let mut bytes = [0; 4];
+        self.bytes(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn i64(&mut self) -> Result<i64, StateError> {
+        let mut bytes = [0; 8];
+        self.bytes(&mut bytes)?;
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    pub fn u128(&mut self) -> Result<u128, StateError> {
+        let mut bytes = [0; 16];
+        self.bytes(&mut bytes)?;
+        Ok(u128::from_le_bytes(bytes))
+    }
+
+    /// Reads an unsigned LEB128 variable-length integer.
+    pub fn varint(&mut self) -> Result<u64, StateError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(StateError::Corrupt {
+            reason: "integer too long",
+        })
+    }
+}
+
+/// Tags written before each node.
+const LEAF: u8 = 0;
+const INTERIOR: u8 = 1;
+
+/// Flags written after the nodes for each node's evolution results.
+const HAS_STEP: u8 = 1;
+const HAS_JUMP: u8 = 2;
+
+impl Store {
+    /// Writes the store and the given root nodes in a compact binary format.
+    ///
+    /// The nodes, the memoized steps and jumps, and the step size are saved. The memory limit,
+    /// statistics and usage history are not.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store is layered on top of another store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use smeagol::{node::{Level, Store}, Position};
+    ///
+    /// let mut store = Store::new();
+    /// let empty = store.create_empty(Level(7));
+    /// let node = empty.set_cell_alive(&mut store, Position::new(1, 2));
+    ///
+    /// let mut bytes = vec![];
+    /// store.save(&mut bytes, &[node])?;
+    ///
+    /// let (store, roots) = Store::load(&bytes[..])?;
+    /// assert_eq!(roots[0].get_alive_cells(&store), vec![Position::new(1, 2)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save<W>(&self, writer: W, roots: &[NodeId]) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        let mut encoder = Encoder::new(writer, STORE_MAGIC)?;
+        self.encode(&mut encoder, roots)?;
+        encoder.finish()
+    }
+
+    /// Reads a store and its root nodes written by `save`.
+    pub fn load<R>(reader: R) -> Result<(Store, Vec<NodeId>), StateError>
+    where
+        R: Read,
+    {
+        let mut decoder = Decoder::new(reader, STORE_MAGIC)?;
+        let loaded = Self::decode(&mut decoder)?;
+        decoder.finish()?;
+        Ok(loaded)
+    }
+
+    /// Writes the store and the given root nodes to an encoder.
+    pub(crate) fn encode<W>(
+        &self,
+        encoder: &mut Encoder<W>,
+        roots: &[NodeId],
+    ) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        assert!(self.base.is_none());
+        if self.rule.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "stores of multi-state nodes cannot be saved",
+            ));
+        }
+        let position = |id: NodeId| id.index.0 as usize as u64;
+
+        encoder.u8(LEAF_LEVEL.0)?;
+        encoder.u8(self.step_log_2)?;
+        encoder.varint(self.nodes.len() as u64)?;
+
+        let row_bytes = (1 << LEAF_LEVEL.0) / 8;
+        for (index, &node) in self.nodes.iter().enumerate() {
+            match node {
+                Node::Leaf { grid } => {
+                    encoder.u8(LEAF)?;
+                    for y in leaf::MIN_COORD..=leaf::MAX_COORD {
+                        encoder.bytes(&leaf::get_row(grid, y).to_le_bytes()[..row_bytes])?;
+                    }
+                }
+                Node::StateLeaf { .. } => unreachable!(),
+                Node::Interior { nw, ne, sw, se, .. } => {
+                    encoder.u8(INTERIOR)?;
+                    for &child in &[nw, ne, sw, se] {
+                        encoder.varint(index as u64 - position(child))?;
+                    }
+                }
+            }
+        }
+
+        for (step, jump) in self.steps.iter().zip(&self.jumps) {
+            let flags = if step.is_some() { HAS_STEP } else { 0 }
+                | if jump.is_some() { HAS_JUMP } else { 0 };
+            encoder.u8(flags)?;
+            for &result in step.iter().chain(jump) {
+                encoder.varint(position(result))?;
+            }
+        }
+
+        encoder.varint(roots.len() as u64)?;
+        for &root in roots {
+            encoder.varint(position(root))?;
+        }
+        Ok(())
+    }
+
+    /// Reads a store and its root nodes from a decoder.
+    pub(crate) fn decode<R>(decoder: &mut Decoder<R>) -> Result<(Store, Vec<NodeId>), StateError>
+    where
+        R: Read,
+    {
+        let corrupt = |reason| StateError::Corrupt { reason };
+
+        let leaf_level = decoder.u8()?;
+        if leaf_level != LEAF_LEVEL.0 {
+            return Err(StateError::LeafSize {
+                size: 1 << leaf_level.min(63),
+            });
+        }
+
+        let mut store = Store::new();
+        store.step_log_2 = decoder.u8()?;
+        let len = decoder.varint()?;
+        if len >= Store::MAX_NODES as u64 {
+            return Err(corrupt("too many nodes"));
+        }
+
+        // saved nodes are unique, so they keep their positions in a new store
+        let row_bytes = (1 << LEAF_LEVEL.0) / 8;
+        for index in 0..len {
+            let id = match decoder.u8()? {
+                LEAF => {
+                    let mut grid = leaf::empty();
+                    for y in leaf::MIN_COORD..=leaf::MAX_COORD {
+                        let mut row = [0; 8];
+                        decoder.bytes(&mut row[..row_bytes])?;
+                        grid = leaf::set_row(grid, y, u64::from_le_bytes(row));
+                    }
+                    store.create_leaf(grid)
+                }
+                INTERIOR => {
+                    let mut children = [NodeId { index: Index(0) }; 4];
+                    for child in children.iter_mut() {
+                        let delta = decoder.varint()?;
+                        if delta == 0 || delta > index {
+                            return Err(corrupt("child created after parent"));
+                        }
+                        *child = NodeId {
+                            index: Index((index - delta) as RawIndex),
+                        };
+                    }
+                    let level = children[0].level(&store);
+                    if children.iter().any(|child| child.level(&store) != level) {
+                        return Err(corrupt("children with different levels"));
+                    }
+                    store.create_interior(NodeTemplate {
+                        nw: children[0],
+                        ne: children[1],
+                        sw: children[2],
+                        se: children[3],
+                    })
+                }
+                _ => return Err(corrupt("unknown node type")),
+            };
+            if id.index.0 != index as RawIndex {
+                return Err(corrupt("duplicate node"));
+            }
+        }
+
+        let read_result = |decoder: &mut Decoder<R>, store: &Store, level: Level| {
+            let position = decoder.varint()?;
+            if position >= len {
+                return Err(corrupt("result out of range"));
+            }
+            let result = NodeId {
+                index: Index(position as RawIndex),
+            };
+            if result.level(store).0 + 1 != level.0 {
+                return Err(corrupt("result with wrong level"));
+            }
+            Ok(result)
+        };
+        for index in 0..len as usize {
+            let flags = decoder.u8()?;
+            if flags & !(HAS_STEP | HAS_JUMP) != 0 {
+                return Err(corrupt("unknown evolution result flags"));
+            }
+            let level = NodeId {
+                index: Index(index as RawIndex),
+            }
+            .level(&store);
+            if flags & HAS_STEP != 0 {
+                store.steps[index] = Some(read_result(decoder, &store, level)?);
+            }
+            if flags & HAS_JUMP != 0 {
+                store.jumps[index] = Some(read_result(decoder, &store, level)?);
+            }
+        }
+
+        let root_count = decoder.varint()?;
+        let mut roots = vec![];
+        for _ in 0..root_count {
+            let position = decoder.varint()?;
+            if position >= len {
+                return Err(corrupt("root out of range"));
+            }
+            roots.push(NodeId {
+                index: Index(position as RawIndex),
+            });
+        }
+        Ok((store, roots))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn saved_glider() -> (Store, NodeId, Vec<u8>) {
+        let mut store = Store::new();
+        let mut node = store.create_empty(Level(leaf::BASE_LEVEL.0 + 2));
+        for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            node = node.set_cell_alive(&mut store, Position::new(x, y));
+        }
+        store.set_step_log_2(2);
+        node.step(&mut store);
+        node.jump(&mut store);
+
+        let mut bytes = vec![];
+        store.save(&mut bytes, &[node]).unwrap();
+        (store, node, bytes)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (store, node, bytes) = saved_glider();
+        let (loaded, roots) = Store::load(&bytes[..]).unwrap();
+
+        assert_eq!(roots, vec![node]);
+        assert_eq!(loaded.len(), store.len());
+        assert_eq!(loaded.step_log_2(), 2);
+        assert_eq!(loaded.get_step(node), store.get_step(node));
+        assert_eq!(loaded.get_jump(node), store.get_jump(node));
+        assert_eq!(node.get_alive_cells(&loaded), node.get_alive_cells(&store));
+    }
+
+    #[test]
+    fn varint() {
+        for &value in &[0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX] {
+            let mut bytes = vec![];
+            let mut encoder = Encoder::new(&mut bytes, STORE_MAGIC).unwrap();
+            encoder.varint(value).unwrap();
+            encoder.finish().unwrap();
+
+            let mut decoder = Decoder::new(&bytes[..], STORE_MAGIC).unwrap();
+            assert_eq!(decoder.varint().unwrap(), value);
+            decoder.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let (_, _, bytes) = saved_glider();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] ^= 1;
+        assert!(matches!(
+            Store::load(&wrong_magic[..]),
+            Err(StateError::Magic)
+        ));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[8] = 3;
+        assert!(matches!(
+            Store::load(&wrong_version[..]),
+            Err(StateError::Version { version: 3 })
+        ));
+
+        let mut wrong_leaf_size = bytes.clone();
+        wrong_leaf_size[12] += 1;
+        assert!(matches!(
+            Store::load(&wrong_leaf_size[..]),
+            Err(StateError::LeafSize { .. })
+        ));
+
+        let mut flipped = bytes.clone();
+        let last_node_byte = bytes.len() - 20;
+        flipped[last_node_byte] ^= 0x10;
+        assert!(Store::load(&flipped[..]).is_err());
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(Store::load(truncated), Err(StateError::Io { .. })));
+    }
+}
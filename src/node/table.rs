@@ -0,0 +1,186 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! A hash table used to find nodes that are already in a store.
+//!
+//! The table only stores the positions of nodes in the store's list of nodes, so every node is
+//! stored once. Collisions are resolved by linear probing.
+
+use crate::node::{Node, RawIndex};
+use std::hash::{Hash, Hasher};
+
+/// Marks a slot that doesn't contain a node. Stores never contain this many nodes.
+const EMPTY: RawIndex = RawIndex::MAX;
+
+/// The table grows once more than `MAX_LOAD_NUMERATOR / MAX_LOAD_DENOMINATOR` of it is used.
+const MAX_LOAD_NUMERATOR: usize = 7;
+const MAX_LOAD_DENOMINATOR: usize = 10;
+
+const MIN_CAPACITY_LOG_2: u32 = 4;
+
+/// A hash table of the positions of nodes in a list of nodes.
+#[derive(Clone, Debug)]
+pub struct NodeTable {
+    slots: Vec<RawIndex>,
+    len: usize,
+}
+
+impl NodeTable {
+    /// Creates a new empty table.
+    pub fn new() -> Self {
+        Self {
+            slots: vec![EMPTY; 1 << MIN_CAPACITY_LOG_2],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of nodes the table can hold before it grows.
+    pub fn capacity(&self) -> usize {
+        self.slots.len() * MAX_LOAD_NUMERATOR / MAX_LOAD_DENOMINATOR
+    }
+
+    /// Returns the number of bytes allocated by the table.
+    pub fn memory_usage(&self) -> usize {
+        self.slots.capacity() * std::mem::size_of::<RawIndex>()
+    }
+
+    /// Returns the position of the given node in the list of nodes, if it is in the table.
+    pub fn find(&self, nodes: &[Node], node: &Node) -> Option<usize> {
+        let mask = self.slots.len() - 1;
+        let mut slot = self.home_slot(node);
+        loop {
+            match self.slots[slot] {
+                EMPTY => return None,
+                position if nodes[position as usize] == *node => return Some(position as usize),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    /// Adds the node at the given position in the list of nodes to the table.
+    ///
+    /// The node must not already be in the table.
+    pub fn insert(&mut self, nodes: &[Node], position: usize) {
+        if self.len + 1 > self.capacity() {
+            self.grow(nodes);
+        }
+        self.insert_unchecked(&nodes[position], position as RawIndex);
+        self.len += 1;
+    }
+
+    /// Doubles the number of slots, moving every node to its new slot.
+    fn grow(&mut self, nodes: &[Node]) {
+        let new_slots = vec![EMPTY; self.slots.len() * 2];
+        let slots = std::mem::replace(&mut self.slots, new_slots);
+        for position in slots.into_iter().filter(|&position| position != EMPTY) {
+            self.insert_unchecked(&nodes[position as usize], position);
+        }
+    }
+
+    fn insert_unchecked(&mut self, node: &Node, position: RawIndex) {
+        let mask = self.slots.len() - 1;
+        let mut slot = self.home_slot(node);
+        while self.slots[slot] != EMPTY {
+            slot = (slot + 1) & mask;
+        }
+        self.slots[slot] = position;
+    }
+
+    /// Returns the first slot to look for the node in.
+    fn home_slot(&self, node: &Node) -> usize {
+        let mut hasher = NodeHasher(0);
+        node.hash(&mut hasher);
+        // the high bits of a multiplicative hash are the best mixed
+        let capacity_log_2 = self.slots.len().trailing_zeros();
+        (hasher.finish() >> (64 - capacity_log_2)) as usize
+    }
+}
+
+/// A fast hasher for nodes, which are made of a few machine words.
+///
+/// This is the hash function used by Firefox and rustc.
+struct NodeHasher(u64);
+
+impl NodeHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for NodeHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+        for &byte in chunks.remainder() {
+            self.add(u64::from(byte));
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.add(u64::from(value));
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.add(u64::from(value));
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.add(u64::from(value));
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.add(value);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.add(value as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{leaf, Index, Level, NodeId};
+
+    fn interior(i: RawIndex) -> Node {
+        let id = NodeId { index: Index(i) };
+        Node::Interior {
+            nw: id,
+            ne: id,
+            sw: id,
+            se: id,
+            level: Level(leaf::BASE_LEVEL.0),
+            population: 0,
+        }
+    }
+
+    #[test]
+    fn find_insert() {
+        let mut nodes = vec![];
+        let mut table = NodeTable::new();
+        for i in 0..1000 {
+            let node = interior(i);
+            assert_eq!(table.find(&nodes, &node), None);
+            nodes.push(node);
+            table.insert(&nodes, i as usize);
+        }
+        for i in 0..1000 {
+            assert_eq!(table.find(&nodes, &interior(i)), Some(i as usize));
+        }
+        assert_eq!(table.find(&nodes, &interior(1000)), None);
+        assert!(table.capacity() >= 1000);
+    }
+}
@@ -6,5 +6,6 @@
 
 //! Parse different kinds of Life patterns.
 
+pub mod apgcode;
 pub mod macrocell;
 pub mod rle;
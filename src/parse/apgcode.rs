@@ -0,0 +1,237 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Catagolue apgcodes, like `xs4_33` for a block or `xq4_153` for a glider.
+//!
+//! An apgcode is a prefix describing the object, followed by an underscore and the cells of the
+//! object in extended Wechsler format. The prefix is `xs` and the population for still lifes, `xp`
+//! and the period for oscillators, and `xq` and the period for spaceships.
+//!
+//! In extended Wechsler format, the pattern is split into strips of five rows, separated by `z`.
+//! Each column of a strip is written as a character from `0` to `v`, the bits of which are the
+//! cells of the column from top to bottom. Runs of empty columns are shortened to `w` for two, `x`
+//! for three, and `y` followed by a character from `0` to `z` for four to 39 empty columns, and
+//! empty columns at the end of a strip are left out.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), failure::Error> {
+//! let glider = smeagol::parse::apgcode::Apgcode::from_code("xq4_153")?;
+//! assert_eq!(glider.prefix(), "xq4");
+//! assert_eq!(glider.alive_cells().len(), 5);
+//! # Ok(())
+//! # }
+//! ```
+
+/// The characters used in extended Wechsler format, in order of their values.
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// The number of rows in a strip.
+const STRIP_HEIGHT: u32 = 5;
+
+/// An error than can occur while parsing an apgcode.
+#[derive(Debug, Fail)]
+pub enum ApgcodeError {
+    /// The prefix is not `xs`, `xp` or `xq` followed by a number.
+    #[fail(display = "Invalid apgcode prefix: {}", prefix)]
+    InvalidPrefix { prefix: String },
+    /// The cells contain a character that isn't valid in extended Wechsler format.
+    #[fail(display = "Invalid extended Wechsler character: {:?}", character)]
+    InvalidCharacter { character: char },
+}
+
+/// A Catagolue apgcode.
+pub struct Apgcode {
+    prefix: String,
+    cells: Vec<(u32, u32)>,
+}
+
+impl Apgcode {
+    /// Parses an apgcode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // blinker
+    /// let apgcode = smeagol::parse::apgcode::Apgcode::from_code("xp2_7")?;
+    /// assert_eq!(apgcode.alive_cells(), vec![(0, 0), (0, 1), (0, 2)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_code(code: &str) -> Result<Self, ApgcodeError> {
+        let (prefix, wechsler) = match code.find('_') {
+            Some(index) => (&code[..index], &code[index + 1..]),
+            None => (code, ""),
+        };
+        let is_valid_prefix = ["xs", "xp", "xq"]
+            .iter()
+            .any(|&kind| prefix.starts_with(kind))
+            && prefix.len() > 2
+            && prefix[2..].bytes().all(|byte| byte.is_ascii_digit());
+        if !is_valid_prefix || wechsler.is_empty() {
+            return Err(ApgcodeError::InvalidPrefix {
+                prefix: prefix.to_owned(),
+            });
+        }
+        Ok(Self {
+            prefix: prefix.to_owned(),
+            cells: decode_wechsler(wechsler)?,
+        })
+    }
+
+    /// Returns the prefix of the apgcode, like `xs4` or `xp2`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns a `Vec` containing the coordinates of alive cells in the apgcode, sorted by row.
+    pub fn alive_cells(&self) -> Vec<(u32, u32)> {
+        self.cells.clone()
+    }
+}
+
+/// Encodes the alive cells in extended Wechsler format.
+///
+/// The cells are encoded as they are, so a pattern that doesn't touch the top and left edges
+/// starts with empty rows and columns.
+///
+/// # Examples
+///
+/// ```
+/// use smeagol::parse::apgcode::encode_wechsler;
+///
+/// // block
+/// assert_eq!(encode_wechsler(&[(0, 0), (1, 0), (0, 1), (1, 1)]), "33");
+/// ```
+pub fn encode_wechsler(cells: &[(u32, u32)]) -> String {
+    let strips = cells
+        .iter()
+        .map(|&(_, y)| y / STRIP_HEIGHT + 1)
+        .max()
+        .unwrap_or(0);
+    let width = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let mut columns = vec![vec![0_u8; width as usize]; strips as usize];
+    for &(x, y) in cells {
+        columns[(y / STRIP_HEIGHT) as usize][x as usize] |= 1 << (y % STRIP_HEIGHT);
+    }
+
+    let mut code = String::new();
+    for (index, strip) in columns.iter().enumerate() {
+        if index > 0 {
+            code.push('z');
+        }
+        let mut zeros = 0;
+        for &column in strip {
+            if column == 0 {
+                zeros += 1;
+                continue;
+            }
+            while zeros >= 40 {
+                code.push_str("yz");
+                zeros -= 39;
+            }
+            match zeros {
+                0 => {}
+                1 => code.push('0'),
+                2 => code.push('w'),
+                3 => code.push('x'),
+                _ => {
+                    code.push('y');
+                    code.push(char::from(DIGITS[zeros - 4]));
+                }
+            }
+            zeros = 0;
+            code.push(char::from(DIGITS[usize::from(column)]));
+        }
+    }
+    code
+}
+
+/// Decodes alive cells from extended Wechsler format, sorted by row.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), failure::Error> {
+/// use smeagol::parse::apgcode::decode_wechsler;
+///
+/// // block
+/// assert_eq!(decode_wechsler("33")?, vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_wechsler(code: &str) -> Result<Vec<(u32, u32)>, ApgcodeError> {
+    let value = |character: char| {
+        DIGITS
+            .iter()
+            .position(|&digit| char::from(digit) == character)
+            .ok_or(ApgcodeError::InvalidCharacter { character })
+    };
+
+    let mut cells = vec![];
+    let mut x = 0;
+    let mut strip = 0;
+    let mut characters = code.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let character = characters
+                    .next()
+                    .ok_or(ApgcodeError::InvalidCharacter { character })?;
+                x += 4 + value(character)? as u32;
+            }
+            'z' => {
+                x = 0;
+                strip += 1;
+            }
+            _ => {
+                let column = value(character)?;
+                for row in 0..STRIP_HEIGHT {
+                    if column & 1 << row != 0 {
+                        cells.push((x, strip * STRIP_HEIGHT + row));
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for &code in &["33", "696", "153", "7", "y41", "8k8z8k8", "1zz1", "1yzyz03"] {
+            assert_eq!(encode_wechsler(&decode_wechsler(code).unwrap()), code);
+        }
+    }
+
+    #[test]
+    fn long_gaps() {
+        let cells = [(0, 0), (100, 0)];
+        let code = encode_wechsler(&cells);
+        assert_eq!(code, "1yzyzyh1");
+        assert_eq!(decode_wechsler(&code).unwrap(), cells);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Apgcode::from_code("xs4").is_err());
+        assert!(Apgcode::from_code("yl144_1_16_afb5f3db909e60548f086e22ee3353ac").is_err());
+        assert!(Apgcode::from_code("xs_33").is_err());
+        assert!(Apgcode::from_code("xs4_3!").is_err());
+        assert!(Apgcode::from_code("xs4_3y").is_err());
+        assert!(Apgcode::from_code("xs4_w").is_ok());
+    }
+}
@@ -19,14 +19,15 @@
 //! # Ok(())
 //! # }
 //! ```
+use crate::Topology;
 use nom::{line_ending, not_line_ending};
 use std::io::Read;
 
-/// Matches any amount of whitespace.
+// Matches any amount of whitespace.
 named!(whitespace, take_while!(|c: u8| (c as char).is_whitespace()));
 
-/// Matches a comment line in an RLE file, returning the comment without the leading `#` or
-/// trailing newline.
+// Matches a comment line in an RLE file, returning the comment without the leading `#` or
+// trailing newline.
 named!(comment_line<&[u8], &[u8]>,
     do_parse!(
         char!('#') >>
@@ -36,10 +37,24 @@ named!(comment_line<&[u8], &[u8]>,
     )
 );
 
-/// Matches the header portion of an RLE file, returning the dimensions `(x, y)`.
-///
-/// TODO: also consume the Life rule.
-named!(header<&[u8], (u32, u32)>,
+// Matches the rule at the end of the header of an RLE file, returning the rule.
+named!(header_rule<&[u8], &[u8]>,
+    do_parse!(
+        whitespace >>
+        tag!(",") >>
+        whitespace >>
+        tag!("rule") >>
+        whitespace >>
+        tag!("=") >>
+        whitespace >>
+        rule: take_till!(|c: u8| (c as char).is_whitespace()) >>
+        (rule)
+    )
+);
+
+// Matches the header portion of an RLE file, returning the dimensions `(x, y)` and the rule, if
+// any.
+named!(header<&[u8], (u32, u32, Option<&[u8]>)>,
     do_parse!(
         whitespace >>
         tag!("x") >>
@@ -55,9 +70,10 @@ named!(header<&[u8], (u32, u32)>,
         tag!("=") >>
         whitespace >>
         height: map_res!(nom::digit0, btoi::btoi) >>
+        rule: opt!(header_rule) >>
         not_line_ending >>
         line_ending >>
-        (width, height)
+        (width, height, rule)
     )
 );
 
@@ -70,7 +86,7 @@ fn parse_rle_digits(digits: &[u8]) -> Result<u32, btoi::ParseIntegerError> {
     }
 }
 
-/// Matches a single unit in an RLE pattern.
+// Matches a single unit in an RLE pattern.
 named!(pattern_unit<&[u8], PatternUnit>, do_parse!(
     take_while!(|c: u8| (c as char).is_whitespace()) >>
     reps: map_res!(nom::digit0, parse_rle_digits) >>
@@ -78,15 +94,15 @@ named!(pattern_unit<&[u8], PatternUnit>, do_parse!(
     ( PatternUnit { reps, tag } )
 ));
 
-/// Matches an entire RLE pattern string.
+// Matches an entire RLE pattern string.
 named!(pattern<&[u8], Vec<PatternUnit>>, do_parse!(
     units: many0!(pattern_unit) >>
     tag!("!") >>
     (units)
 ));
 
-/// Matches an entire RLE file, returning the triple `(comments, (x, y), pattern_units)`.
-named!(rle<&[u8], (Vec<&[u8]>, (u32, u32), Vec<PatternUnit>)>,
+// Matches an entire RLE file, returning the triple `(comments, (x, y, rule), pattern_units)`.
+named!(rle<&[u8], (Vec<&[u8]>, (u32, u32, Option<&[u8]>), Vec<PatternUnit>)>,
     do_parse!(
         comments: many0!(comment_line) >>
         dimensions: header >>
@@ -104,6 +120,17 @@ pub enum RleError {
     Parse,
 }
 
+/// Returns the topology given in the rule of an RLE file.
+fn parse_topology(rule: Option<&[u8]>) -> Result<Topology, failure::Error> {
+    match rule {
+        Some(rule) => {
+            let rule = std::str::from_utf8(rule).map_err(|_| RleError::Parse)?;
+            Ok(Topology::from_rule(rule)?)
+        }
+        None => Ok(Topology::Plane),
+    }
+}
+
 /// A single unit in an RLE pattern.
 ///
 /// A pattern unit consists of a character and a number indicating the repititions of that
@@ -116,6 +143,7 @@ struct PatternUnit {
 /// A run-length encoded Life pattern.
 pub struct Rle {
     units: Vec<PatternUnit>,
+    topology: Topology,
 }
 
 impl Rle {
@@ -139,17 +167,15 @@ impl Rle {
         let mut buf = vec![];
         reader.read_to_end(&mut buf)?;
 
-        let (_rest, (_comments, (_width, _height), units)) =
-            rle(&buf).map_err(|_| RleError::Parse)?;
-
-        Ok(Self { units })
+        Self::from_file_contents(&buf)
     }
 
     pub fn from_file_contents(contents: &[u8]) -> Result<Self, failure::Error> {
-        let (_rest, (_comments, (_width, _height), units)) =
+        let (_rest, (_comments, (_width, _height, rule), units)) =
             rle(contents).map_err(|_| RleError::Parse)?;
+        let topology = parse_topology(rule)?;
 
-        Ok(Self { units })
+        Ok(Self { units, topology })
     }
 
     /// Reads an RLE pattern from the given byte array.
@@ -165,7 +191,35 @@ impl Rle {
     /// ```
     pub fn from_pattern(pattern_str: &[u8]) -> Result<Self, failure::Error> {
         let (_rest, units) = pattern(pattern_str).map_err(|_| RleError::Parse)?;
-        Ok(Self { units })
+        Ok(Self {
+            units,
+            topology: Topology::Plane,
+        })
+    }
+
+    /// Returns the topology given in the rule of the RLE file, such as `B3/S23:T64,64`.
+    ///
+    /// Patterns without a topology are on an unbounded plane.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let rle = smeagol::parse::rle::Rle::from_file_contents(
+    ///     b"x = 3, y = 3, rule = B3/S23:T16,16\nbob$2bo$3o!",
+    /// )?;
+    /// assert_eq!(
+    ///     rle.topology(),
+    ///     smeagol::Topology::Torus {
+    ///         width: 16,
+    ///         height: 16
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn topology(&self) -> Topology {
+        self.topology
     }
 
     /// Returns a `Vec` containing the coordinates of alive cells in the RLE pattern.
@@ -240,6 +294,24 @@ mod tests {
         Rle::from_pattern(b"foo").unwrap();
     }
 
+    #[test]
+    fn topology() {
+        let rle = Rle::from_file("./assets/glider.rle").unwrap();
+        assert_eq!(rle.topology(), Topology::Plane);
+
+        let rle =
+            Rle::from_file_contents(b"x = 3, y = 3, rule = B3/S23:P10,20\nbob$2bo$3o!").unwrap();
+        assert_eq!(
+            rle.topology(),
+            Topology::BoundedPlane {
+                width: 10,
+                height: 20
+            }
+        );
+
+        assert!(Rle::from_file_contents(b"x = 3, y = 3, rule = B3/S23:T0,0\nbob$2bo$3o!").is_err());
+    }
+
     #[test]
     fn alive_cells() {
         // glider
@@ -0,0 +1,32 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Multi-state cellular automata described by Golly rule files.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), failure::Error> {
+//! let rule = smeagol::rules::table::Rule::from_file("./assets/wireworld.rule")?;
+//! let mut universe = smeagol::rules::Universe::new(rule);
+//!
+//! // an electron travelling east along a wire
+//! universe.set_cell(smeagol::Position::new(0, 0), 2);
+//! universe.set_cell(smeagol::Position::new(1, 0), 1);
+//! for x in 2..10 {
+//!     universe.set_cell(smeagol::Position::new(x, 0), 3);
+//! }
+//!
+//! universe.step();
+//! assert_eq!(universe.get_cell(smeagol::Position::new(2, 0)), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod table;
+mod universe;
+
+pub use self::universe::Universe;
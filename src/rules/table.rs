@@ -0,0 +1,1016 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Golly rule files (`@RULE`, `@TABLE` and `@TREE`).
+//!
+//! A rule file names the rule in its `@RULE` line and describes the transition function in either
+//! a `@TABLE` section, which lists transitions using variables and symmetries, or a `@TREE`
+//! section, which describes a decision tree over the states of the neighborhood. Other sections
+//! such as `@COLORS` and `@ICONS` are ignored.
+//!
+//! Neighbors are always given in clockwise order starting from north: `N, NE, E, SE, S, SW, W, NW`
+//! for the Moore neighborhood and `N, E, S, W` for the von Neumann neighborhood.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), failure::Error> {
+//! let rule = smeagol::rules::table::Rule::from_file("./assets/wireworld.rule")?;
+//! assert_eq!(rule.name(), "WireWorld");
+//! assert_eq!(rule.num_states(), 4);
+//!
+//! // a conductor next to a single electron head becomes an electron head
+//! assert_eq!(rule.next_state(3, &[1, 0, 0, 0, 0, 0, 0, 0]), 1);
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+};
+
+/// The maximum number of states supported by a rule.
+const MAX_STATES: usize = 256;
+
+/// An error than can occur while parsing a rule file.
+#[derive(Debug, Fail)]
+pub enum RuleError {
+    /// The rule file is not valid UTF-8.
+    #[fail(display = "Rule file is not valid UTF-8")]
+    Utf8,
+    /// The rule file has no `@RULE` line.
+    #[fail(display = "Missing @RULE line")]
+    MissingName,
+    /// The rule file has neither a `@TABLE` nor a `@TREE` section.
+    #[fail(display = "Missing @TABLE or @TREE section")]
+    MissingTransitions,
+    /// A parsing error.
+    #[fail(display = "Parsing error on line {}: {}", line, message)]
+    Parse { line: usize, message: String },
+}
+
+impl RuleError {
+    fn parse(line: usize, message: &str) -> Self {
+        RuleError::Parse {
+            line,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// The cells that influence the next state of a cell.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Neighborhood {
+    /// The eight orthogonally and diagonally adjacent cells.
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+}
+
+impl Neighborhood {
+    /// Returns the number of neighbors of a cell.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smeagol::rules::table::Neighborhood;
+    ///
+    /// assert_eq!(Neighborhood::Moore.size(), 8);
+    /// assert_eq!(Neighborhood::VonNeumann.size(), 4);
+    /// ```
+    pub fn size(self) -> usize {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+        }
+    }
+
+    /// Returns the order in which rule trees visit the neighbors, which is the order used by Golly.
+    fn tree_order(self) -> &'static [usize] {
+        match self {
+            // NW, NE, SW, SE, N, W, E, S
+            Neighborhood::Moore => &[7, 1, 5, 3, 0, 6, 2, 4],
+            // N, W, E, S
+            Neighborhood::VonNeumann => &[0, 3, 1, 2],
+        }
+    }
+}
+
+/// The symmetries applied to each transition of a rule table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Symmetry {
+    None,
+    Rotate4,
+    Rotate8,
+    ReflectHorizontal,
+    Rotate4Reflect,
+    Rotate8Reflect,
+    Permute,
+}
+
+impl Symmetry {
+    /// Returns the permutations of the neighbor positions generated by the symmetry, or `None` for
+    /// `Symmetry::Permute`.
+    ///
+    /// Position 0 is the center cell and positions `1..=size` are the neighbors in clockwise order.
+    fn permutations(self, neighborhood: Neighborhood) -> Option<Vec<Vec<usize>>> {
+        let size = neighborhood.size();
+        let rotate = |amount: usize| {
+            let mut permutation = vec![0];
+            permutation.extend((0..size).map(|i| 1 + (i + amount) % size));
+            permutation
+        };
+        let reflect = |permutation: &[usize]| {
+            let mut reflected = vec![0];
+            reflected.extend((0..size).map(|i| permutation[1 + (size - i) % size]));
+            reflected
+        };
+
+        // rotations by 90 degrees
+        let quarter = size / 4;
+        let rotations = match self {
+            Symmetry::None | Symmetry::ReflectHorizontal => vec![rotate(0)],
+            Symmetry::Rotate4 | Symmetry::Rotate4Reflect => {
+                (0..4).map(|i| rotate(i * quarter)).collect()
+            }
+            Symmetry::Rotate8 | Symmetry::Rotate8Reflect => (0..size).map(rotate).collect(),
+            Symmetry::Permute => return None,
+        };
+
+        let permutations = match self {
+            Symmetry::ReflectHorizontal | Symmetry::Rotate4Reflect | Symmetry::Rotate8Reflect => {
+                rotations
+                    .iter()
+                    .flat_map(|rotation| vec![rotation.clone(), reflect(rotation)])
+                    .collect()
+            }
+            _ => rotations,
+        };
+
+        Some(permutations)
+    }
+}
+
+/// A set of cell states.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct StateSet([u64; MAX_STATES / 64]);
+
+impl StateSet {
+    fn empty() -> Self {
+        StateSet([0; MAX_STATES / 64])
+    }
+
+    fn single(state: u8) -> Self {
+        let mut set = Self::empty();
+        set.insert(state);
+        set
+    }
+
+    fn insert(&mut self, state: u8) {
+        self.0[usize::from(state) / 64] |= 1 << (state % 64);
+    }
+
+    fn union(&mut self, other: StateSet) {
+        for (word, other_word) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= other_word;
+        }
+    }
+
+    fn contains(&self, state: u8) -> bool {
+        self.0[usize::from(state) / 64] & (1 << (state % 64)) > 0
+    }
+
+    fn states(&self) -> Vec<u8> {
+        (0..MAX_STATES)
+            .map(|state| state as u8)
+            .filter(|&state| self.contains(state))
+            .collect()
+    }
+}
+
+/// A single input or output of a transition before variables are expanded.
+#[derive(Clone, Copy, Debug)]
+enum Token {
+    /// A single state.
+    State(u8),
+    /// An index into the list of variables.
+    Var(usize),
+}
+
+/// A transition of a rule table with its variables and symmetries expanded, except for
+/// `symmetries:permute`.
+#[derive(Clone, Debug)]
+struct Transition {
+    /// The accepted states of the neighbors in the order visited by rule trees, followed by the
+    /// accepted states of the center.
+    inputs: Vec<StateSet>,
+    output: u8,
+    /// Whether the neighbors may be matched in any order.
+    permute: bool,
+}
+
+/// How far a transition has matched the inputs read so far while a rule table is compiled.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Progress {
+    /// The inputs are matched in order.
+    Ordered,
+    /// The neighbors are matched in any order. Bit `m` is set if the neighbors read so far can be
+    /// assigned to the neighbor positions in the mask `m` of the transition.
+    Permuted([u64; 4]),
+}
+
+impl Progress {
+    /// Returns the progress after reading the given state as the next neighbor, or `None` if the
+    /// transition no longer matches.
+    fn advance(self, transition: &Transition, state: u8) -> Option<Self> {
+        let masks = match self {
+            Progress::Ordered => return Some(self),
+            Progress::Permuted(masks) => masks,
+        };
+        let neighbors = &transition.inputs[..transition.inputs.len() - 1];
+        let mut next = [0u64; 4];
+        for mask in (0..256).filter(|&mask| masks[mask / 64] & (1 << (mask % 64)) != 0) {
+            for (position, set) in neighbors.iter().enumerate() {
+                if mask & (1 << position) == 0 && set.contains(state) {
+                    let next_mask = mask | (1 << position);
+                    next[next_mask / 64] |= 1 << (next_mask % 64);
+                }
+            }
+        }
+        if next == [0; 4] {
+            None
+        } else {
+            Some(Progress::Permuted(next))
+        }
+    }
+}
+
+/// Compiles the transitions of a rule table into a rule tree.
+///
+/// Each node of the tree corresponds to the transitions that still match after reading some of the
+/// inputs, so nodes are memoized by those transitions and their progress. Transitions with
+/// `symmetries:permute` only depend on how many neighbors are in each state, which keeps the number
+/// of nodes small without expanding every arrangement of the neighbors.
+struct Compiler<'a> {
+    num_states: usize,
+    transitions: &'a [Transition],
+    children: Vec<u32>,
+    nodes: HashMap<(usize, Vec<u32>), u32>,
+    memo: HashMap<(usize, Vec<(usize, Progress)>), u32>,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(num_states: usize, num_inputs: usize, transitions: &'a [Transition]) -> Tree {
+        let mut compiler = Self {
+            num_states,
+            transitions,
+            children: vec![],
+            nodes: HashMap::new(),
+            memo: HashMap::new(),
+        };
+        let candidates = transitions
+            .iter()
+            .enumerate()
+            .map(|(index, transition)| {
+                let progress = if transition.permute {
+                    Progress::Permuted([1, 0, 0, 0])
+                } else {
+                    Progress::Ordered
+                };
+                (index, progress)
+            })
+            .collect();
+        let root = compiler.node(num_inputs, candidates);
+        Tree {
+            num_states,
+            children: compiler.children,
+            root: root as usize,
+        }
+    }
+
+    /// Returns the node that reads the last `remaining` inputs given the transitions that match
+    /// the inputs read so far, in order.
+    fn node(&mut self, remaining: usize, candidates: Vec<(usize, Progress)>) -> u32 {
+        let key = (remaining, candidates);
+        if let Some(&node) = self.memo.get(&key) {
+            return node;
+        }
+        let (_, candidates) = &key;
+
+        let mut children = Vec::with_capacity(self.num_states);
+        // there can be 256 states, which don't all fit in a range of `u8`s
+        for state in (0..self.num_states).map(|state| state as u8) {
+            let matching = candidates.iter().filter_map(|&(index, progress)| {
+                let transition = &self.transitions[index];
+                let position = transition.inputs.len() - remaining;
+                let matched = if remaining == 1 || progress == Progress::Ordered {
+                    transition.inputs[position]
+                        .contains(state)
+                        .then_some(progress)
+                } else {
+                    progress.advance(transition, state)
+                };
+                matched.map(|progress| (index, progress))
+            });
+            let child = if remaining == 1 {
+                // a cell that matches no transition keeps its state
+                matching
+                    .map(|(index, _)| self.transitions[index].output)
+                    .next()
+                    .unwrap_or(state)
+                    .into()
+            } else {
+                let matching = matching.collect();
+                self.node(remaining - 1, matching)
+            };
+            children.push(child);
+        }
+
+        let next = self.nodes.len() as u32;
+        let flat = &mut self.children;
+        let node = *self
+            .nodes
+            .entry((remaining, children))
+            .or_insert_with_key(|(_, children)| {
+                flat.extend_from_slice(children);
+                next
+            });
+        self.memo.insert(key, node);
+        node
+    }
+}
+
+/// A compiled rule tree.
+#[derive(Clone, Debug)]
+struct Tree {
+    num_states: usize,
+    /// The children of node `i` are `children[i * num_states..(i + 1) * num_states]`.
+    children: Vec<u32>,
+    root: usize,
+}
+
+impl Tree {
+    fn lookup(&self, inputs: impl IntoIterator<Item = u8>) -> u8 {
+        let mut node = self.root;
+        for state in inputs {
+            node = self.children[node * self.num_states + usize::from(state)] as usize;
+        }
+        node as u8
+    }
+}
+
+/// A multi-state cellular automaton rule.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    name: String,
+    num_states: usize,
+    neighborhood: Neighborhood,
+    /// The transition function, with rule tables compiled into a tree.
+    tree: Tree,
+}
+
+impl Rule {
+    /// Loads a rule from the given file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let rule = smeagol::rules::table::Rule::from_file("./assets/wireworld.rule")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file<P>(path: P) -> Result<Self, failure::Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::open(path)?;
+        let mut reader = std::io::BufReader::new(file);
+
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf)?;
+
+        Self::from_file_contents(&buf)
+    }
+
+    /// Reads a rule from the contents of a rule file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// // every cell copies the state of its northern neighbor
+    /// let rule = smeagol::rules::table::Rule::from_file_contents(
+    ///     b"@RULE Shift
+    /// @TABLE
+    /// n_states:2
+    /// neighborhood:vonNeumann
+    /// symmetries:none
+    /// var a={0,1}
+    /// var b={0,1}
+    /// var c={0,1}
+    /// var d={0,1}
+    /// var e={0,1}
+    /// a,b,c,d,e,b",
+    /// )?;
+    /// assert_eq!(rule.next_state(0, &[1, 0, 0, 0]), 1);
+    /// assert_eq!(rule.next_state(1, &[0, 1, 1, 1]), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_file_contents(contents: &[u8]) -> Result<Self, failure::Error> {
+        let contents = std::str::from_utf8(contents).map_err(|_| RuleError::Utf8)?;
+
+        let mut name = None;
+        let mut table = None;
+        let mut tree = None;
+
+        let mut section = "";
+        let mut section_lines = vec![];
+        // a sentinel section flushes the last section
+        let lines = contents.lines().chain(std::iter::once("@END"));
+        for (number, line) in lines.enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if !line.starts_with('@') {
+                section_lines.push((number, line));
+                continue;
+            }
+
+            match section {
+                "@TABLE" => table = Some(parse_table(&section_lines)?),
+                "@TREE" => tree = Some(parse_tree(&section_lines)?),
+                _ => {}
+            }
+
+            section = line.split_whitespace().next().unwrap_or("");
+            section_lines.clear();
+
+            if section == "@RULE" {
+                let rule_name = line["@RULE".len()..].trim();
+                if rule_name.is_empty() {
+                    return Err(RuleError::parse(number, "missing rule name").into());
+                }
+                name = Some(rule_name.to_owned());
+            }
+        }
+
+        let name = name.ok_or(RuleError::MissingName)?;
+        let (num_states, neighborhood, tree) =
+            table.or(tree).ok_or(RuleError::MissingTransitions)?;
+
+        Ok(Self {
+            name,
+            num_states,
+            neighborhood,
+            tree,
+        })
+    }
+
+    /// Returns the name of the rule.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number of states of the rule, including the empty state 0.
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    /// Returns the neighborhood used by the rule.
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// Returns the next state of a cell given its current state and the states of its neighbors.
+    ///
+    /// The neighbors are given in clockwise order starting from north. A cell that matches no
+    /// transition of a rule table keeps its current state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of neighbors does not match the neighborhood of the rule, or if any
+    /// state is not a valid state of the rule.
+    pub fn next_state(&self, center: u8, neighbors: &[u8]) -> u8 {
+        assert_eq!(neighbors.len(), self.neighborhood.size());
+        assert!(usize::from(center) < self.num_states);
+        assert!(neighbors
+            .iter()
+            .all(|&state| usize::from(state) < self.num_states));
+
+        self.transition(center, neighbors)
+    }
+
+    /// Returns the next state of a cell like `next_state`, without checking the states.
+    pub(crate) fn transition(&self, center: u8, neighbors: &[u8]) -> u8 {
+        self.tree.lookup(
+            self.neighborhood
+                .tree_order()
+                .iter()
+                .map(|&i| neighbors[i])
+                .chain(std::iter::once(center)),
+        )
+    }
+}
+
+/// Splits a line of the form `key:value` or `key=value`, returning the trimmed value if the key
+/// matches.
+fn key_value<'a>(line: &'a str, key: &str, separator: char) -> Option<&'a str> {
+    let mut parts = line.splitn(2, separator);
+    if parts.next()?.trim() == key {
+        Some(parts.next()?.trim())
+    } else {
+        None
+    }
+}
+
+/// Parses the number of states of a rule.
+fn parse_num_states(number: usize, value: &str) -> Result<usize, RuleError> {
+    match value.parse::<usize>() {
+        Ok(num_states) if (2..=MAX_STATES).contains(&num_states) => Ok(num_states),
+        _ => Err(RuleError::parse(number, "invalid number of states")),
+    }
+}
+
+/// Splits a transition line into its inputs and output.
+///
+/// Transitions are separated by commas, or by whitespace. If neither is present, every character
+/// is a separate state or variable name.
+fn split_transition(line: &str) -> Vec<&str> {
+    if line.contains(',') {
+        line.split(',').map(str::trim).collect()
+    } else if line.contains(char::is_whitespace) {
+        line.split_whitespace().collect()
+    } else {
+        line.char_indices()
+            .map(|(i, c)| &line[i..i + c.len_utf8()])
+            .collect()
+    }
+}
+
+/// Parses the lines of a `@TABLE` section.
+fn parse_table(lines: &[(usize, &str)]) -> Result<(usize, Neighborhood, Tree), RuleError> {
+    let mut num_states = None;
+    let mut neighborhood = Neighborhood::Moore;
+    let mut symmetry = Symmetry::None;
+    let mut vars: Vec<(String, StateSet)> = vec![];
+    let mut transitions: Vec<(usize, Vec<Token>)> = vec![];
+
+    for &(number, line) in lines {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(value) = key_value(line, "n_states", ':') {
+            num_states = Some(parse_num_states(number, value)?);
+        } else if let Some(value) = key_value(line, "neighborhood", ':') {
+            neighborhood = match value {
+                "Moore" => Neighborhood::Moore,
+                "vonNeumann" => Neighborhood::VonNeumann,
+                _ => return Err(RuleError::parse(number, "unsupported neighborhood")),
+            };
+        } else if let Some(value) = key_value(line, "symmetries", ':') {
+            symmetry = match value {
+                "none" => Symmetry::None,
+                "rotate4" => Symmetry::Rotate4,
+                "rotate8" => Symmetry::Rotate8,
+                "reflect_horizontal" => Symmetry::ReflectHorizontal,
+                "rotate4reflect" => Symmetry::Rotate4Reflect,
+                "rotate8reflect" => Symmetry::Rotate8Reflect,
+                "permute" => Symmetry::Permute,
+                _ => return Err(RuleError::parse(number, "unsupported symmetries")),
+            };
+        } else if let Some(var) = line.strip_prefix("var ") {
+            let num_states =
+                num_states.ok_or_else(|| RuleError::parse(number, "n_states must come first"))?;
+            let mut parts = var.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let values = parts.next().unwrap_or("").trim();
+            if name.is_empty() || !values.starts_with('{') || !values.ends_with('}') {
+                return Err(RuleError::parse(number, "invalid variable"));
+            }
+
+            let mut set = StateSet::empty();
+            for value in values[1..values.len() - 1].split(',').map(str::trim) {
+                match parse_token(value, &vars, num_states) {
+                    Some(Token::State(state)) => set.insert(state),
+                    Some(Token::Var(index)) => set.union(vars[index].1),
+                    None => return Err(RuleError::parse(number, "invalid variable value")),
+                }
+            }
+            vars.push((name.to_owned(), set));
+        } else {
+            let num_states =
+                num_states.ok_or_else(|| RuleError::parse(number, "n_states must come first"))?;
+            let tokens = split_transition(line)
+                .into_iter()
+                .map(|token| parse_token(token, &vars, num_states))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| RuleError::parse(number, "invalid state or variable"))?;
+            if tokens.len() != neighborhood.size() + 2 {
+                return Err(RuleError::parse(number, "wrong number of states"));
+            }
+            transitions.push((number, tokens));
+        }
+    }
+
+    let num_states = num_states.ok_or_else(|| RuleError::parse(0, "missing n_states"))?;
+    let rotate8 = symmetry == Symmetry::Rotate8 || symmetry == Symmetry::Rotate8Reflect;
+    if rotate8 && neighborhood == Neighborhood::VonNeumann {
+        return Err(RuleError::parse(0, "unsupported symmetries"));
+    }
+
+    let permutations = symmetry.permutations(neighborhood);
+    let mut expanded = vec![];
+    for (number, tokens) in transitions {
+        for (inputs, output) in expand_variables(&tokens, &vars)
+            .ok_or_else(|| RuleError::parse(number, "unbound output variable"))?
+        {
+            let inputs = match &permutations {
+                Some(permutations) => expand_symmetries(inputs, permutations),
+                None => vec![inputs],
+            };
+            expanded.extend(inputs.into_iter().map(|inputs| {
+                // the center comes last, as in rule trees
+                let mut tree_inputs = neighborhood
+                    .tree_order()
+                    .iter()
+                    .map(|&i| inputs[1 + i])
+                    .collect::<Vec<_>>();
+                tree_inputs.push(inputs[0]);
+                Transition {
+                    inputs: tree_inputs,
+                    output,
+                    permute: permutations.is_none(),
+                }
+            }));
+        }
+    }
+
+    let tree = Compiler::compile(num_states, neighborhood.size() + 1, &expanded);
+    Ok((num_states, neighborhood, tree))
+}
+
+/// Parses a state or a previously defined variable name.
+fn parse_token(token: &str, vars: &[(String, StateSet)], num_states: usize) -> Option<Token> {
+    if let Some(index) = vars.iter().rposition(|(name, _)| name == token) {
+        return Some(Token::Var(index));
+    }
+    match token.parse::<usize>() {
+        Ok(state) if state < num_states => Some(Token::State(state as u8)),
+        _ => None,
+    }
+}
+
+/// Expands the bound variables of a transition.
+///
+/// A variable that occurs more than once in a transition takes the same value at each occurrence,
+/// so a separate transition is generated for each of its values. Variables occurring once match
+/// any of their values.
+///
+/// Returns `None` if the output is a variable that does not occur in the inputs.
+fn expand_variables(
+    tokens: &[Token],
+    vars: &[(String, StateSet)],
+) -> Option<Vec<(Vec<StateSet>, u8)>> {
+    let occurrences = |index: usize| {
+        tokens
+            .iter()
+            .filter(|token| match token {
+                Token::Var(other) => *other == index,
+                Token::State(_) => false,
+            })
+            .count()
+    };
+
+    let mut bound = vec![];
+    for token in tokens {
+        if let Token::Var(index) = *token {
+            if occurrences(index) > 1 && !bound.contains(&index) {
+                bound.push(index);
+            }
+        }
+    }
+
+    // every combination of values for the bound variables
+    let mut assignments: Vec<Vec<(usize, u8)>> = vec![vec![]];
+    for &index in &bound {
+        assignments = assignments
+            .into_iter()
+            .flat_map(|assignment| {
+                vars[index].1.states().into_iter().map(move |state| {
+                    let mut assignment = assignment.clone();
+                    assignment.push((index, state));
+                    assignment
+                })
+            })
+            .collect();
+    }
+
+    let (output, inputs) = tokens.split_last()?;
+    assignments
+        .into_iter()
+        .map(|assignment| {
+            let value = |index: usize| {
+                assignment
+                    .iter()
+                    .find(|&&(bound_index, _)| bound_index == index)
+                    .map(|&(_, state)| state)
+            };
+            let inputs = inputs
+                .iter()
+                .map(|token| match *token {
+                    Token::State(state) => StateSet::single(state),
+                    Token::Var(index) => {
+                        value(index).map(StateSet::single).unwrap_or(vars[index].1)
+                    }
+                })
+                .collect();
+            let output = match *output {
+                Token::State(state) => state,
+                Token::Var(index) => value(index)?,
+            };
+            Some((inputs, output))
+        })
+        .collect()
+}
+
+/// Applies the given permutations of the neighbor positions to the inputs of a transition,
+/// returning the distinct inputs in order.
+fn expand_symmetries(inputs: Vec<StateSet>, permutations: &[Vec<usize>]) -> Vec<Vec<StateSet>> {
+    let mut seen = HashSet::new();
+    let mut expanded = vec![];
+    for permutation in permutations {
+        let permuted = permutation.iter().map(|&i| inputs[i]).collect::<Vec<_>>();
+        if seen.insert(permuted.clone()) {
+            expanded.push(permuted);
+        }
+    }
+    expanded
+}
+
+/// Parses the lines of a `@TREE` section.
+fn parse_tree(lines: &[(usize, &str)]) -> Result<(usize, Neighborhood, Tree), RuleError> {
+    let mut num_states = None;
+    let mut neighborhood = None;
+    let mut num_nodes = None;
+    let mut children = vec![];
+    let mut levels = vec![];
+
+    for &(number, line) in lines {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(value) = key_value(line, "num_states", '=') {
+            num_states = Some(parse_num_states(number, value)?);
+        } else if let Some(value) = key_value(line, "num_neighbors", '=') {
+            neighborhood = match value {
+                "8" => Some(Neighborhood::Moore),
+                "4" => Some(Neighborhood::VonNeumann),
+                _ => return Err(RuleError::parse(number, "unsupported neighborhood")),
+            };
+        } else if let Some(value) = key_value(line, "num_nodes", '=') {
+            num_nodes = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| RuleError::parse(number, "invalid number of nodes"))?,
+            );
+        } else {
+            let (num_states, neighborhood) = match (num_states, neighborhood) {
+                (Some(num_states), Some(neighborhood)) => (num_states, neighborhood),
+                _ => {
+                    return Err(RuleError::parse(
+                        number,
+                        "num_states and num_neighbors must come first",
+                    ))
+                }
+            };
+
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| RuleError::parse(number, "invalid node"))?;
+            if values.len() != num_states + 1 {
+                return Err(RuleError::parse(number, "wrong number of children"));
+            }
+
+            let level = values[0];
+            let valid = if level == 1 {
+                values[1..].iter().all(|&state| state < num_states)
+            } else {
+                level >= 2
+                    && level <= neighborhood.size() + 1
+                    && values[1..]
+                        .iter()
+                        .all(|&child| child < levels.len() && levels[child] == level - 1)
+            };
+            if !valid {
+                return Err(RuleError::parse(number, "invalid node"));
+            }
+
+            levels.push(level);
+            children.extend(values[1..].iter().map(|&child| child as u32));
+        }
+    }
+
+    let (num_states, neighborhood) = match (num_states, neighborhood) {
+        (Some(num_states), Some(neighborhood)) => (num_states, neighborhood),
+        _ => return Err(RuleError::parse(0, "missing num_states or num_neighbors")),
+    };
+    if num_nodes != Some(levels.len()) {
+        return Err(RuleError::parse(0, "wrong number of nodes"));
+    }
+    if levels.last() != Some(&(neighborhood.size() + 1)) {
+        return Err(RuleError::parse(0, "root node has the wrong level"));
+    }
+
+    let tree = Tree {
+        num_states,
+        children,
+        root: levels.len() - 1,
+    };
+    Ok((num_states, neighborhood, tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rules::Universe, Position};
+
+    const LIFE_TABLE: &[u8] = b"@RULE Life
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={0,1}
+var c={0,1}
+var d={0,1}
+var e={0,1}
+var f={0,1}
+var g={0,1}
+var h={0,1}
+# birth
+0,1,1,1,0,0,0,0,0,1
+# survival
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+# death
+1,a,b,c,d,e,f,g,h,0
+";
+
+    // next state is the OR of a cell and its von Neumann neighbors
+    const OR_TREE: &[u8] = b"@RULE Or
+@TREE
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 1
+1 1 1
+2 0 1
+2 1 1
+3 2 3
+3 3 3
+4 4 5
+4 5 5
+5 6 7
+";
+
+    #[test]
+    fn from_file() {
+        let rule = Rule::from_file("./assets/wireworld.rule").unwrap();
+        assert_eq!(rule.name(), "WireWorld");
+        assert_eq!(rule.num_states(), 4);
+        assert_eq!(rule.neighborhood(), Neighborhood::Moore);
+
+        // electron heads become tails, and tails become conductors
+        assert_eq!(rule.next_state(1, &[3, 3, 3, 3, 3, 3, 3, 3]), 2);
+        assert_eq!(rule.next_state(2, &[1, 1, 1, 1, 1, 1, 1, 1]), 3);
+
+        // conductors become electron heads next to one or two electron heads
+        assert_eq!(rule.next_state(3, &[0, 0, 0, 0, 0, 1, 0, 0]), 1);
+        assert_eq!(rule.next_state(3, &[0, 1, 2, 0, 0, 1, 0, 3]), 1);
+        assert_eq!(rule.next_state(3, &[0, 1, 0, 1, 0, 1, 0, 0]), 3);
+        assert_eq!(rule.next_state(3, &[2, 2, 3, 3, 0, 0, 0, 0]), 3);
+
+        // empty cells stay empty
+        assert_eq!(rule.next_state(0, &[1, 1, 1, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn life_table() {
+        let rule = Rule::from_file_contents(LIFE_TABLE).unwrap();
+        for neighbors in 0..=255u8 {
+            let states = (0..8).map(|i| (neighbors >> i) & 1).collect::<Vec<_>>();
+            let count = neighbors.count_ones();
+            assert_eq!(rule.next_state(0, &states), u8::from(count == 3));
+            assert_eq!(
+                rule.next_state(1, &states),
+                u8::from(count == 2 || count == 3)
+            );
+        }
+    }
+
+    #[test]
+    fn bound_variables() {
+        // a cell takes the state of its northern neighbor if its southern neighbor matches
+        let rule = Rule::from_file_contents(
+            b"@RULE Bound
+@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:none
+var a={1,2}
+var b={0,1,2}
+var c={0,1,2}
+0,a,b,a,c,a
+",
+        )
+        .unwrap();
+        assert_eq!(rule.next_state(0, &[1, 0, 1, 0]), 1);
+        assert_eq!(rule.next_state(0, &[2, 0, 2, 1]), 2);
+        assert_eq!(rule.next_state(0, &[2, 0, 1, 0]), 0);
+    }
+
+    #[test]
+    fn symmetries() {
+        let rule = Rule::from_file_contents(
+            b"@RULE Rotate
+@TABLE
+n_states:3
+neighborhood:Moore
+symmetries:rotate4
+0,1,2,0,0,0,0,0,0,1
+",
+        )
+        .unwrap();
+        assert_eq!(rule.next_state(0, &[1, 2, 0, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(rule.next_state(0, &[0, 0, 1, 2, 0, 0, 0, 0]), 1);
+        assert_eq!(rule.next_state(0, &[0, 0, 0, 0, 0, 0, 1, 2]), 1);
+        // not a rotation by 90 degrees
+        assert_eq!(rule.next_state(0, &[0, 1, 2, 0, 0, 0, 0, 0]), 0);
+        // a reflection
+        assert_eq!(rule.next_state(0, &[1, 0, 0, 0, 0, 0, 0, 2]), 0);
+    }
+
+    #[test]
+    fn permute_distinct_neighbors() {
+        // a cell becomes 1 if its neighbors are a permutation of the states 0 to 7
+        let mut contents = b"@RULE Distinct\n@TABLE\nn_states:8\nneighborhood:Moore\n".to_vec();
+        contents.extend_from_slice(b"symmetries:permute\n0,0,1,2,3,4,5,6,7,1\n");
+        let rule = Rule::from_file_contents(&contents).unwrap();
+        assert_eq!(rule.next_state(0, &[0, 1, 2, 3, 4, 5, 6, 7]), 1);
+        assert_eq!(rule.next_state(0, &[7, 6, 5, 4, 3, 2, 1, 0]), 1);
+        assert_eq!(rule.next_state(0, &[3, 1, 4, 0, 5, 2, 6, 7]), 1);
+        assert_eq!(rule.next_state(0, &[3, 1, 4, 1, 5, 2, 6, 7]), 0);
+        assert_eq!(rule.next_state(2, &[0, 1, 2, 3, 4, 5, 6, 7]), 2);
+    }
+
+    #[test]
+    fn all_states() {
+        // a cell next to a cell in the last state becomes the state after its own
+        let mut contents = b"@RULE Max\n@TABLE\nn_states:256\nneighborhood:vonNeumann\n".to_vec();
+        contents.extend_from_slice(b"symmetries:rotate4\n0,255,0,0,0,1\n1,255,0,0,0,2\n");
+        let rule = Rule::from_file_contents(&contents).unwrap();
+        assert_eq!(rule.num_states(), 256);
+        assert_eq!(rule.next_state(0, &[0, 0, 255, 0]), 1);
+        assert_eq!(rule.next_state(1, &[0, 0, 0, 255]), 2);
+        assert_eq!(rule.next_state(255, &[0, 0, 0, 0]), 255);
+
+        let mut universe = Universe::new(rule);
+        universe.set_cell(Position::new(0, 0), 255);
+        universe.step();
+        assert_eq!(universe.get_cell(Position::new(0, 0)), 255);
+        assert_eq!(universe.get_cell(Position::new(0, 1)), 1);
+        assert_eq!(universe.get_cell(Position::new(-1, 0)), 1);
+        assert_eq!(universe.population(), 5);
+    }
+
+    #[test]
+    fn tree() {
+        let rule = Rule::from_file_contents(OR_TREE).unwrap();
+        assert_eq!(rule.name(), "Or");
+        assert_eq!(rule.neighborhood(), Neighborhood::VonNeumann);
+        assert_eq!(rule.next_state(0, &[0, 0, 0, 0]), 0);
+        assert_eq!(rule.next_state(1, &[0, 0, 0, 0]), 1);
+        for i in 0..4 {
+            let mut neighbors = [0; 4];
+            neighbors[i] = 1;
+            assert_eq!(rule.next_state(0, &neighbors), 1);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Rule::from_file_contents(b"@TABLE\nn_states:2\n").is_err());
+        assert!(Rule::from_file_contents(b"@RULE Empty\n").is_err());
+        assert!(Rule::from_file_contents(b"@RULE Bad\n@TABLE\nn_states:2\n0,1,2,0,0\n").is_err());
+        assert!(Rule::from_file_contents(b"@RULE Bad\n@TABLE\nn_states:2\n0,a,0,0,0,0\n").is_err());
+        assert!(Rule::from_file_contents(b"@RULE Bad\n@TREE\nnum_states=2\n").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+    node::{Level, NodeId, Store},
+    rules::table::Rule,
+    BoundingBox, Position,
+};
+
+const INITIAL_LEVEL: Level = Level(4);
+
+/// The largest step size log 2, since the root node is at most level 64 and must be two levels
+/// above the step size.
+const MAX_STEP_LOG_2: u8 = 62;
+
+/// A multi-state cellular automaton evolving according to a `Rule`.
+///
+/// The grid is stored in a `Store` of multi-state nodes, so it shares the memoized HashLife
+/// evolution of `Life`.
+///
+/// State 0 is the empty state. The rule must leave an empty cell with empty neighbors empty.
+#[derive(Clone, Debug)]
+pub struct Universe {
+    /// The root node of the grid.
+    root: NodeId,
+    /// The store, which holds the rule.
+    store: Store,
+    /// How many generations the grid has been advanced.
+    generation: u128,
+}
+
+impl Universe {
+    /// Creates a new empty universe evolving according to the given rule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rule turns an empty cell with empty neighbors into a non-empty cell.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), failure::Error> {
+    /// let rule = smeagol::rules::table::Rule::from_file("./assets/wireworld.rule")?;
+    /// let universe = smeagol::rules::Universe::new(rule);
+    /// assert_eq!(universe.population(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(rule: Rule) -> Self {
+        assert_eq!(rule.next_state(0, &[0; 8][..rule.neighborhood().size()]), 0);
+
+        let mut store = Store::with_rule(rule);
+        let root = store.create_empty(INITIAL_LEVEL);
+        Self {
+            root,
+            store,
+            generation: 0,
+        }
+    }
+
+    /// Returns the rule of the universe.
+    pub fn rule(&self) -> &Rule {
+        self.store.rule().unwrap()
+    }
+
+    /// Returns true if the position is inside the root node.
+    fn contains(&self, position: Position) -> bool {
+        let min = self.root.min_coord(&self.store);
+        let max = self.root.max_coord(&self.store);
+        position.x >= min && position.y >= min && position.x <= max && position.y <= max
+    }
+
+    /// Returns the state of the cell at the given position.
+    pub fn get_cell(&self, position: Position) -> u8 {
+        if self.contains(position) {
+            self.root.get_state(&self.store, position)
+        } else {
+            0
+        }
+    }
+
+    /// Sets the state of the cell at the given position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state is not a valid state of the rule.
+    pub fn set_cell(&mut self, position: Position, state: u8) {
+        assert!(usize::from(state) < self.rule().num_states());
+        while !self.contains(position) {
+            self.root = self.root.expand(&mut self.store);
+        }
+        self.root = self.root.set_state(&mut self.store, position, state);
+    }
+
+    /// Returns a list of the positions and states of the non-empty cells in the universe.
+    pub fn get_cells(&self) -> Vec<(Position, u8)> {
+        self.root.get_states(&self.store)
+    }
+
+    /// Returns a bounding box containing all the non-empty cells in the universe.
+    ///
+    /// Returns `None` if there are no non-empty cells.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.root.bounding_box(&self.store)
+    }
+
+    /// Returns the number of generations that have been advanced.
+    pub fn generation(&self) -> u128 {
+        self.generation
+    }
+
+    /// Returns the number of non-empty cells in the universe.
+    pub fn population(&self) -> u128 {
+        self.root.population(&self.store)
+    }
+
+    /// Returns the current step size.
+    ///
+    /// The default step size is 1.
+    pub fn step_size(&self) -> u64 {
+        1 << self.store.step_log_2()
+    }
+
+    /// Returns the step size log 2.
+    pub fn step_log_2(&self) -> u8 {
+        self.store.step_log_2()
+    }
+
+    /// Sets the step size to be `2^step_log_2`.
+    ///
+    /// Step sizes larger than `2^62` are reduced to `2^62`. This clears the cache of previously
+    /// computed steps.
+    pub fn set_step_log_2(&mut self, step_log_2: u8) {
+        self.store.set_step_log_2(step_log_2.min(MAX_STEP_LOG_2));
+    }
+
+    /// Returns true if the grid can be advanced into the future without the edges of the node
+    /// interfering.
+    fn is_padded(&self) -> bool {
+        let store = &self.store;
+        let root = self.root;
+        let level = root.level(store);
+        // the children of the quadrants must not be leaves
+        level.0 >= store.leaf_level().0 + 3
+            && store.step_log_2() <= level.0 - 2
+            && root.nw(store).population(store)
+                == root.nw(store).se(store).se(store).population(store)
+            && root.ne(store).population(store)
+                == root.ne(store).sw(store).sw(store).population(store)
+            && root.sw(store).population(store)
+                == root.sw(store).ne(store).ne(store).population(store)
+            && root.se(store).population(store)
+                == root.se(store).nw(store).nw(store).population(store)
+    }
+
+    /// Pads the grid such that it can be advanced into the future without the edges of the node
+    /// interfering.
+    fn pad(&mut self) {
+        while !self.is_padded() {
+            self.root = self.root.expand(&mut self.store);
+        }
+    }
+
+    /// Advances the universe into the future.
+    ///
+    /// The number of generations advanced is determined by the step size.
+    pub fn step(&mut self) {
+        self.pad();
+        self.root = self.root.step(&mut self.store);
+        self.generation += u128::from(self.step_size());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIFE_TABLE: &[u8] = b"@RULE Life
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={0,1}
+var c={0,1}
+var d={0,1}
+var e={0,1}
+var f={0,1}
+var g={0,1}
+var h={0,1}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+";
+
+    fn wireworld() -> Universe {
+        Universe::new(Rule::from_file("./assets/wireworld.rule").unwrap())
+    }
+
+    #[test]
+    fn get_set() {
+        let mut universe = wireworld();
+        let positions = [
+            Position::new(0, 0),
+            Position::new(-1, -1),
+            Position::new(1000, -3),
+            Position::new(-77, 12345),
+        ];
+        for (i, &pos) in positions.iter().enumerate() {
+            universe.set_cell(pos, (i % 3 + 1) as u8);
+        }
+        for (i, &pos) in positions.iter().enumerate() {
+            assert_eq!(universe.get_cell(pos), (i % 3 + 1) as u8);
+        }
+        assert_eq!(universe.population(), 4);
+
+        let mut cells = universe.get_cells();
+        cells.sort();
+        let mut expected = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &pos)| (pos, (i % 3 + 1) as u8))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(cells, expected);
+
+        universe.set_cell(Position::new(0, 0), 0);
+        assert_eq!(universe.population(), 3);
+        assert_eq!(universe.get_cell(Position::new(0, 0)), 0);
+    }
+
+    #[test]
+    fn wireworld_wire() {
+        let mut universe = wireworld();
+
+        // an electron travelling east along a straight wire
+        for x in 0..100 {
+            universe.set_cell(Position::new(x, 0), 3);
+        }
+        universe.set_cell(Position::new(0, 0), 2);
+        universe.set_cell(Position::new(1, 0), 1);
+
+        for _ in 0..16 {
+            universe.step();
+        }
+        assert_eq!(universe.get_cell(Position::new(16, 0)), 2);
+        assert_eq!(universe.get_cell(Position::new(17, 0)), 1);
+        assert_eq!(universe.population(), 100);
+
+        universe.set_step_log_2(5);
+        universe.step();
+        assert_eq!(universe.generation(), 48);
+        assert_eq!(universe.get_cell(Position::new(48, 0)), 2);
+        assert_eq!(universe.get_cell(Position::new(49, 0)), 1);
+        assert_eq!(
+            universe.bounding_box(),
+            Some(BoundingBox::new(Position::new(0, 0), Position::new(99, 0)))
+        );
+    }
+
+    #[test]
+    fn matches_life() {
+        let mut life = crate::Life::from_rle_pattern(
+            b"
+24bo11b$22bobo11b$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o14b$2o8b
+o3bob2o4bobo11b$10bo5bo7bo11b$11bo3bo20b$12b2o!",
+        )
+        .unwrap();
+
+        let mut universe = Universe::new(Rule::from_file_contents(LIFE_TABLE).unwrap());
+        for pos in life.get_alive_cells() {
+            universe.set_cell(pos, 1);
+        }
+
+        for &step_log_2 in &[0, 3, 6] {
+            life.set_step_log_2(step_log_2);
+            universe.set_step_log_2(step_log_2);
+            for _ in 0..3 {
+                life.step();
+                universe.step();
+
+                let mut cells = universe
+                    .get_cells()
+                    .into_iter()
+                    .map(|(pos, _)| pos)
+                    .collect::<Vec<_>>();
+                cells.sort();
+                let mut expected = life.get_alive_cells();
+                expected.sort();
+                assert_eq!(cells, expected);
+                assert_eq!(universe.generation(), life.generation());
+            }
+        }
+    }
+
+    #[test]
+    fn large_step_size() {
+        let mut universe = wireworld();
+        for x in 0..100 {
+            universe.set_cell(Position::new(x, 0), 3);
+        }
+        universe.set_cell(Position::new(0, 0), 2);
+        universe.set_cell(Position::new(1, 0), 1);
+
+        universe.set_step_log_2(u8::MAX);
+        assert_eq!(universe.step_log_2(), 62);
+        assert_eq!(universe.step_size(), 1 << 62);
+
+        // the electron has long reached the end of the wire
+        universe.step();
+        assert_eq!(universe.generation(), 1 << 62);
+        assert_eq!(universe.population(), 100);
+        assert!((0..100).all(|x| universe.get_cell(Position::new(x, 0)) == 3));
+    }
+}
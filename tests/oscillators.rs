@@ -64,3 +64,19 @@ fn queen_bee_shuttle() {
     let mut life = smeagol::Life::from_rle_file("./assets/queenbeeshuttle.rle").unwrap();
     oscillate(&mut life, 30);
 }
+
+#[test]
+fn detect_period() {
+    for &(path, period) in &[
+        ("./assets/figureeight.rle", 8),
+        ("./assets/pentadecathlon.rle", 15),
+        ("./assets/pulsar.rle", 3),
+        ("./assets/queenbeeshuttle.rle", 30),
+    ] {
+        let life = smeagol::Life::from_rle_file(path).unwrap();
+        let periodicity = life.detect_period(100).unwrap();
+        assert_eq!(periodicity.period, period, "{}", path);
+        assert!(periodicity.is_stationary());
+        assert_eq!(periodicity.start, 0);
+    }
+}
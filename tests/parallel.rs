@@ -0,0 +1,50 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use smeagol::{Life, Topology};
+
+fn matches_sequential(life: Life, step_log_2: u8, steps: usize) {
+    let mut sequential = life.clone();
+    let mut parallel = life;
+    sequential.set_step_log_2(step_log_2);
+    parallel.set_step_log_2(step_log_2);
+    parallel.set_parallel(true);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    for _ in 0..steps {
+        sequential.step();
+        pool.install(|| parallel.step());
+        assert_eq!(parallel.generation(), sequential.generation());
+        assert_eq!(parallel.population(), sequential.population());
+        assert_eq!(parallel.get_alive_cells(), sequential.get_alive_cells());
+    }
+}
+
+#[test]
+fn breeder() {
+    let life = Life::from_rle_file("./assets/breeder1.rle").unwrap();
+    matches_sequential(life, 4, 2);
+}
+
+#[test]
+fn spaghetti_monster() {
+    let life = Life::from_rle_file("./assets/spaghettimonster.rle").unwrap();
+    matches_sequential(life, 8, 8);
+}
+
+#[test]
+fn torus() {
+    let mut life = Life::from_rle_file("./assets/gosperglidergun.rle").unwrap();
+    life.set_topology(Topology::Torus {
+        width: 1000,
+        height: 1000,
+    })
+    .unwrap();
+    matches_sequential(life, 5, 8);
+}
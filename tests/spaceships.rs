@@ -93,3 +93,33 @@ fn weekender() {
     let mut life = smeagol::Life::from_rle_file("./assets/weekender.rle").unwrap();
     fly(&mut life, (0, 7), (-2, 7), 7);
 }
+
+#[test]
+fn detect_spaceship() {
+    for &(path, speed, direction) in &[
+        ("./assets/glider.rle", "c/4", smeagol::Direction::Diagonal),
+        (
+            "./assets/sirrobin.rle",
+            "(2,1)c/6",
+            smeagol::Direction::Oblique,
+        ),
+        (
+            "./assets/spaghettimonster.rle",
+            "3c/7",
+            smeagol::Direction::Orthogonal,
+        ),
+        (
+            "./assets/weekender.rle",
+            "2c/7",
+            smeagol::Direction::Orthogonal,
+        ),
+    ] {
+        let life = smeagol::Life::from_rle_file(path).unwrap();
+        let spaceship = life.detect_spaceship(100).unwrap();
+        assert_eq!(spaceship.speed(), speed, "{}", path);
+        assert_eq!(spaceship.direction(), direction, "{}", path);
+    }
+
+    let pulsar = smeagol::Life::from_rle_file("./assets/pulsar.rle").unwrap();
+    assert_eq!(pulsar.detect_spaceship(100), None);
+}
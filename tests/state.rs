@@ -0,0 +1,39 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use smeagol::Life;
+
+#[test]
+fn resume_with_warm_cache() {
+    // the pulsar has period 3, so every phase has already been evolved once
+    let mut life = Life::from_rle_file("./assets/pulsar.rle").unwrap();
+    for _ in 0..3 {
+        life.step();
+    }
+
+    let path = std::env::temp_dir().join(format!("smeagol-state-{}.bin", std::process::id()));
+    life.save_state(&path).unwrap();
+    let mut resumed = Life::load_state(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut cold = Life::new();
+    for position in life.get_alive_cells() {
+        cold.set_cell_alive(position);
+    }
+
+    for _ in 0..3 {
+        life.step();
+        resumed.step();
+        cold.step();
+    }
+    assert_eq!(resumed.generation(), life.generation());
+    assert_eq!(resumed.get_alive_cells(), life.get_alive_cells());
+    assert_eq!(cold.get_alive_cells(), life.get_alive_cells());
+
+    assert_eq!(resumed.stats().step_hits, 3);
+    assert_eq!(resumed.stats().step_misses, 0);
+    assert!(cold.stats().step_misses > 0);
+}
@@ -0,0 +1,118 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public License,
+ * v. 2.0. If a copy of the MPL was not distributed with this file, You can
+ * obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use smeagol::{Life, Position, Topology, Twist};
+
+const WIDTH: u32 = 20;
+const HEIGHT: u32 = 13;
+const GENERATIONS: u128 = 48;
+
+/// Returns a pseudo-random soup filling the grid.
+fn soup() -> Vec<Position> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut cells = vec![];
+    for y in 0..i64::from(HEIGHT) {
+        for x in 0..i64::from(WIDTH) {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            if state >> 62 == 0 {
+                cells.push(Position::new(x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// Advances the grid by one generation, looking up neighbors through the topology.
+fn reference_step(topology: Topology, cells: &[Position]) -> Vec<Position> {
+    let width = i64::from(WIDTH);
+    let height = i64::from(HEIGHT);
+    let alive = |pos: Position| topology.wrap(pos).is_some_and(|pos| cells.contains(&pos));
+
+    let mut next = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Position::new(x, y);
+            let mut neighbors = 0;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx != 0 || dy != 0) && alive(pos.offset(dx, dy)) {
+                        neighbors += 1;
+                    }
+                }
+            }
+            if neighbors == 3 || (neighbors == 2 && alive(pos)) {
+                next.push(pos);
+            }
+        }
+    }
+    next
+}
+
+fn sorted(mut cells: Vec<Position>) -> Vec<Position> {
+    cells.sort_by_key(|pos| (pos.y, pos.x));
+    cells
+}
+
+fn matches_reference(topology: Topology) {
+    let mut expected = soup();
+    let mut history = vec![expected.clone()];
+    for _ in 0..GENERATIONS {
+        expected = reference_step(topology, &expected);
+        history.push(expected.clone());
+    }
+
+    for &step_log_2 in &[0, 2, 4] {
+        let mut life = Life::new();
+        life.set_topology(topology).unwrap();
+        for &pos in &history[0] {
+            life.set_cell_alive(pos);
+        }
+        life.set_step_log_2(step_log_2);
+
+        while life.generation() < GENERATIONS {
+            life.step();
+            assert_eq!(
+                sorted(life.get_alive_cells()),
+                history[life.generation() as usize],
+                "generation {} with step size 2^{}",
+                life.generation(),
+                step_log_2
+            );
+        }
+    }
+}
+
+#[test]
+fn bounded_plane() {
+    matches_reference(Topology::BoundedPlane {
+        width: WIDTH,
+        height: HEIGHT,
+    });
+}
+
+#[test]
+fn torus() {
+    matches_reference(Topology::Torus {
+        width: WIDTH,
+        height: HEIGHT,
+    });
+}
+
+#[test]
+fn klein_bottle() {
+    matches_reference(Topology::KleinBottle {
+        width: WIDTH,
+        height: HEIGHT,
+        twist: Twist::Horizontal,
+    });
+    matches_reference(Topology::KleinBottle {
+        width: WIDTH,
+        height: HEIGHT,
+        twist: Twist::Vertical,
+    });
+}